@@ -0,0 +1,236 @@
+use std::fmt;
+use std::sync::Arc;
+
+use chrono::{DateTime, FixedOffset, NaiveTime, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+use serde::{de, Deserialize, Deserializer};
+
+use crate::workload::{RateSchedule, WorkloadPhase};
+
+/// Timezone of the wall-clock a workload is anchored to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AnchorTimezone {
+    /// Coordinated Universal Time.
+    Utc,
+
+    /// Fixed offset from UTC (ex. `+02:00`), ignoring daylight saving time.
+    Offset(FixedOffset),
+
+    /// Timezone of the [IANA database](https://www.iana.org/time-zones) (ex. `Europe/London`),
+    /// following its daylight saving time.
+    Named(Tz),
+}
+
+impl AnchorTimezone {
+    /// Implementation of [`clap::value_parser`], used to create an argument by parsing a user-provided value.
+    ///
+    /// The `&str` provided must be one of:
+    ///
+    /// * `UTC`
+    /// * a UTC offset, in format `+HH:MM` or `-HH:MM`
+    /// * the name of a timezone of the IANA database (ex. `America/New_York`)
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn clap_parser(tz_as_str: &str) -> Result<AnchorTimezone, String> {
+        if tz_as_str.eq_ignore_ascii_case("utc") {
+            return Ok(AnchorTimezone::Utc);
+        }
+
+        if tz_as_str.starts_with(['+', '-']) {
+            return match tz_as_str.parse::<FixedOffset>() {
+                Err(e) => Err(format!("Failed to parse UTC offset '{tz_as_str}' (expected '+HH:MM' or '-HH:MM'): {e}")),
+                Ok(offset) => Ok(AnchorTimezone::Offset(offset)),
+            };
+        }
+
+        match tz_as_str.parse::<Tz>() {
+            Err(_) => Err(format!("Unsupported timezone '{tz_as_str}'")),
+            Ok(tz) => Ok(AnchorTimezone::Named(tz)),
+        }
+    }
+
+    /// Seconds elapsed on the wall-clock of this timezone, from `1970-01-01 00:00:00` until `now`.
+    ///
+    /// Unlike a UNIX timestamp, this counts the seconds that a local clock displays: with daylight saving time,
+    /// it jumps forward or back by an hour, like the clock does.
+    fn wall_clock_sec(&self, now: DateTime<Utc>) -> i64 {
+        fn local<T: TimeZone>(now: DateTime<Utc>, tz: &T) -> i64 {
+            now.with_timezone(tz).naive_local().and_utc().timestamp()
+        }
+
+        match self {
+            AnchorTimezone::Utc => local(now, &Utc),
+            AnchorTimezone::Offset(offset) => local(now, offset),
+            AnchorTimezone::Named(tz) => local(now, tz),
+        }
+    }
+}
+
+impl fmt::Display for AnchorTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnchorTimezone::Utc => write!(f, "UTC"),
+            AnchorTimezone::Offset(offset) => write!(f, "{offset}"),
+            AnchorTimezone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+/// An [`AnchorTimezone`] is deserialized from the same format parsed by [`AnchorTimezone::clap_parser`].
+impl<'de> Deserialize<'de> for AnchorTimezone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tz_as_str = String::deserialize(deserializer)?;
+
+        AnchorTimezone::clap_parser(&tz_as_str).map_err(de::Error::custom)
+    }
+}
+
+/// Time of the day that second `0` of the workload is anchored to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct AnchorTime(NaiveTime);
+
+impl AnchorTime {
+    /// Implementation of [`clap::value_parser`], used to create an argument by parsing a user-provided value.
+    ///
+    /// The `&str` provided must be of format `HH:MM[:SS]`, with hours in `[0..23]`.
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn clap_parser(time_as_str: &str) -> Result<AnchorTime, String> {
+        NaiveTime::parse_from_str(time_as_str, "%H:%M:%S")
+            .or_else(|_| NaiveTime::parse_from_str(time_as_str, "%H:%M"))
+            .map(AnchorTime)
+            .map_err(|e| format!("Failed to parse time '{time_as_str}' (expected 'HH:MM[:SS]'): {e}"))
+    }
+
+    fn sec_of_day(&self) -> i64 {
+        self.0.num_seconds_from_midnight() as i64
+    }
+}
+
+impl fmt::Display for AnchorTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format("%H:%M:%S"))
+    }
+}
+
+/// An [`AnchorTime`] is deserialized from the same format parsed by [`AnchorTime::clap_parser`].
+impl<'de> Deserialize<'de> for AnchorTime {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let time_as_str = String::deserialize(deserializer)?;
+
+        AnchorTime::clap_parser(&time_as_str).map_err(de::Error::custom)
+    }
+}
+
+/// A [`RateSchedule`] anchored to the wall-clock: its second `0` falls at an [`AnchorTime`] of the day,
+/// in an [`AnchorTimezone`].
+///
+/// When created, it works out how far into its cycle the wall-clock is, and starts from there: this way,
+/// a restarted instance resumes the schedule where it's meant to be, instead of starting it over.
+/// For cycles of 24 hours (or a divisor of it), second `0` falls at the anchor time every day.
+#[derive(Debug)]
+pub struct AnchoredSchedule {
+    schedule: Arc<dyn RateSchedule>,
+    start_sec: u64,
+}
+
+impl AnchoredSchedule {
+    pub fn new(
+        schedule: Arc<dyn RateSchedule>,
+        tz: AnchorTimezone,
+        time: AnchorTime,
+        now: DateTime<Utc>,
+    ) -> AnchoredSchedule {
+        let cycle_sec = schedule.overall_duration_sec() as i64;
+        let start_sec = (tz.wall_clock_sec(now) - time.sec_of_day()).rem_euclid(cycle_sec) as u64;
+
+        AnchoredSchedule {
+            schedule,
+            start_sec,
+        }
+    }
+
+    /// Second of the cycle of the anchored schedule, that the run starts from.
+    pub fn start_sec(&self) -> u64 {
+        self.start_sec
+    }
+}
+
+impl RateSchedule for AnchoredSchedule {
+    fn records_per_sec_at(&self, sec: u64) -> f64 {
+        self.schedule.records_per_sec_at(self.start_sec + sec)
+    }
+
+    fn phase_at(&self, sec: u64) -> WorkloadPhase {
+        self.schedule.phase_at(self.start_sec + sec)
+    }
+
+    fn max_records_per_sec(&self) -> f64 {
+        self.schedule.max_records_per_sec()
+    }
+
+    fn overall_duration_sec(&self) -> u32 {
+        self.schedule.overall_duration_sec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transition::Transition;
+    use crate::workload::{Workload, WorkloadSegment};
+
+    use super::*;
+
+    #[test]
+    fn test_clap_parsers() {
+        assert_eq!(Ok(AnchorTimezone::Utc), AnchorTimezone::clap_parser("UTC"));
+        assert_eq!("+02:00", AnchorTimezone::clap_parser("+02:00").unwrap().to_string());
+        assert_eq!("-05:30", AnchorTimezone::clap_parser("-05:30").unwrap().to_string());
+        assert_eq!(Ok(AnchorTimezone::Named(Tz::Europe__Rome)), AnchorTimezone::clap_parser("Europe/Rome"));
+        assert_eq!(Err("Unsupported timezone 'Mars/Olympus'".to_string()), AnchorTimezone::clap_parser("Mars/Olympus"));
+        assert!(AnchorTimezone::clap_parser("+25:00").is_err());
+
+        assert_eq!("09:30:00", AnchorTime::clap_parser("09:30").unwrap().to_string());
+        assert_eq!("23:59:59", AnchorTime::clap_parser("23:59:59").unwrap().to_string());
+        assert_eq!("00:00:00", AnchorTime::default().to_string());
+        assert!(AnchorTime::clap_parser("24:00").is_err());
+    }
+
+    #[test]
+    fn test_anchored() {
+        // 24 hours: quiet for the night, busy during the day
+        let day: Arc<dyn RateSchedule> = Arc::new(Workload::from_segments(vec![
+            WorkloadSegment::new(1.0, 9 * 3600, Transition::None),
+            WorkloadSegment::new(100.0, 12 * 3600, Transition::None),
+            WorkloadSegment::new(1.0, 3 * 3600, Transition::None),
+        ]));
+
+        // 10:00 in UTC
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap();
+        let anchored = AnchoredSchedule::new(day.clone(), AnchorTimezone::Utc, AnchorTime::default(), now);
+        assert_eq!(10 * 3600, anchored.start_sec());
+        assert_eq!(100.0, anchored.records_per_sec_at(0));
+        assert_eq!(1.0, anchored.records_per_sec_at(11 * 3600));
+
+        // 10:00 in UTC is 05:00 in New York, 06:00 with an anchor at 23:00
+        let ny = AnchorTimezone::clap_parser("America/New_York").unwrap();
+        let anchored = AnchoredSchedule::new(day.clone(), ny, AnchorTime::clap_parser("23:00").unwrap(), now);
+        assert_eq!(6 * 3600, anchored.start_sec());
+        assert_eq!(1.0, anchored.records_per_sec_at(0));
+
+        // Daylight saving time moves the anchor along with the wall-clock, fixed offsets don't
+        let summer = Utc.with_ymd_and_hms(2024, 7, 1, 10, 0, 0).unwrap();
+        let anchored = AnchoredSchedule::new(day.clone(), ny, AnchorTime::default(), summer);
+        assert_eq!(6 * 3600, anchored.start_sec());
+        let offset = AnchorTimezone::clap_parser("-05:00").unwrap();
+        let anchored = AnchoredSchedule::new(day.clone(), offset, AnchorTime::default(), summer);
+        assert_eq!(5 * 3600, anchored.start_sec());
+
+        // Cycles shorter than a day repeat from the anchor
+        let hour: Arc<dyn RateSchedule> =
+            Arc::new(Workload::from_segments(vec![WorkloadSegment::new(1.0, 3600, Transition::None)]));
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 10, 20, 30).unwrap();
+        let anchored = AnchoredSchedule::new(hour, AnchorTimezone::Utc, AnchorTime::clap_parser("00:15").unwrap(), now);
+        assert_eq!(5 * 60 + 30, anchored.start_sec());
+    }
+}
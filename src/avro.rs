@@ -0,0 +1,413 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rand::distributions::{Alphanumeric, DistString};
+use rand::Rng;
+use serde_json::{Map, Value};
+
+use crate::random::{uuid_v4, DATE_TIME_RANGE_SEC, DEFAULT_MAX_ITEMS, DEFAULT_MAX_LENGTH};
+use crate::schema_registry::{wire_format_prefix, write_varint};
+
+/// An [Avro](https://avro.apache.org/) schema, loaded from an `.avsc` file, to generate random datums from.
+///
+/// The schema is loaded and validated once, via [`AvroSchema::load`], and then sampled for every record
+/// via [`AvroSchema::sample`], encoded in Avro binary format. Once the schema is registered in a Schema Registry
+/// (see [`AvroSchema::with_schema_id`]), datums are prefixed as per the Confluent wire format.
+///
+/// All types of the specification are supported, including named types referenced by name, as long as
+/// they are not recursive. Logical types `uuid`, `date`, `time-*` and `timestamp-*` produce realistic values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AvroSchema {
+    /// Path to the file the schema was loaded from
+    path: PathBuf,
+
+    /// The schema, as found in the file
+    source: String,
+
+    /// The schema, compiled to what's needed to generate datums
+    root: AvroType,
+
+    /// ID of the schema in the Schema Registry, if registered
+    schema_id: Option<u32>,
+}
+
+/// What to generate, as described by (a part of) an [`AvroSchema`].
+#[derive(Debug, Clone, PartialEq)]
+enum AvroType {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+
+    /// A `string` with logical type `uuid`.
+    Uuid,
+
+    /// An `int` with logical type `date`.
+    Date,
+
+    /// An `int` with logical type `time-millis`.
+    TimeMillis,
+
+    /// A `long` with logical type `time-micros`.
+    TimeMicros,
+
+    /// A `long` with logical type `timestamp-millis` (or `local-timestamp-millis`).
+    TimestampMillis,
+
+    /// A `long` with logical type `timestamp-micros` (or `local-timestamp-micros`).
+    TimestampMicros,
+
+    /// A `fixed` of the given size.
+    Fixed(usize),
+
+    /// An `enum` with the given amount of symbols.
+    Enum(usize),
+
+    Array(Box<AvroType>),
+    Map(Box<AvroType>),
+    Union(Vec<AvroType>),
+
+    /// A `record` with the given fields, in order.
+    Record(Vec<AvroType>),
+}
+
+impl AvroSchema {
+    /// Loads the Avro schema from the file at `path`, failing if it can't read or parse it.
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn load(path: &Path) -> Result<AvroSchema, String> {
+        let source =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read INPUT file '{}': {e}", path.display()))?;
+
+        AvroSchema::parse(path, source.trim())
+    }
+
+    fn parse(path: &Path, source: &str) -> Result<AvroSchema, String> {
+        let schema_as_json: Value = serde_json::from_str(source)
+            .map_err(|e| format!("Failed to parse INPUT file '{}' as JSON: {e}", path.display()))?;
+
+        let mut compiler = Compiler {
+            named: HashMap::new(),
+        };
+        let root = compiler.compile(&schema_as_json, None).map_err(|e| format!("Invalid Avro schema: {e}"))?;
+
+        Ok(AvroSchema {
+            path: path.to_path_buf(),
+            source: source.to_string(),
+            root,
+            schema_id: None,
+        })
+    }
+
+    /// The schema, as found in the file it was loaded from.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns this schema, registered in the Schema Registry with the given `schema_id`.
+    pub fn with_schema_id(self, schema_id: u32) -> AvroSchema {
+        AvroSchema {
+            schema_id: Some(schema_id),
+            ..self
+        }
+    }
+
+    /// Samples a datum, valid against the schema, drawing random values from the given `rng`.
+    ///
+    /// The datum is encoded in Avro binary format: if the schema is registered, prefixed as per the Confluent
+    /// wire format (i.e. magic byte `0`, followed by the 4 bytes of the schema ID).
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Vec<u8> {
+        let mut datum = Vec::new();
+
+        if let Some(id) = self.schema_id {
+            datum.extend_from_slice(&wire_format_prefix(id));
+        }
+        self.root.sample(rng, &mut datum);
+
+        datum
+    }
+}
+
+/// Compiles (parts of) an Avro schema into [`AvroType`]s.
+struct Compiler {
+    /// Named types (`record`, `enum` and `fixed`) by full name: `None` while being compiled.
+    named: HashMap<String, Option<AvroType>>,
+}
+
+impl Compiler {
+    /// Compiles the `schema`, found within the given `namespace` (if any).
+    fn compile(&mut self, schema: &Value, namespace: Option<&str>) -> Result<AvroType, String> {
+        match schema {
+            Value::String(name) => self.compile_name(name, namespace),
+            Value::Array(branches) if !branches.is_empty() => {
+                branches.iter().map(|b| self.compile(b, namespace)).collect::<Result<_, _>>().map(AvroType::Union)
+            },
+            Value::Object(schema) => match schema.get("type") {
+                Some(Value::String(t)) => match t.as_str() {
+                    "record" | "error" | "enum" | "fixed" => self.compile_named(t, schema, namespace),
+                    "array" => Ok(AvroType::Array(Box::new(self.compile_child(schema, "items", namespace)?))),
+                    "map" => Ok(AvroType::Map(Box::new(self.compile_child(schema, "values", namespace)?))),
+                    _ => Ok(compile_logical(schema.get("logicalType"), self.compile_name(t, namespace)?)),
+                },
+                Some(t) => self.compile(t, namespace),
+                None => Err("'type' is missing".to_string()),
+            },
+            _ => Err(format!("{schema} is not a schema")),
+        }
+    }
+
+    fn compile_child(&mut self, schema: &Map<String, Value>, key: &str, ns: Option<&str>) -> Result<AvroType, String> {
+        match schema.get(key) {
+            Some(child) => self.compile(child, ns),
+            None => Err(format!("'{key}' is missing")),
+        }
+    }
+
+    /// Compiles a primitive type, or a reference to a named type.
+    fn compile_name(&self, name: &str, namespace: Option<&str>) -> Result<AvroType, String> {
+        let primitive = match name {
+            "null" => AvroType::Null,
+            "boolean" => AvroType::Boolean,
+            "int" => AvroType::Int,
+            "long" => AvroType::Long,
+            "float" => AvroType::Float,
+            "double" => AvroType::Double,
+            "bytes" => AvroType::Bytes,
+            "string" => AvroType::String,
+            _ => {
+                let full_name = full_name(name, namespace);
+                return match self.named.get(&full_name).or_else(|| self.named.get(name)) {
+                    Some(Some(named)) => Ok(named.clone()),
+                    Some(None) => Err(format!("type '{full_name}' is recursive, which is not supported")),
+                    None => Err(format!("type '{name}' is unknown")),
+                };
+            },
+        };
+
+        Ok(primitive)
+    }
+
+    /// Compiles a named type: `record` (or `error`), `enum` or `fixed`.
+    fn compile_named(&mut self, t: &str, schema: &Map<String, Value>, ns: Option<&str>) -> Result<AvroType, String> {
+        let Some(name) = schema.get("name").and_then(Value::as_str) else {
+            return Err(format!("'{t}' must have a 'name'"));
+        };
+        let namespace = schema.get("namespace").and_then(Value::as_str).or(ns);
+        let full_name = full_name(name, namespace);
+        // Names within a named type are relative to its namespace, which might be part of its name
+        let namespace = full_name.rsplit_once('.').map(|(ns, _)| ns.to_string());
+
+        if self.named.insert(full_name.clone(), None).is_some() {
+            return Err(format!("type '{full_name}' is defined more than once"));
+        }
+
+        let named = match t {
+            "enum" => match schema.get("symbols").and_then(Value::as_array) {
+                Some(symbols) if !symbols.is_empty() => AvroType::Enum(symbols.len()),
+                _ => return Err(format!("'enum' '{full_name}' must have non-empty 'symbols'")),
+            },
+            "fixed" => match schema.get("size").and_then(Value::as_u64).and_then(|s| usize::try_from(s).ok()) {
+                Some(size) => AvroType::Fixed(size),
+                None => return Err(format!("'fixed' '{full_name}' must have a non-negative 'size'")),
+            },
+            _ => {
+                let Some(fields) = schema.get("fields").and_then(Value::as_array) else {
+                    return Err(format!("'record' '{full_name}' must have 'fields'"));
+                };
+                let fields = fields
+                    .iter()
+                    .map(|f| {
+                        let field_name = f.get("name").and_then(Value::as_str).unwrap_or_default();
+                        match f.get("type") {
+                            Some(field_type) => self.compile(field_type, namespace.as_deref()),
+                            None => Err("'type' is missing".to_string()),
+                        }
+                        .map_err(|e| format!("field '{field_name}' of '{full_name}': {e}"))
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                AvroType::Record(fields)
+            },
+        };
+
+        self.named.insert(full_name, Some(named.clone()));
+        Ok(named)
+    }
+}
+
+/// The full name of the named type `name`, defined (or referenced) within the given `namespace`.
+fn full_name(name: &str, namespace: Option<&str>) -> String {
+    match namespace {
+        Some(ns) if !name.contains('.') && !ns.is_empty() => format!("{ns}.{name}"),
+        _ => name.to_string(),
+    }
+}
+
+/// Applies the `logical_type` (if any) to the `primitive` type: unknown logical types are ignored, as per specification.
+fn compile_logical(logical_type: Option<&Value>, primitive: AvroType) -> AvroType {
+    match (logical_type.and_then(Value::as_str).unwrap_or_default(), &primitive) {
+        ("uuid", AvroType::String) => AvroType::Uuid,
+        ("date", AvroType::Int) => AvroType::Date,
+        ("time-millis", AvroType::Int) => AvroType::TimeMillis,
+        ("time-micros", AvroType::Long) => AvroType::TimeMicros,
+        ("timestamp-millis" | "local-timestamp-millis", AvroType::Long) => AvroType::TimestampMillis,
+        ("timestamp-micros" | "local-timestamp-micros", AvroType::Long) => AvroType::TimestampMicros,
+        _ => primitive,
+    }
+}
+
+impl AvroType {
+    /// Samples a random value of this type, appending it to `datum`, encoded in Avro binary format.
+    fn sample<R: Rng>(&self, rng: &mut R, datum: &mut Vec<u8>) {
+        let (min_sec, max_sec) = DATE_TIME_RANGE_SEC;
+
+        match self {
+            AvroType::Null => {},
+            AvroType::Boolean => datum.push(rng.gen::<bool>() as u8),
+            AvroType::Int => write_varint(datum, rng.gen::<i32>() as i64),
+            AvroType::Long => write_varint(datum, rng.gen()),
+            AvroType::Float => datum.extend_from_slice(&rng.gen::<f32>().to_le_bytes()),
+            AvroType::Double => datum.extend_from_slice(&rng.gen::<f64>().to_le_bytes()),
+            AvroType::Bytes => {
+                let bytes: Vec<u8> = (0..rng.gen_range(0..=DEFAULT_MAX_LENGTH)).map(|_| rng.gen()).collect();
+                write_bytes(datum, &bytes);
+            },
+            AvroType::String => {
+                let length = rng.gen_range(0..=DEFAULT_MAX_LENGTH);
+                write_bytes(datum, Alphanumeric.sample_string(rng, length).as_bytes());
+            },
+            AvroType::Uuid => write_bytes(datum, uuid_v4(rng).as_bytes()),
+            AvroType::Date => write_varint(datum, rng.gen_range(min_sec / 86_400..max_sec / 86_400)),
+            AvroType::TimeMillis => write_varint(datum, rng.gen_range(0..86_400_000)),
+            AvroType::TimeMicros => write_varint(datum, rng.gen_range(0..86_400_000_000)),
+            AvroType::TimestampMillis => write_varint(datum, rng.gen_range(min_sec * 1_000..max_sec * 1_000)),
+            AvroType::TimestampMicros => write_varint(datum, rng.gen_range(min_sec * 1_000_000..max_sec * 1_000_000)),
+            AvroType::Fixed(size) => datum.extend((0..*size).map(|_| rng.gen::<u8>())),
+            AvroType::Enum(symbols) => write_varint(datum, rng.gen_range(0..*symbols) as i64),
+            AvroType::Array(items) => {
+                // Arrays (and maps) are encoded as blocks: here, at most 1 block followed by the empty one
+                let length = rng.gen_range(0..=DEFAULT_MAX_ITEMS);
+                if length > 0 {
+                    write_varint(datum, length as i64);
+                    (0..length).for_each(|_| items.sample(rng, datum));
+                }
+                write_varint(datum, 0);
+            },
+            AvroType::Map(values) => {
+                let length = rng.gen_range(0..=DEFAULT_MAX_ITEMS);
+                if length > 0 {
+                    write_varint(datum, length as i64);
+                    for _ in 0..length {
+                        let key_length = rng.gen_range(1..=DEFAULT_MAX_LENGTH);
+                        write_bytes(datum, Alphanumeric.sample_string(rng, key_length).as_bytes());
+                        values.sample(rng, datum);
+                    }
+                }
+                write_varint(datum, 0);
+            },
+            AvroType::Union(branches) => {
+                let idx = rng.gen_range(0..branches.len());
+                write_varint(datum, idx as i64);
+                branches[idx].sample(rng, datum);
+            },
+            AvroType::Record(fields) => fields.iter().for_each(|f| f.sample(rng, datum)),
+        }
+    }
+}
+
+/// Appends to `datum` the Avro binary encoding of `bytes` (or a `string`): length, followed by the bytes.
+fn write_bytes(datum: &mut Vec<u8>, bytes: &[u8]) {
+    write_varint(datum, bytes.len() as i64);
+    datum.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn parse(source: &str) -> Result<AvroSchema, String> {
+        AvroSchema::parse(Path::new("schema.avsc"), source)
+    }
+
+    #[test]
+    fn test_sample() {
+        let schema = parse(
+            r#"{
+                "type": "record", "name": "Event", "namespace": "com.example",
+                "fields": [
+                    { "name": "none", "type": "null" },
+                    { "name": "kind", "type": { "type": "enum", "name": "Kind", "symbols": ["ONLY"] } },
+                    { "name": "hash", "type": { "type": "fixed", "name": "Hash", "size": 2 } },
+                    { "name": "same", "type": "com.example.Kind" },
+                    { "name": "id", "type": { "type": "string", "logicalType": "uuid" } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let datum = schema.sample(&mut rng);
+        assert_eq!(0, datum[0]);
+        assert_eq!(0, datum[3]);
+        assert_eq!(72, datum[4]);
+        assert_eq!(36, String::from_utf8(datum[5..].to_vec()).unwrap().len());
+
+        // Once registered, in wire format
+        let datum = schema.with_schema_id(258).sample(&mut rng);
+        assert_eq!(vec![0, 0, 0, 1, 2, 0], datum[..6]);
+        assert_eq!(5 + 41, datum.len());
+    }
+
+    #[test]
+    fn test_sample_all_types() {
+        let schema = parse(
+            r#"{
+                "type": "record", "name": "All",
+                "fields": [
+                    { "name": "a", "type": ["null", "boolean", "int", "long", "float", "double", "bytes", "string"] },
+                    { "name": "b", "type": { "type": "array", "items": { "type": "map", "values": "long" } } },
+                    { "name": "c", "type": { "type": "record", "name": "Inner", "fields": [] } },
+                    { "name": "d", "type": ["null", "Inner"] },
+                    { "name": "e", "type": { "type": "long", "logicalType": "timestamp-millis" } },
+                    { "name": "f", "type": { "type": "bytes", "logicalType": "decimal", "precision": 4 } }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let datums: Vec<Vec<u8>> = (0..100).map(|_| schema.sample(&mut rng)).collect();
+        assert!(datums.iter().all(|d| d[0] < 16));
+        assert!(datums.iter().any(|d| d != &datums[0]));
+        assert_eq!(r#"{"type": "string"}"#, parse(r#"{"type": "string"}"#).unwrap().source());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Err("Invalid Avro schema: type 'Nope' is unknown".to_string()), parse(r#""Nope""#));
+        assert_eq!(
+            Err("Invalid Avro schema: field 'next' of 'Node': type 'Node' is recursive, which is not supported"
+                .to_string()),
+            parse(r#"{"type": "record", "name": "Node", "fields": [{"name": "next", "type": ["null", "Node"]}]}"#)
+        );
+        assert_eq!(
+            Err("Invalid Avro schema: 'enum' 'a.E' must have non-empty 'symbols'".to_string()),
+            parse(r#"{"type": "enum", "name": "E", "namespace": "a", "symbols": []}"#)
+        );
+        assert_eq!(
+            Err("Invalid Avro schema: type 'F' is defined more than once".to_string()),
+            parse(r#"[{"type": "fixed", "name": "F", "size": 1}, {"type": "fixed", "name": "F", "size": 1}]"#)
+        );
+        assert_eq!(Err("Invalid Avro schema: 'type' is missing".to_string()), parse(r#"{"name": "X"}"#));
+        assert!(parse("{").unwrap_err().contains("as JSON"));
+        assert!(AvroSchema::load(Path::new("does_not_exist.avsc")).is_err());
+    }
+}
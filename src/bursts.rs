@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use rand::Rng;
+use serde::{de, Deserialize, Deserializer};
+
+use crate::random::{RandomStream, Randomness};
+use crate::transition::Transition;
+use crate::workload::{is_valid_rate, RateSchedule, Workload, WorkloadPhase, WorkloadSegment};
+
+/// Seconds whose burst starts are drawn together, from the same random generator.
+const BLOCK_SEC: u64 = 1024;
+
+/// Most blocks of burst starts kept in memory: plenty for the seconds looked back at, when going through them in order.
+const MAX_CACHED_BLOCKS: usize = 64;
+
+/// How high a burst goes, on top of the workload.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BurstHeight {
+    /// The records/sec of the workload are multiplied by this factor, at the peak of the burst.
+    Multiplier(f64),
+
+    /// These records/sec are added to the ones of the workload, at the peak of the burst.
+    Absolute(f64),
+}
+
+impl fmt::Display for BurstHeight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BurstHeight::Multiplier(m) => write!(f, "{m}x"),
+            BurstHeight::Absolute(r) => write!(f, "{r}"),
+        }
+    }
+}
+
+/// Random bursts, injected on top of a workload: see [`BurstOverlay`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bursts {
+    mean_interval_sec: f64,
+    height: BurstHeight,
+    duration_sec: u32,
+    transition: Transition,
+}
+
+impl Bursts {
+    pub fn new(mean_interval_sec: f64, height: BurstHeight, duration_sec: u32, transition: Transition) -> Bursts {
+        Bursts {
+            mean_interval_sec,
+            height,
+            duration_sec,
+            transition,
+        }
+    }
+
+    /// Implementation of [`clap::value_parser`], used to create an argument by parsing a user-provided value.
+    ///
+    /// The `&str` provided must be of format `MEAN_INTERVAL_SEC:HEIGHT:SEC[:TRANSITION_TYPE]`:
+    ///
+    /// * `MEAN_INTERVAL_SEC` is parsed to `f64` average seconds between the start of bursts (at least `1`)
+    /// * `HEIGHT` is either a multiplier of the records/sec, like `3x`, or an amount of records/sec to add, like `500`
+    /// * `SEC` is parsed to `u32` duration of each burst, in seconds (greater than `0`)
+    /// * `TRANSITION_TYPE` (optional) is parsed to the [`Transition`] shaping each burst, defaulting to `none`
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn clap_parser(bursts_as_str: &str) -> Result<Bursts, String> {
+        const FORMAT: &str = "MEAN_INTERVAL_SEC:HEIGHT:SEC[:TRANSITION_TYPE]";
+
+        let parts: Vec<&str> = bursts_as_str.splitn(4, ':').collect();
+        if parts.len() < 3 {
+            return Err(format!("Should have '{FORMAT}' format"));
+        }
+
+        let mean_interval_sec = match parts[0].parse::<f64>() {
+            Err(e) => return Err(format!("Failed to parse 'MEAN_INTERVAL_SEC' from '{FORMAT}': {e}")),
+            Ok(v) if !(v >= 1.0 && v.is_finite()) => {
+                return Err("'MEAN_INTERVAL_SEC' must be a number greater or equal to 1".to_string())
+            },
+            Ok(v) => v,
+        };
+
+        let height = match parts[1].strip_suffix('x').map(|m| m.parse::<f64>()) {
+            Some(Ok(m)) if is_valid_rate(m) => BurstHeight::Multiplier(m),
+            Some(_) => return Err("'HEIGHT' multiplier must be a non-negative number, followed by 'x'".to_string()),
+            None => match parts[1].parse::<f64>() {
+                Ok(r) if is_valid_rate(r) => BurstHeight::Absolute(r),
+                _ => return Err("'HEIGHT' must be a non-negative number, or a multiplier like '3x'".to_string()),
+            },
+        };
+
+        let duration_sec = match parts[2].parse::<u32>() {
+            Err(e) => return Err(format!("Failed to parse 'SEC' from '{FORMAT}': {e}")),
+            Ok(0) => return Err("'SEC' must be greater than 0".to_string()),
+            Ok(v) => v,
+        };
+
+        let transition = match parts.get(3) {
+            None => Transition::None,
+            Some(s) => Transition::clap_parser(s)
+                .map_err(|e| format!("Failed to parse 'TRANSITION_TYPE' from '{FORMAT}': {e}"))?,
+        };
+
+        Ok(Bursts::new(mean_interval_sec, height, duration_sec, transition))
+    }
+
+    /// Probability that a burst starts in any given second.
+    ///
+    /// Bursts arrive as a Poisson process: the probability of at least one arrival in 1 second is `1 - e^(-1/mean)`.
+    fn start_probability(&self) -> f64 {
+        1.0 - (-1.0 / self.mean_interval_sec).exp()
+    }
+}
+
+impl fmt::Display for Bursts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}:{}", self.mean_interval_sec, self.height, self.duration_sec, self.transition)
+    }
+}
+
+/// A [`Bursts`] is deserialized from the same format parsed by [`Bursts::clap_parser`].
+impl<'de> Deserialize<'de> for Bursts {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bursts_as_str = String::deserialize(deserializer)?;
+
+        Bursts::clap_parser(&bursts_as_str).map_err(de::Error::custom)
+    }
+}
+
+/// A [`RateSchedule`] with random [`Bursts`] injected on top of it.
+///
+/// Each second, a burst starts with the probability given by its mean interval, independently of the
+/// previous seconds. A burst rises to its height and back down along its [`Transition`] (with
+/// [`Transition::None`], it stays at its height for the whole duration). Overlapping bursts don't add up:
+/// the highest prevails.
+///
+/// Whether a burst starts at a given second only depends on the [`Randomness`] and the second itself:
+/// with a seed, the same bursts happen at the same seconds, run after run. The seconds bursts start at are
+/// drawn for blocks of seconds at a time, and kept in memory while looked back at.
+#[derive(Debug)]
+pub struct BurstOverlay {
+    schedule: Arc<dyn RateSchedule>,
+    bursts: Bursts,
+    randomness: Randomness,
+
+    /// The shape of a burst, from `0` to `1` and back, for its duration (`None` when flat).
+    shape: Option<Workload>,
+
+    /// Seconds where a burst starts (in order), for each block of [`BLOCK_SEC`] seconds drawn recently
+    starts_cache: Mutex<HashMap<u64, Arc<Vec<u64>>>>,
+}
+
+impl BurstOverlay {
+    pub fn new(schedule: Arc<dyn RateSchedule>, bursts: Bursts, randomness: Randomness) -> BurstOverlay {
+        // The shape lasts 1 second more than the burst, so the burst skips the initial `0`
+        let shape = (bursts.transition != Transition::None).then(|| {
+            let shape_sec = bursts.duration_sec + 1;
+            let up_sec = shape_sec.div_ceil(2);
+            Workload::from_segments(vec![
+                WorkloadSegment::new(1.0, up_sec, bursts.transition),
+                WorkloadSegment::new(0.0, shape_sec - up_sec, bursts.transition),
+            ])
+        });
+
+        BurstOverlay {
+            schedule,
+            bursts,
+            // Seconds are looked back at multiple times: they must always draw the same
+            randomness: randomness.to_seeded(),
+            shape,
+            starts_cache: Mutex::default(),
+        }
+    }
+
+    /// Seconds where a burst starts, within the `block`-th block of [`BLOCK_SEC`] seconds.
+    fn block_starts(&self, block: u64) -> Arc<Vec<u64>> {
+        // If the lock is poisoned, the cache is still valid: blocks are only ever added whole
+        let mut cache = self.starts_cache.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(starts) = cache.get(&block) {
+            return starts.clone();
+        }
+
+        let mut rng = self.randomness.rng(RandomStream::Bursts, block);
+        let probability = self.bursts.start_probability();
+        let first_sec = block.saturating_mul(BLOCK_SEC);
+        let starts: Arc<Vec<u64>> =
+            Arc::new((first_sec..first_sec.saturating_add(BLOCK_SEC)).filter(|_| rng.gen_bool(probability)).collect());
+
+        if cache.len() >= MAX_CACHED_BLOCKS {
+            cache.clear();
+        }
+        cache.insert(block, starts.clone());
+        starts
+    }
+
+    /// Seconds where a burst starts, from `first_sec` to `last_sec` (inclusive), in order.
+    fn burst_starts(&self, first_sec: u64, last_sec: u64) -> Vec<u64> {
+        (first_sec / BLOCK_SEC..=last_sec / BLOCK_SEC)
+            .flat_map(|block| {
+                let starts = self.block_starts(block);
+                let from = starts.partition_point(|s| *s < first_sec);
+                let to = starts.partition_point(|s| *s <= last_sec);
+                starts[from..to].to_vec()
+            })
+            .collect()
+    }
+
+    /// Whether a burst starts at `sec`.
+    #[cfg(test)]
+    fn is_burst_start(&self, sec: u64) -> bool {
+        !self.burst_starts(sec, sec).is_empty()
+    }
+
+    /// Intensity of the bursts at `sec`, in `[0..1]`: `0` when no burst is ongoing.
+    fn intensity_at(&self, sec: u64) -> f64 {
+        // Look back at the seconds where an ongoing burst could have started
+        let first_start_sec = sec.saturating_sub(self.bursts.duration_sec as u64 - 1);
+
+        self.burst_starts(first_start_sec, sec)
+            .into_iter()
+            .map(|start_sec| match &self.shape {
+                None => 1.0,
+                Some(shape) => shape.records_per_sec_at(sec - start_sec + 1),
+            })
+            .fold(0.0, f64::max)
+    }
+}
+
+impl RateSchedule for BurstOverlay {
+    fn records_per_sec_at(&self, sec: u64) -> f64 {
+        let rate = self.schedule.records_per_sec_at(sec);
+        let intensity = self.intensity_at(sec);
+
+        match self.bursts.height {
+            BurstHeight::Multiplier(m) => rate * (1.0 + intensity * (m - 1.0)).max(0.0),
+            BurstHeight::Absolute(r) => rate + intensity * r,
+        }
+    }
+
+    fn phase_at(&self, sec: u64) -> WorkloadPhase {
+        self.schedule.phase_at(sec)
+    }
+
+    fn max_records_per_sec(&self) -> f64 {
+        let max = self.schedule.max_records_per_sec();
+
+        match self.bursts.height {
+            BurstHeight::Multiplier(m) => max * m.max(1.0),
+            BurstHeight::Absolute(r) => max + r,
+        }
+    }
+
+    fn overall_duration_sec(&self) -> u32 {
+        self.schedule.overall_duration_sec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat(rate: f64) -> Arc<dyn RateSchedule> {
+        Arc::new(Workload::from_segments(vec![WorkloadSegment::new(rate, 60, Transition::None)]))
+    }
+
+    #[test]
+    fn test_bursts() {
+        let bursts = Bursts::new(20.0, BurstHeight::Multiplier(3.0), 5, Transition::None);
+        let overlay = BurstOverlay::new(flat(100.0), bursts, Randomness::new(Some(42)));
+        let rates = (0..4_000).map(|sec| overlay.records_per_sec_at(sec)).collect::<Vec<_>>();
+
+        // Only base or burst rates
+        assert!(rates.iter().all(|r| *r == 100.0 || *r == 300.0));
+        assert_eq!(300.0, overlay.max_records_per_sec());
+
+        // About 1 burst every 20 seconds
+        let starts = (0..4_000).filter(|sec| overlay.is_burst_start(*sec)).count();
+        assert!((150..250).contains(&starts), "{starts} bursts started");
+
+        // Each burst lasts 5 seconds
+        let first_start = (0..4_000).find(|sec| overlay.is_burst_start(*sec)).unwrap() as usize;
+        assert_eq!(vec![300.0; 5], rates[first_start..first_start + 5]);
+
+        // Same seed, same bursts; different seed, different bursts
+        let same = BurstOverlay::new(flat(100.0), bursts, Randomness::new(Some(42)));
+        assert_eq!(rates, (0..4_000).map(|sec| same.records_per_sec_at(sec)).collect::<Vec<_>>());
+        let other = BurstOverlay::new(flat(100.0), bursts, Randomness::new(Some(43)));
+        assert_ne!(rates, (0..4_000).map(|sec| other.records_per_sec_at(sec)).collect::<Vec<_>>());
+
+        // Even unseeded, the same second always has the same rate
+        let unseeded = BurstOverlay::new(flat(100.0), bursts, Randomness::default());
+        assert_eq!(unseeded.records_per_sec_at(1234), unseeded.records_per_sec_at(1234));
+    }
+
+    #[test]
+    fn test_shaped_bursts() {
+        let bursts = Bursts::new(50.0, BurstHeight::Absolute(100.0), 3, Transition::Linear);
+        let overlay = BurstOverlay::new(flat(10.0), bursts, Randomness::new(Some(42)));
+        assert_eq!(110.0, overlay.max_records_per_sec());
+
+        // A burst that doesn't overlap with others
+        let start = (2..4_000)
+            .find(|sec| {
+                overlay.is_burst_start(*sec) && (sec - 2..=sec + 2).filter(|s| overlay.is_burst_start(*s)).count() == 1
+            })
+            .unwrap();
+
+        // Rises and falls back: the peak is in the middle
+        let rates = (start - 1..start + 4).map(|sec| overlay.records_per_sec_at(sec)).collect::<Vec<_>>();
+        assert_eq!(10.0, rates[0]);
+        assert!(rates[1] > 10.0 && rates[1] < 110.0);
+        assert_eq!(110.0, rates[2]);
+        assert!(rates[3] > 10.0 && rates[3] < 110.0);
+        assert_eq!(10.0, rates[4]);
+    }
+
+    #[test]
+    fn test_long_bursts() {
+        // Hour-long bursts, about once a day: looking back doesn't draw every second again
+        let bursts = Bursts::new(86_400.0, BurstHeight::Multiplier(10.0), 3_600, Transition::Linear);
+        let overlay = BurstOverlay::new(flat(100.0), bursts, Randomness::new(Some(7)));
+        let rates = (0..7 * 86_400).map(|sec| overlay.records_per_sec_at(sec)).collect::<Vec<_>>();
+        assert!(rates.iter().any(|r| *r > 100.0));
+
+        // The cache is bounded, and the rates don't depend on what is cached
+        assert!(overlay.starts_cache.lock().unwrap().len() <= MAX_CACHED_BLOCKS);
+        let same = BurstOverlay::new(flat(100.0), bursts, Randomness::new(Some(7)));
+        for sec in [500_000, 3, 86_400, 250_000] {
+            assert_eq!(rates[sec as usize], same.records_per_sec_at(sec));
+        }
+    }
+
+    #[test]
+    fn test_clap_parser() {
+        assert_eq!(
+            Ok(Bursts::new(3600.0, BurstHeight::Multiplier(10.0), 60, Transition::None)),
+            Bursts::clap_parser("3600:10x:60")
+        );
+        assert_eq!(
+            Ok(Bursts::new(600.0, BurstHeight::Absolute(500.0), 30, Transition::SpikeIn)),
+            Bursts::clap_parser("600:500:30:spike-in")
+        );
+        assert_eq!(
+            "60:2.5x:10:bezier:0.1,0.2,0.3,0.4",
+            Bursts::clap_parser("60:2.5x:10:bezier:0.1,0.2,0.3,0.4").unwrap().to_string()
+        );
+
+        assert_eq!(
+            Err("Should have 'MEAN_INTERVAL_SEC:HEIGHT:SEC[:TRANSITION_TYPE]' format".to_string()),
+            Bursts::clap_parser("60:10x")
+        );
+        assert_eq!(
+            Err("'MEAN_INTERVAL_SEC' must be a number greater or equal to 1".to_string()),
+            Bursts::clap_parser("0.5:10x:10")
+        );
+        assert_eq!(
+            Err("'HEIGHT' multiplier must be a non-negative number, followed by 'x'".to_string()),
+            Bursts::clap_parser("60:tenx:10")
+        );
+        assert_eq!(Err("'SEC' must be greater than 0".to_string()), Bursts::clap_parser("60:10x:0"));
+    }
+}
@@ -1,9 +1,25 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
 use clap::error::ErrorKind;
-pub use clap::{value_parser, ArgGroup, CommandFactory, Parser};
+use clap::parser::ValueSource;
+pub use clap::{value_parser, ArgGroup, CommandFactory, FromArgMatches, Parser};
 
+use crate::anchor::{AnchorTime, AnchorTimezone};
+use crate::bursts::Bursts;
 use crate::generator::ValueGenerator;
+use crate::noise::Noise;
+use crate::plot::PlotFormat;
+use crate::ramp::Ramp;
+use crate::rate_expr::{RateExpr, MAX_PERIOD_SEC};
 use crate::rdkafka::PartitionerConfig;
+use crate::records_tap::{Pacing, ThroughputUnit};
+use crate::scenario::{ProducerScenario, Scenario, WorkloadScenario};
+use crate::sequence::Sequence;
+use crate::time_scale::{time_scale_clap_parser, TimeScaleKeep};
 use crate::transition::Transition;
+use crate::waveform::Waveform;
+use crate::workload::WorkloadSegment;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -14,23 +30,31 @@ use crate::transition::Transition;
         .args(["verbose", "quiet"]),
 ))]
 pub struct Cli {
+    // ---------------------------------------------------------------------- Scenario configuration
+    /// Scenario file describing the whole run (format: TOML or YAML).
+    ///
+    /// It can describe the producer, the records and the workload: any argument provided
+    /// on the command line overrides the corresponding value in the file.
+    #[arg(long = "scenario", value_name = "PATH")]
+    pub scenario: Option<PathBuf>,
+
     // ---------------------------------------------------------------------- Producer configuration
     /// Initial Kafka Brokers to connect to (format: 'HOST:PORT,...').
     ///
     /// Equivalent to '--config=bootstrap.servers:host:port,...'.
-    #[arg(short, long = "brokers", value_name = "BOOTSTRAP_BROKERS")]
-    pub bootstrap_brokers: String,
+    #[arg(short, long = "brokers", value_name = "BOOTSTRAP_BROKERS", required_unless_present_any = ["scenario", "dry_run"])]
+    pub bootstrap_brokers: Option<String>,
 
     /// Client identifier used by the internal Kafka Producer.
     ///
     /// Equivalent to '--config=client.id:my-client-id'.
-    #[arg(long = "client-id", value_name = "CLIENT_ID", default_value = env!("CARGO_PKG_NAME"))]
+    #[arg(long = "client-id", value_name = "CLIENT_ID", default_value_t = ProducerScenario::default().client_id)]
     pub client_id: String,
 
     /// Partitioner used by the internal Kafka Producer.
     ///
     /// Equivalent to '--config=partitioner:random'.
-    #[arg(long, value_name = "PARTITIONER", value_enum, default_value_t = PartitionerConfig::default())]
+    #[arg(long, value_name = "PARTITIONER", value_enum, default_value_t = ProducerScenario::default().partitioner)]
     pub partitioner: PartitionerConfig,
 
     /// Additional configuration used by the internal Kafka Producer (format: 'CONF_KEY:CONF_VAL').
@@ -44,8 +68,8 @@ pub struct Cli {
     /// Destination Topic.
     ///
     /// Topic must already exist.
-    #[arg(short = 't', long = "topic", value_name = "TOPIC")]
-    pub topic: String,
+    #[arg(short = 't', long = "topic", value_name = "TOPIC", required_unless_present_any = ["scenario", "dry_run"])]
+    pub topic: Option<String>,
 
     /// Records Key (format: 'KEY_TYPE:INPUT').
     ///
@@ -57,6 +81,11 @@ pub struct Cli {
     /// * 'bytes:LENGTH': LENGTH is the length of a random bytes array
     /// * 'int:MIN-MAX': MIN and MAX are limits of an inclusive range from which an integer number is picked
     /// * 'float:MIN-MAX': MIN and MAX are limits of an inclusive range from which a float number is picked
+    /// * 'template:TEMPLATE': TEMPLATE is a text with placeholders (ex. '{{uuid}}'), filled in for every record
+    /// * 'jsonschema:PATH': PATH is a path to a JSON Schema file, from which a valid JSON document is generated
+    /// * 'avro:PATH': PATH is a path to an Avro schema file, from which a valid Avro datum is generated
+    /// * 'protobuf:PATH:MESSAGE': PATH is a path to a '.proto' file (or descriptor set), MESSAGE the type to generate
+    /// * 'seq:OPTIONS': OPTIONS configure a monotonic sequence of integers (ex. 'start=1,step=1,per=key,encoding=be')
     #[arg(short, long, value_name = "KEY_TYPE:INPUT", value_parser = ValueGenerator::clap_parser, verbatim_doc_comment)]
     pub key: Option<ValueGenerator>,
 
@@ -70,6 +99,11 @@ pub struct Cli {
     /// * 'bytes:LENGTH': LENGTH is the length of a random bytes array
     /// * 'int:MIN-MAX': MIN and MAX are limits of an inclusive range from which an integer number is picked
     /// * 'float:MIN-MAX': MIN and MAX are limits of an inclusive range from which a float number is picked
+    /// * 'template:TEMPLATE': TEMPLATE is a text with placeholders (ex. '{{uuid}}'), filled in for every record
+    /// * 'jsonschema:PATH': PATH is a path to a JSON Schema file, from which a valid JSON document is generated
+    /// * 'avro:PATH': PATH is a path to an Avro schema file, from which a valid Avro datum is generated
+    /// * 'protobuf:PATH:MESSAGE': PATH is a path to a '.proto' file (or descriptor set), MESSAGE the type to generate
+    /// * 'seq:OPTIONS': OPTIONS configure a monotonic sequence of integers (ex. 'start=1,step=1,per=key,encoding=be')
     #[arg(short, long, value_name = "PAYLOAD_TYPE:INPUT", value_parser = ValueGenerator::clap_parser, verbatim_doc_comment)]
     pub payload: Option<ValueGenerator>,
 
@@ -86,39 +120,326 @@ pub struct Cli {
     #[arg(long = "head", value_name = "HEAD_KEY:HEAD_VAL", value_parser = kv_clap_value_parser)]
     pub headers: Vec<KVPair>,
 
+    /// Records Header(s) with a monotonic sequence of integers, as text (format: 'HEAD_KEY:OPTIONS').
+    ///
+    /// OPTIONS are the same of 'seq:OPTIONS' for '--key' and '--payload' (ex. 'start=1,step=1,per=key').
+    /// To set multiple headers, use this argument multiple times.
+    #[arg(long = "head-seq", value_name = "HEAD_KEY:OPTIONS", value_parser = seq_kv_clap_value_parser)]
+    pub header_seqs: Vec<(String, Sequence)>,
+
+    /// Schema Registry URL (format: 'http[s]://HOST:PORT').
+    ///
+    /// Required to produce 'avro' key or payload, optional for 'protobuf': their schemas are looked up
+    /// (or registered, if missing) under subjects 'TOPIC-key' and 'TOPIC-value', and records are prefixed
+    /// with the schema ID as per the Confluent wire format.
+    #[arg(long, value_name = "URL")]
+    pub schema_registry: Option<String>,
+
     // ---------------------------------------------------------------------- Workload configuration
     /// Minimum amount of records/sec.
-    #[arg(long = "min", value_name = "REC/SEC")]
-    pub min: u32,
+    ///
+    /// Can be fractional: ex. '0.1' produces 1 record every 10 seconds.
+    #[arg(long = "min", value_name = "REC/SEC", required_unless_present_any = ["segments", "trace", "waveform", "rate_expr", "scenario"])]
+    pub min: Option<f64>,
 
     /// How long to produce at minimum records/sec, before ramp-up.
-    #[arg(long = "min-sec", default_value_t = 60, value_name = "SEC")]
+    #[arg(long = "min-sec", default_value_t = WorkloadScenario::default().min_sec, value_name = "SEC")]
     pub min_sec: u32,
 
     /// Maximum amount of records/sec.
-    #[arg(long = "max", value_name = "REC/SEC")]
-    pub max: u32,
+    ///
+    /// Can be fractional, like '--min'.
+    #[arg(long = "max", value_name = "REC/SEC", required_unless_present_any = ["segments", "trace", "waveform", "rate_expr", "scenario"])]
+    pub max: Option<f64>,
 
     /// How long to produce at maximum records/sec, before ramp-down.
-    #[arg(long = "max-sec", default_value_t = 60, value_name = "SEC")]
+    #[arg(long = "max-sec", default_value_t = WorkloadScenario::default().max_sec, value_name = "SEC")]
     pub max_sec: u32,
 
     /// Ramp-up transition from minimum to maximum records/sec.
-    #[arg(long = "up", value_enum, default_value_t = Transition::Linear, value_name = "TRANSITION_TYPE")]
+    ///
+    /// The supported transition types are:
+    ///
+    /// * 'none': Immediate transition, with no in-between values
+    /// * 'linear': Linear transition, constant increments between values
+    /// * 'ease-in': Slow increment at the beginning, accelerates half way through until the end
+    /// * 'ease-out': Fast increment at the beginning, decelerates half way through until the end
+    /// * 'ease-in-out': Slow increment at the beginning, accelerates half way, decelerates at the end
+    /// * 'spike-in': Fastest increment at the beginning, slowest deceleration close to the end
+    /// * 'spike-out': Slowest increment at the beginning, fastest acceleration close to the end
+    /// * 'spike-in-out': Fastest increment at the beginning, slow half way, fastest acceleration close to the end
+    /// * 'bezier:X1,Y1,X2,Y2': Custom Cubic Bézier control points, like CSS 'cubic-bezier()' (X1 and X2 in [0..1])
+    /// * 'steps:N[:EASING]': Staircase of N steps of constant records/sec, with heights following the EASING transition
+    /// * 'exp:K': Exponential growth with growth factor K in (0..100]: slow at the beginning, faster and faster
+    /// * 'log:K': Logarithmic growth with growth factor K in (0..100]: fast at the beginning, slower and slower
+    /// * 'sigmoid:S[:M]': S-curve with steepness S in (0..100], fastest at midpoint M in [0..1] (default: 0.5)
+    #[arg(
+        long = "up",
+        value_parser = Transition::clap_parser,
+        default_value_t = WorkloadScenario::default().up,
+        value_name = "TRANSITION_TYPE",
+        verbatim_doc_comment
+    )]
     pub up: Transition,
 
     /// How long the ramp-up transition should last.
-    #[arg(long = "up-sec", default_value_t = 10, value_name = "SEC")]
+    #[arg(long = "up-sec", default_value_t = WorkloadScenario::default().up_sec, value_name = "SEC")]
     pub up_sec: u32,
 
     /// Ramp-down transition from maximum to minimum records/sec.
-    #[arg(long = "down", value_enum, default_value_t = Transition::None, value_name = "TRANSITION_TYPE")]
+    ///
+    /// The supported transition types are:
+    ///
+    /// * 'none': Immediate transition, with no in-between values
+    /// * 'linear': Linear transition, constant increments between values
+    /// * 'ease-in': Slow increment at the beginning, accelerates half way through until the end
+    /// * 'ease-out': Fast increment at the beginning, decelerates half way through until the end
+    /// * 'ease-in-out': Slow increment at the beginning, accelerates half way, decelerates at the end
+    /// * 'spike-in': Fastest increment at the beginning, slowest deceleration close to the end
+    /// * 'spike-out': Slowest increment at the beginning, fastest acceleration close to the end
+    /// * 'spike-in-out': Fastest increment at the beginning, slow half way, fastest acceleration close to the end
+    /// * 'bezier:X1,Y1,X2,Y2': Custom Cubic Bézier control points, like CSS 'cubic-bezier()' (X1 and X2 in [0..1])
+    /// * 'steps:N[:EASING]': Staircase of N steps of constant records/sec, with heights following the EASING transition
+    /// * 'exp:K': Exponential growth with growth factor K in (0..100]: slow at the beginning, faster and faster
+    /// * 'log:K': Logarithmic growth with growth factor K in (0..100]: fast at the beginning, slower and slower
+    /// * 'sigmoid:S[:M]': S-curve with steepness S in (0..100], fastest at midpoint M in [0..1] (default: 0.5)
+    #[arg(
+        long = "down",
+        value_parser = Transition::clap_parser,
+        default_value_t = WorkloadScenario::default().down,
+        value_name = "TRANSITION_TYPE",
+        verbatim_doc_comment
+    )]
     pub down: Transition,
 
     /// How long the ramp-down transition should last.
-    #[arg(long = "down-sec", default_value_t = 10, value_name = "SEC")]
+    #[arg(long = "down-sec", default_value_t = WorkloadScenario::default().down_sec, value_name = "SEC")]
     pub down_sec: u32,
 
+    /// Workload segment (format: 'REC/SEC:SEC[:TRANSITION_TYPE]').
+    ///
+    /// Alternative to the 4 phases described by '--min', '--up', '--max' and '--down'.
+    /// To describe a workload as a sequence of segments, use this argument multiple times:
+    /// the segments are traversed in order, and after the last one the first starts again.
+    ///
+    /// Each segment reaches REC/SEC and lasts SEC seconds, moving from the records/sec of the
+    /// previous segment along the TRANSITION_TYPE curve (default: 'none'): see '--up' for the supported types.
+    #[arg(
+        long = "segment",
+        value_name = "REC/SEC:SEC[:TRANSITION_TYPE]",
+        value_parser = WorkloadSegment::clap_parser,
+        conflicts_with_all = ["min", "min_sec", "max", "max_sec", "up", "up_sec", "down", "down_sec"]
+    )]
+    pub segments: Vec<WorkloadSegment>,
+
+    /// Rate trace to replay (format: CSV or JSON).
+    ///
+    /// Alternative to the 4 phases and to '--segment': a recorded time series of (second, rate) points,
+    /// with seconds relative to the first point. CSV files contain lines of 'sec,rate' (an initial header
+    /// is ignored), JSON files an array of '{"sec": N, "rate": R}' objects (or '[N, R]' arrays).
+    ///
+    /// By default, the trace is replayed once, and then the run ends.
+    #[arg(
+        long = "trace",
+        value_name = "PATH",
+        conflicts_with_all = ["min", "min_sec", "max", "max_sec", "up", "up_sec", "down", "down_sec", "segments"]
+    )]
+    pub trace: Option<PathBuf>,
+
+    /// Transition between the points of the rate trace.
+    ///
+    /// With 'none', each rate is held until the next point: see '--up' for the supported types.
+    #[arg(
+        long = "trace-transition",
+        value_name = "TRANSITION_TYPE",
+        value_parser = Transition::clap_parser,
+        default_value_t = WorkloadScenario::default().trace_transition,
+        requires = "trace"
+    )]
+    pub trace_transition: Transition,
+
+    /// Replay the rate trace in a loop, instead of once.
+    #[arg(long = "trace-loop", requires = "trace")]
+    pub trace_loop: bool,
+
+    /// Periodic waveform (format: 'TYPE:AMPLITUDE:OFFSET:PERIOD[:SHIFT]').
+    ///
+    /// Alternative to the 4 phases, to '--segment' and to '--trace': records/sec oscillate around OFFSET
+    /// by AMPLITUDE, repeating every PERIOD seconds. The wave can be shifted forward by SHIFT seconds (default: 0).
+    /// Records/sec never go below 0.
+    ///
+    /// The supported waveform types are:
+    ///
+    /// * 'sine': Smooth oscillation
+    /// * 'sawtooth': Constant increase to the top, then immediate drop to the bottom
+    /// * 'square': Top for the first half of the period, bottom for the second half
+    /// * 'triangle': Constant increase to the top, then constant decrease to the bottom
+    #[arg(
+        long = "waveform",
+        value_name = "TYPE:AMPLITUDE:OFFSET:PERIOD[:SHIFT]",
+        value_parser = Waveform::clap_parser,
+        conflicts_with_all = ["min", "min_sec", "max", "max_sec", "up", "up_sec", "down", "down_sec", "segments", "trace"],
+        verbatim_doc_comment
+    )]
+    pub waveform: Option<Waveform>,
+
+    /// Math expression of the time 't', in seconds since the beginning of the cycle (ex. '500 + 400*sin(2*pi*t/300)').
+    ///
+    /// Alternative to the 4 phases, to '--segment', to '--trace' and to '--waveform': records/sec are the value
+    /// of the expression, evaluated for every second (never below 0). The expression supports:
+    ///
+    /// * Numbers, the variable 't' and the constants 'pi' and 'e'
+    /// * Arithmetic: '+', '-', '*', '/', '%' (remainder) and '^' (power)
+    /// * Comparisons: '<', '<=', '>', '>=', '==' and '!=', that are 1 when true and 0 when false
+    /// * Logic: '&&', '||' and '!', where any value other than 0 is true
+    /// * Functions: 'sin', 'cos', 'tan', 'asin', 'acos', 'atan', 'sqrt', 'exp', 'ln', 'log10', 'abs',
+    ///   'floor', 'ceil', 'round', 'min(a, b, ...)', 'max(a, b, ...)', 'clamp(x, lo, hi)' and 'if(cond, a, b)'
+    #[arg(
+        long = "rate-expr",
+        value_name = "RATE_EXPR",
+        value_parser = RateExpr::clap_parser,
+        conflicts_with_all = ["min", "min_sec", "max", "max_sec", "up", "up_sec", "down", "down_sec", "segments", "trace", "waveform"],
+        verbatim_doc_comment
+    )]
+    pub rate_expr: Option<RateExpr>,
+
+    /// How long the cycle of the '--rate-expr' lasts: 't' goes from 0 to SEC-1, and then starts again.
+    #[arg(
+        long = "rate-expr-period",
+        value_name = "SEC",
+        value_parser = value_parser!(u32).range(1..=MAX_PERIOD_SEC as i64),
+        default_value_t = WorkloadScenario::default().rate_expr_period,
+        requires = "rate_expr"
+    )]
+    pub rate_expr_period: u32,
+
+    /// Anchor the workload to the wall-clock of a timezone (ex. 'Europe/London', 'UTC' or '+02:00').
+    ///
+    /// By default, the workload cycle begins when Ksunami starts. When anchored, the cycle begins
+    /// at '--anchor-time' in the given timezone (or UTC offset), and Ksunami starts from the point of
+    /// the cycle that corresponds to the current wall-clock time: restarting doesn't shift the workload.
+    #[arg(long = "anchor-tz", value_name = "TIMEZONE", value_parser = AnchorTimezone::clap_parser)]
+    pub anchor_tz: Option<AnchorTimezone>,
+
+    /// Time of the day (format: 'HH:MM[:SS]') that the workload cycle begins at, when anchored.
+    #[arg(
+        long = "anchor-time",
+        value_name = "HH:MM[:SS]",
+        value_parser = AnchorTime::clap_parser,
+        default_value_t = WorkloadScenario::default().anchor_time,
+        requires = "anchor_tz"
+    )]
+    pub anchor_time: AnchorTime,
+
+    /// Seconds of the workload that each real second covers.
+    ///
+    /// Greater than 1 compresses the workload (ex. '24' replays a 24 hours cycle in 1 hour),
+    /// lower than 1 stretches it (ex. '0.5' makes a 60 seconds cycle last 120 seconds). Up to '86400'.
+    #[arg(
+        long = "time-scale",
+        value_name = "FACTOR",
+        value_parser = time_scale_clap_parser,
+        default_value_t = WorkloadScenario::default().time_scale
+    )]
+    pub time_scale: f64,
+
+    /// What to keep of the workload when time-scaled: the rate (records/sec) or the volume (records overall).
+    #[arg(
+        long = "time-scale-keep",
+        value_name = "KEEP",
+        value_enum,
+        default_value_t = WorkloadScenario::default().time_scale_keep
+    )]
+    pub time_scale_keep: TimeScaleKeep,
+
+    /// Random bursts injected on top of the workload (format: 'MEAN_INTERVAL_SEC:HEIGHT:SEC[:TRANSITION_TYPE]').
+    ///
+    /// Bursts start at random, on average every MEAN_INTERVAL_SEC seconds (Poisson arrivals), and last SEC seconds.
+    /// HEIGHT is either a multiplier of the records/sec (ex. '3x'), or an amount of records/sec to add (ex. '500').
+    /// Each burst rises to HEIGHT and back along the TRANSITION_TYPE curve (default: 'none', flat for the whole
+    /// burst): see '--up' for the supported types. Use '--seed' to make the bursts reproducible.
+    #[arg(
+        long = "bursts",
+        value_name = "MEAN_INTERVAL_SEC:HEIGHT:SEC[:TRANSITION_TYPE]",
+        value_parser = Bursts::clap_parser
+    )]
+    pub bursts: Option<Bursts>,
+
+    /// Noise added to the records/sec of each second.
+    ///
+    /// * 'gaussian:PCT': records/sec deviate following a normal distribution, with standard deviation PCT% of them
+    /// * 'poisson': records/sec are a count following a Poisson distribution, with mean equal to them
+    ///
+    /// Noise is applied to the records/sec of the workload (bursts included), and never makes them negative:
+    /// 'gaussian' stays within 3 standard deviations of them. Use '--seed' to make the noise reproducible.
+    #[arg(long = "noise", value_name = "NOISE", value_parser = Noise::clap_parser, verbatim_doc_comment)]
+    pub noise: Option<Noise>,
+
+    /// One-shot warm-up before the first cycle (format: 'SEC[:TRANSITION_TYPE]').
+    ///
+    /// Records/sec go from 0 to where the workload begins, in SEC seconds, along the TRANSITION_TYPE curve
+    /// (default: 'linear'): see '--up' for the supported types. The workload begins after the warm-up.
+    #[arg(long = "warm-up", value_name = "SEC[:TRANSITION_TYPE]", value_parser = Ramp::clap_parser)]
+    pub warm_up: Option<Ramp>,
+
+    /// Cool-down at the end of the run (format: 'SEC[:TRANSITION_TYPE]').
+    ///
+    /// Once shutdown is requested, or the run limits are reached, records/sec go from where they are to 0,
+    /// in SEC seconds, along the TRANSITION_TYPE curve (default: 'linear'): see '--up' for the supported types.
+    /// Records produced during the cool-down go beyond the run limits. Request shutdown again to skip it.
+    #[arg(long = "cool-down", value_name = "SEC[:TRANSITION_TYPE]", value_parser = Ramp::clap_parser)]
+    pub cool_down: Option<Ramp>,
+
+    /// How the records of each second are spread across the second itself.
+    ///
+    /// * 'burst': all records are sent as fast as possible, at the beginning of the second
+    /// * 'even': records are spaced evenly across the second (with a resolution of 1ms)
+    /// * 'slices:N': the second is divided in N slices, each sending an equal share of records as a burst
+    #[arg(
+        long = "pacing",
+        value_name = "PACING",
+        value_parser = Pacing::clap_parser,
+        default_value_t = WorkloadScenario::default().pacing,
+        verbatim_doc_comment
+    )]
+    pub pacing: Pacing,
+
+    /// Unit of the workload: records/sec or bytes/sec.
+    ///
+    /// With 'bytes', '--min', '--max' and the segments rates are bytes/sec: how many records are produced
+    /// each second depends on the size of the generated records (key, payload and headers).
+    #[arg(long = "unit", value_name = "UNIT", value_enum, default_value_t = WorkloadScenario::default().unit)]
+    pub unit: ThroughputUnit,
+
+    // --------------------------------------------------------------------------- Run configuration
+    /// Stop after this many cycles of the workload.
+    ///
+    /// When multiple limits are set, the run stops as soon as the first one is reached:
+    /// the records still in flight are delivered, stats are reported, and Ksunami exits successfully.
+    #[arg(long = "cycles", value_name = "N", value_parser = value_parser!(u64).range(1..))]
+    pub cycles: Option<u64>,
+
+    /// Stop after this many seconds.
+    #[arg(long = "duration", value_name = "SEC", value_parser = value_parser!(u64).range(1..))]
+    pub duration: Option<u64>,
+
+    /// Stop after this many records have been produced.
+    #[arg(long = "total-records", value_name = "N", value_parser = value_parser!(u64).range(1..))]
+    pub total_records: Option<u64>,
+
+    /// Seed of all the randomness of the run (ex. random keys, payloads, bursts and noise).
+    ///
+    /// Runs with the same seed (and configuration) generate the same records, bursts and noise.
+    #[arg(long = "seed", value_name = "SEED")]
+    pub seed: Option<u64>,
+
+    /// Plot the workload schedule, instead of producing records (format: 'chart' or 'csv').
+    ///
+    /// Prints the records/sec of each second for '--cycles' (default: 1) or '--duration',
+    /// without connecting to Kafka. '--brokers' and '--topic' are not required.
+    #[arg(long = "dry-run", value_name = "FORMAT", value_enum, num_args = 0..=1, default_missing_value = "chart")]
+    pub dry_run: Option<PlotFormat>,
+
     /// Verbose logging.
     ///
     /// * none    = 'WARN'
@@ -139,43 +460,234 @@ pub struct Cli {
     /// Alternatively, set environment variable 'KSUNAMI_LOG=(ERROR|WARN|INFO|DEBUG|TRACE|OFF)'.
     #[arg(short,long, action = clap::ArgAction::Count, verbatim_doc_comment)]
     pub quiet: u8,
+
+    /// Identifiers of the arguments explicitly provided by the user (i.e. not defaulted).
+    #[arg(skip)]
+    explicit_args: HashSet<String>,
 }
 
 impl Cli {
     pub fn parse_and_validate() -> Self {
-        let cli = Self::parse();
+        let matches = Self::command().get_matches();
+
+        match Self::from_matches(&matches) {
+            Err(e) => e.exit(),
+            Ok(cli) => cli,
+        }
+    }
+
+    fn from_matches(matches: &clap::ArgMatches) -> Result<Self, clap::Error> {
+        let mut cli = Self::from_arg_matches(matches)?;
+
+        // Keep track of what was provided by the user, as it overrides the content of a scenario file
+        cli.explicit_args = matches
+            .ids()
+            .filter(|id| !matches!(matches.value_source(id.as_str()), None | Some(ValueSource::DefaultValue)))
+            .map(|id| id.to_string())
+            .collect();
 
-        // Validate values provided for `min` and `max`
-        if cli.min >= cli.max {
-            let mut cmd = Cli::command();
-            cmd.error(ErrorKind::InvalidValue, "Argument 'min' must be less than 'max'").exit();
+        Ok(cli)
+    }
+
+    /// Resolves the [`Scenario`] to run, and validates it.
+    ///
+    /// If a scenario file was provided, it is loaded first, and then overridden by
+    /// the arguments explicitly provided on the command line. Otherwise, the scenario is
+    /// entirely described by the command line arguments.
+    ///
+    /// In case of error, it prints out the problem and exits, like for any other invalid argument.
+    pub fn resolve_scenario(&self) -> Scenario {
+        match self.try_resolve_scenario() {
+            Err(e) => Cli::command().error(ErrorKind::InvalidValue, e).exit(),
+            Ok(scenario) => scenario,
         }
+    }
 
-        // Validate `(up|down)` transition in respect to their `(up|down)_sec` value
-        if cli.up != Transition::None && cli.up_sec == 0 {
-            let mut cmd = Cli::command();
-            cmd.error(
-                ErrorKind::InvalidValue,
-                "Argument 'up-sec' must be greater than 0 when 'up' transition is not 'none'",
-            )
-            .exit();
+    fn try_resolve_scenario(&self) -> Result<Scenario, String> {
+        let mut scenario = match &self.scenario {
+            Some(path) => Scenario::from_file(path)?,
+            None => Scenario::default(),
+        };
+
+        // Producer configuration
+        let p = &mut scenario.producer;
+        if self.is_explicit("bootstrap_brokers") {
+            p.brokers = self.bootstrap_brokers.clone();
+        }
+        if self.is_explicit("client_id") {
+            p.client_id = self.client_id.clone();
         }
-        if cli.down != Transition::None && cli.down_sec == 0 {
-            let mut cmd = Cli::command();
-            cmd.error(
-                ErrorKind::InvalidValue,
-                "Argument 'down-sec' must be greater than 0 when 'down' transition is not 'none'",
-            )
-            .exit();
+        if self.is_explicit("partitioner") {
+            p.partitioner = self.partitioner;
+        }
+        for (k, v) in &self.config {
+            p.config.insert(k.clone(), v.clone());
         }
 
-        // Validate that non-zero values are assigned to `min_sec` and `max_sec`
-        if cli.min_sec == 0 || cli.max_sec == 0 {
-            let mut cmd = Cli::command();
-            cmd.error(ErrorKind::InvalidValue, "Arguments 'min/max' must be greater than 0").exit();
+        // Record generation configuration
+        let r = &mut scenario.records;
+        if self.is_explicit("topic") {
+            r.topic = self.topic.clone();
+        }
+        if self.is_explicit("key") {
+            r.key = self.key.clone();
+        }
+        if self.is_explicit("payload") {
+            r.payload = self.payload.clone();
+        }
+        if self.is_explicit("partition") {
+            r.partition = self.partition;
+        }
+        for (k, v) in &self.headers {
+            r.headers.insert(k.clone(), v.clone());
+        }
+        for (k, seq) in &self.header_seqs {
+            r.header_seqs.insert(k.clone(), seq.clone());
+        }
+        if self.is_explicit("schema_registry") {
+            r.schema_registry = self.schema_registry.clone();
+        }
+
+        // Workload configuration: the 4 phases, the segments, the trace, the waveform, the rate expression and the
+        // composition replace each other
+        let w = &mut scenario.workload;
+        let phases_args = ["min", "min_sec", "max", "max_sec", "up", "up_sec", "down", "down_sec"];
+        if phases_args.iter().any(|id| self.is_explicit(id)) {
+            w.segments.clear();
+            w.trace = None;
+            w.waveform = None;
+            w.rate_expr = None;
+            w.compose = None;
+        }
+        if self.is_explicit("min") {
+            w.min = self.min;
+        }
+        if self.is_explicit("min_sec") {
+            w.min_sec = self.min_sec;
+        }
+        if self.is_explicit("max") {
+            w.max = self.max;
+        }
+        if self.is_explicit("max_sec") {
+            w.max_sec = self.max_sec;
+        }
+        if self.is_explicit("up") {
+            w.up = self.up;
+        }
+        if self.is_explicit("up_sec") {
+            w.up_sec = self.up_sec;
+        }
+        if self.is_explicit("down") {
+            w.down = self.down;
+        }
+        if self.is_explicit("down_sec") {
+            w.down_sec = self.down_sec;
+        }
+        if !self.segments.is_empty() {
+            w.segments = self.segments.clone();
+            w.min = None;
+            w.max = None;
+            w.trace = None;
+            w.waveform = None;
+            w.rate_expr = None;
+            w.compose = None;
+        }
+        if self.trace.is_some() {
+            w.trace = self.trace.clone();
+            w.min = None;
+            w.max = None;
+            w.segments.clear();
+            w.waveform = None;
+            w.rate_expr = None;
+            w.compose = None;
+        }
+        if self.waveform.is_some() {
+            w.waveform = self.waveform;
+            w.min = None;
+            w.max = None;
+            w.segments.clear();
+            w.trace = None;
+            w.rate_expr = None;
+            w.compose = None;
+        }
+        if self.rate_expr.is_some() {
+            w.rate_expr = self.rate_expr.clone();
+            w.min = None;
+            w.max = None;
+            w.segments.clear();
+            w.trace = None;
+            w.waveform = None;
+            w.compose = None;
+        }
+        if self.is_explicit("rate_expr_period") {
+            w.rate_expr_period = self.rate_expr_period;
+        }
+        if self.is_explicit("trace_transition") {
+            w.trace_transition = self.trace_transition;
+        }
+        if self.is_explicit("trace_loop") {
+            w.trace_loop = self.trace_loop;
+        }
+        if self.anchor_tz.is_some() {
+            w.anchor_tz = self.anchor_tz;
+        }
+        if self.is_explicit("anchor_time") {
+            w.anchor_time = self.anchor_time;
+        }
+        if self.is_explicit("time_scale") {
+            w.time_scale = self.time_scale;
+        }
+        if self.is_explicit("time_scale_keep") {
+            w.time_scale_keep = self.time_scale_keep;
+        }
+        if self.bursts.is_some() {
+            w.bursts = self.bursts;
+        }
+        if self.noise.is_some() {
+            w.noise = self.noise;
+        }
+        if self.warm_up.is_some() {
+            w.warm_up = self.warm_up;
+        }
+        if self.cool_down.is_some() {
+            w.cool_down = self.cool_down;
+        }
+        if self.is_explicit("pacing") {
+            w.pacing = self.pacing;
+        }
+        if self.is_explicit("unit") {
+            w.unit = self.unit;
+        }
+
+        // Run configuration
+        let l = &mut scenario.run;
+        if self.is_explicit("cycles") {
+            l.cycles = self.cycles;
+        }
+        if self.is_explicit("duration") {
+            l.duration = self.duration;
+        }
+        if self.is_explicit("total_records") {
+            l.total_records = self.total_records;
+        }
+        if self.is_explicit("seed") {
+            l.seed = self.seed;
         }
 
-        cli
+        // A dry-run doesn't produce records: only what's needed to plot the workload has to be valid
+        if self.dry_run.is_some() {
+            scenario.workload.validate()?;
+            scenario.run.validate()?;
+        } else {
+            scenario.validate()?;
+        }
+        Ok(scenario)
+    }
+
+    /// Returns `true` if the argument `id` should be applied to the [`Scenario`]:
+    /// without a scenario file, all arguments are, otherwise only those explicitly provided.
+    fn is_explicit(&self, id: &str) -> bool {
+        self.scenario.is_none() || self.explicit_args.contains(id)
     }
 
     pub fn verbosity_level(&self) -> i8 {
@@ -197,3 +709,168 @@ fn kv_clap_value_parser(kv: &str) -> Result<KVPair, String> {
 
     Ok((k.to_string(), v.to_string()))
 }
+
+fn seq_kv_clap_value_parser(kv: &str) -> Result<(String, Sequence), String> {
+    let (k, options) = kv_clap_value_parser(kv)?;
+
+    Ok((k, Sequence::parse(&options)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn cli_from(args: &[&str]) -> Cli {
+        let matches = Cli::command().try_get_matches_from(args).unwrap();
+        Cli::from_matches(&matches).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_scenario_without_file() {
+        let cli = cli_from(&["ksunami", "-b", "localhost:9092", "-t", "a_topic", "--min", "1", "--max", "10"]);
+        let scenario = cli.try_resolve_scenario().unwrap();
+
+        assert_eq!(Some("localhost:9092".to_string()), scenario.producer.brokers);
+        assert_eq!("ksunami", scenario.producer.client_id);
+        assert_eq!(Some("a_topic".to_string()), scenario.records.topic);
+        assert_eq!(Some(1.0), scenario.workload.min);
+        assert_eq!(Some(10.0), scenario.workload.max);
+        assert_eq!(WorkloadScenario::default().up_sec, scenario.workload.up_sec);
+
+        let cli = cli_from(&["ksunami", "-b", "localhost:9092", "-t", "a_topic", "--min", "10", "--max", "1"]);
+        assert_eq!(Err("Workload 'min' must be less than 'max'".to_string()), cli.try_resolve_scenario());
+
+        let cli =
+            cli_from(&["ksunami", "-b", "localhost:9092", "-t", "a_topic", "--trace", "trace.csv", "--trace-loop"]);
+        let scenario = cli.try_resolve_scenario().unwrap();
+        assert_eq!(Some(PathBuf::from("trace.csv")), scenario.workload.trace);
+        assert!(scenario.workload.trace_loop);
+        assert_eq!(None, scenario.workload.min);
+
+        let cli = cli_from(&["ksunami", "-b", "localhost:9092", "-t", "a_topic", "--waveform", "sine:100:200:600"]);
+        let scenario = cli.try_resolve_scenario().unwrap();
+        assert_eq!(Waveform::clap_parser("sine:100:200:600").ok(), scenario.workload.waveform);
+        assert_eq!(None, scenario.workload.max);
+
+        let cli = cli_from(&["ksunami", "--dry-run", "--rate-expr", "100 + t", "--rate-expr-period", "60"]);
+        let scenario = cli.try_resolve_scenario().unwrap();
+        assert_eq!("100 + t", scenario.workload.rate_expr.unwrap().to_string());
+        assert_eq!(60, scenario.workload.rate_expr_period);
+        assert!(Cli::command().try_get_matches_from(["ksunami", "--dry-run", "--rate-expr", "100 + x"]).is_err());
+
+        // The anchor time requires the anchor timezone
+        assert!(Cli::command()
+            .try_get_matches_from(["ksunami", "--min", "1", "--max", "10", "--dry-run", "--anchor-time", "09:00"])
+            .is_err());
+        let cli = cli_from(&[
+            "ksunami",
+            "--min",
+            "1",
+            "--max",
+            "10",
+            "--dry-run",
+            "--anchor-tz",
+            "UTC",
+            "--anchor-time",
+            "09:00",
+        ]);
+        let scenario = cli.try_resolve_scenario().unwrap();
+        assert_eq!(Some(AnchorTimezone::Utc), scenario.workload.anchor_tz);
+        assert_eq!(AnchorTime::clap_parser("09:00").unwrap(), scenario.workload.anchor_time);
+
+        let cli = cli_from(&["ksunami", "--min", "1", "--max", "10", "--dry-run", "--time-scale", "24"]);
+        let scenario = cli.try_resolve_scenario().unwrap();
+        assert_eq!(24.0, scenario.workload.time_scale);
+        assert_eq!(TimeScaleKeep::Rate, scenario.workload.time_scale_keep);
+
+        let cli = cli_from(&["ksunami", "--min", "1", "--max", "10", "--dry-run", "--bursts", "600:500:30:linear"]);
+        let scenario = cli.try_resolve_scenario().unwrap();
+        assert_eq!(Bursts::clap_parser("600:500:30:linear").ok(), scenario.workload.bursts);
+
+        let cli = cli_from(&["ksunami", "--min", "1", "--max", "10", "--dry-run", "--noise", "poisson"]);
+        let scenario = cli.try_resolve_scenario().unwrap();
+        assert_eq!(Some(Noise::Poisson), scenario.workload.noise);
+
+        let cli =
+            cli_from(&["ksunami", "--min", "1", "--max", "10", "--dry-run", "--warm-up", "30", "--cool-down", "60"]);
+        let scenario = cli.try_resolve_scenario().unwrap();
+        assert_eq!(Ramp::clap_parser("30").ok(), scenario.workload.warm_up);
+        assert_eq!(Ramp::clap_parser("60").ok(), scenario.workload.cool_down);
+
+        let cli = cli_from(&["ksunami", "--min", "1", "--max", "10", "--dry-run"]);
+        assert_eq!(Some(PlotFormat::Chart), cli.dry_run);
+        assert!(cli.try_resolve_scenario().is_ok());
+    }
+
+    #[test]
+    fn test_resolve_scenario_with_file_overrides() {
+        let path = std::env::temp_dir().join(format!("ksunami-test-scenario-{}.toml", std::process::id()));
+        let mut f = std::fs::File::create(&path).unwrap();
+        writeln!(
+            f,
+            r#"
+            [producer]
+            brokers = "file-broker:9092"
+            client_id = "file-client"
+
+            [records]
+            topic = "file_topic"
+
+            [records.headers]
+            h1 = "file"
+            h2 = "file"
+
+            [workload]
+            min = 10
+            max = 100
+            min_sec = 30
+
+            [run]
+            duration = 3600
+            "#
+        )
+        .unwrap();
+
+        let cli = cli_from(&[
+            "ksunami",
+            "--scenario",
+            path.to_str().unwrap(),
+            "--brokers",
+            "cli-broker:9092",
+            "--head",
+            "h2:cli",
+            "--head-seq",
+            "h3:start=1",
+            "--max",
+            "200",
+            "--total-records",
+            "5000",
+        ]);
+        let scenario = cli.try_resolve_scenario().unwrap();
+
+        // Overridden by the command line
+        assert_eq!(Some("cli-broker:9092".to_string()), scenario.producer.brokers);
+        assert_eq!(Some(200.0), scenario.workload.max);
+        assert_eq!(Some(&"cli".to_string()), scenario.records.headers.get("h2"));
+        assert_eq!(Some(&Sequence::parse("start=1").unwrap()), scenario.records.header_seqs.get("h3"));
+        assert_eq!(Some(5000), scenario.run.total_records);
+
+        // From the file, despite the command line default values
+        assert_eq!("file-client", scenario.producer.client_id);
+        assert_eq!(Some("file_topic".to_string()), scenario.records.topic);
+        assert_eq!(Some(&"file".to_string()), scenario.records.headers.get("h1"));
+        assert_eq!(Some(10.0), scenario.workload.min);
+        assert_eq!(30, scenario.workload.min_sec);
+        assert_eq!(Some(3600), scenario.run.duration);
+
+        // Segments on the command line replace the 4 phases in the file
+        let cli = cli_from(&["ksunami", "--scenario", path.to_str().unwrap(), "--segment", "10:10"]);
+        let scenario = cli.try_resolve_scenario().unwrap();
+        assert_eq!(None, scenario.workload.min);
+        assert_eq!(vec![WorkloadSegment::new(10.0, 10, Transition::None)], scenario.workload.segments);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,316 @@
+use std::fmt;
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::workload::{RateSchedule, WorkloadPhase};
+
+/// How the parts of a [`ComposedSchedule`] are combined.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompositionOp {
+    /// The records/sec of all the parts are added up.
+    #[default]
+    Sum,
+
+    /// The highest records/sec among the parts prevails.
+    Max,
+
+    /// The parts follow one another, in order: each for 1 of its cycles.
+    Sequence,
+}
+
+impl CompositionOp {
+    /// Returns the "name" for the enum value, as used in scenario files and logs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompositionOp::Sum => "sum",
+            CompositionOp::Max => "max",
+            CompositionOp::Sequence => "sequence",
+        }
+    }
+}
+
+impl fmt::Display for CompositionOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// A [`RateSchedule`] composed of other [`RateSchedule`]s (its parts), combined by a [`CompositionOp`].
+///
+/// Each part keeps its own cycle: with [`CompositionOp::Sum`] and [`CompositionOp::Max`], the cycle of the
+/// composition is the least common multiple of the cycles of its repeating parts (extended, if needed, to the
+/// smallest multiple that lets the parts followed only once complete); with [`CompositionOp::Sequence`],
+/// it's their sum.
+#[derive(Debug)]
+pub struct ComposedSchedule {
+    op: CompositionOp,
+    parts: Vec<Arc<dyn RateSchedule>>,
+
+    /// Second of the cycle each part starts at, when in [`CompositionOp::Sequence`].
+    starts_sec: Vec<u64>,
+}
+
+impl ComposedSchedule {
+    pub fn new(op: CompositionOp, parts: Vec<Arc<dyn RateSchedule>>) -> ComposedSchedule {
+        assert!(!parts.is_empty(), "Composition requires at least 1 part");
+
+        let starts_sec = parts
+            .iter()
+            .scan(0, |start_sec, part| {
+                let part_start_sec = *start_sec;
+                *start_sec += part.overall_duration_sec() as u64;
+                Some(part_start_sec)
+            })
+            .collect();
+
+        ComposedSchedule {
+            op,
+            parts,
+            starts_sec,
+        }
+    }
+
+    /// Given `sec`, returns the part of a [`CompositionOp::Sequence`] it falls into, and the second of that part.
+    ///
+    /// The second of the part keeps counting across cycles of the composition, so that parts
+    /// depending on the absolute second (ex. random bursts) don't repeat themselves every cycle.
+    fn sequence_part_at(&self, sec: u64) -> (&dyn RateSchedule, u64) {
+        let cycle_sec = self.starts_sec.last().unwrap() + self.parts.last().unwrap().overall_duration_sec() as u64;
+        let (cycle, nor_sec) = (sec / cycle_sec, sec % cycle_sec);
+
+        // Index of the first part that starts after `nor_sec`: we need the one before it
+        let idx = self.starts_sec.partition_point(|start_sec| *start_sec <= nor_sec) - 1;
+        let part = self.parts[idx].as_ref();
+
+        (part, cycle * part.overall_duration_sec() as u64 + nor_sec - self.starts_sec[idx])
+    }
+}
+
+impl RateSchedule for ComposedSchedule {
+    fn records_per_sec_at(&self, sec: u64) -> f64 {
+        match self.op {
+            CompositionOp::Sum => self.parts.iter().map(|p| p.records_per_sec_at(sec)).sum(),
+            CompositionOp::Max => self.parts.iter().map(|p| p.records_per_sec_at(sec)).fold(0.0, f64::max),
+            CompositionOp::Sequence => {
+                let (part, part_sec) = self.sequence_part_at(sec);
+                part.records_per_sec_at(part_sec)
+            },
+        }
+    }
+
+    /// With [`CompositionOp::Max`] and [`CompositionOp::Sequence`], it's the phase of the part that prevails.
+    /// With [`CompositionOp::Sum`], it depends on where the sum is headed.
+    fn phase_at(&self, sec: u64) -> WorkloadPhase {
+        match self.op {
+            CompositionOp::Sum => {
+                let (curr, next) = (self.records_per_sec_at(sec), self.records_per_sec_at(sec + 1));
+                if next > curr {
+                    WorkloadPhase::Up
+                } else if next < curr {
+                    WorkloadPhase::Down
+                } else {
+                    WorkloadPhase::Hold
+                }
+            },
+            CompositionOp::Max => {
+                let rates = self.parts.iter().map(|p| p.records_per_sec_at(sec));
+                let (idx, _) = rates.enumerate().fold((0, f64::MIN), |a, b| {
+                    if b.1 > a.1 {
+                        b
+                    } else {
+                        a
+                    }
+                });
+                self.parts[idx].phase_at(sec)
+            },
+            CompositionOp::Sequence => {
+                let (part, part_sec) = self.sequence_part_at(sec);
+                part.phase_at(part_sec)
+            },
+        }
+    }
+
+    fn max_records_per_sec(&self) -> f64 {
+        let maxes = self.parts.iter().map(|p| p.max_records_per_sec());
+
+        match self.op {
+            CompositionOp::Sum => maxes.sum(),
+            CompositionOp::Max | CompositionOp::Sequence => maxes.fold(0.0, f64::max),
+        }
+    }
+
+    /// The cycle of a composition, in seconds: capped to [`u32::MAX`] seconds.
+    ///
+    /// With [`CompositionOp::Sum`] and [`CompositionOp::Max`], parts followed only once don't repeat, so they
+    /// don't take part in the least common multiple: their duration is only a lower bound of the cycle.
+    fn overall_duration_sec(&self) -> u32 {
+        let durations = self.parts.iter().map(|p| p.overall_duration_sec() as u64);
+
+        let duration_sec = match self.op {
+            CompositionOp::Sum | CompositionOp::Max => {
+                let (once, repeating): (Vec<_>, Vec<_>) = self.parts.iter().partition(|p| p.is_once());
+                let lcm = repeating
+                    .iter()
+                    .map(|p| p.overall_duration_sec() as u64)
+                    .fold(1, |a, b| (a / gcd(a, b)).saturating_mul(b));
+                let once_sec = once.iter().map(|p| p.overall_duration_sec() as u64).max().unwrap_or_default();
+
+                // Smallest multiple of the repeating cycle, long enough for the parts followed only once
+                once_sec.div_ceil(lcm).max(1).saturating_mul(lcm)
+            },
+            CompositionOp::Sequence => durations.sum(),
+        };
+
+        duration_sec.min(u32::MAX as u64) as u32
+    }
+}
+
+/// Greatest common divisor of `a` and `b`.
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// A [`RateSchedule`] followed only once: after its first cycle, there are no more records to produce.
+///
+/// Useful as part of a [`ComposedSchedule`], to describe one-off events (ex. a spike at minute 30).
+#[derive(Debug)]
+pub struct OnceSchedule {
+    schedule: Arc<dyn RateSchedule>,
+}
+
+impl OnceSchedule {
+    pub fn new(schedule: Arc<dyn RateSchedule>) -> OnceSchedule {
+        OnceSchedule {
+            schedule,
+        }
+    }
+
+    fn is_over(&self, sec: u64) -> bool {
+        sec >= self.schedule.overall_duration_sec() as u64
+    }
+}
+
+impl RateSchedule for OnceSchedule {
+    fn records_per_sec_at(&self, sec: u64) -> f64 {
+        if self.is_over(sec) {
+            0.0
+        } else {
+            self.schedule.records_per_sec_at(sec)
+        }
+    }
+
+    fn phase_at(&self, sec: u64) -> WorkloadPhase {
+        if self.is_over(sec) {
+            WorkloadPhase::Min
+        } else {
+            self.schedule.phase_at(sec)
+        }
+    }
+
+    fn max_records_per_sec(&self) -> f64 {
+        self.schedule.max_records_per_sec()
+    }
+
+    fn overall_duration_sec(&self) -> u32 {
+        self.schedule.overall_duration_sec()
+    }
+
+    fn is_once(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transition::Transition;
+    use crate::workload::{Workload, WorkloadSegment};
+
+    use super::*;
+
+    fn segments(segments: &[(f64, u32)]) -> Arc<dyn RateSchedule> {
+        Arc::new(Workload::from_segments(
+            segments.iter().map(|(rate, sec)| WorkloadSegment::new(*rate, *sec, Transition::None)).collect(),
+        ))
+    }
+
+    fn rates(schedule: &dyn RateSchedule, secs: u64) -> Vec<f64> {
+        (0..secs).map(|sec| schedule.records_per_sec_at(sec)).collect()
+    }
+
+    #[test]
+    fn test_sum_and_max() {
+        let baseline = segments(&[(200.0, 1)]);
+        let wave = segments(&[(0.0, 2), (500.0, 2)]);
+        let spike = segments(&[(0.0, 3), (1000.0, 3)]);
+
+        let sum = ComposedSchedule::new(CompositionOp::Sum, vec![baseline.clone(), wave.clone(), spike.clone()]);
+        assert_eq!(12, sum.overall_duration_sec());
+        assert_eq!(1700.0, sum.max_records_per_sec());
+        assert_eq!(
+            vec![200.0, 200.0, 700.0, 1700.0, 1200.0, 1200.0, 700.0, 700.0, 200.0, 1200.0, 1700.0, 1700.0, 200.0],
+            rates(&sum, 13)
+        );
+        assert_eq!(WorkloadPhase::Up, sum.phase_at(1));
+        assert_eq!(WorkloadPhase::Down, sum.phase_at(3));
+        assert_eq!(WorkloadPhase::Hold, sum.phase_at(4));
+
+        let max = ComposedSchedule::new(CompositionOp::Max, vec![baseline, wave, spike]);
+        assert_eq!(12, max.overall_duration_sec());
+        assert_eq!(1000.0, max.max_records_per_sec());
+        assert_eq!(vec![200.0, 200.0, 500.0, 1000.0, 1000.0, 1000.0, 500.0], rates(&max, 7));
+        assert_eq!(WorkloadPhase::Max, max.phase_at(3));
+    }
+
+    #[test]
+    fn test_sequence() {
+        let warm = segments(&[(10.0, 2)]);
+        let busy = segments(&[(100.0, 1), (200.0, 1)]);
+
+        let sequence = ComposedSchedule::new(CompositionOp::Sequence, vec![warm, busy]);
+        assert_eq!(4, sequence.overall_duration_sec());
+        assert_eq!(200.0, sequence.max_records_per_sec());
+        assert_eq!(vec![10.0, 10.0, 100.0, 200.0, 10.0, 10.0, 100.0, 200.0], rates(&sequence, 8));
+        assert_eq!(WorkloadPhase::Max, sequence.phase_at(3));
+    }
+
+    #[test]
+    fn test_once() {
+        // A spike at second 3, only the first time
+        let spike = Arc::new(OnceSchedule::new(segments(&[(0.0, 3), (1000.0, 1)])));
+        let sum = ComposedSchedule::new(CompositionOp::Sum, vec![segments(&[(1.0, 2)]), spike]);
+        assert_eq!(vec![1.0, 1.0, 1.0, 1001.0, 1.0, 1.0, 1.0, 1.0, 1.0], rates(&sum, 9));
+        assert_eq!(4, sum.overall_duration_sec());
+    }
+
+    #[test]
+    fn test_once_out_of_cycle() {
+        // A one-off spike doesn't stretch the cycle to the least common multiple: only to fit the spike
+        let spike = Arc::new(OnceSchedule::new(segments(&[(0.0, 3600), (1000.0, 60)])));
+        let wave = segments(&[(0.0, 60), (100.0, 60)]);
+        let sum = ComposedSchedule::new(CompositionOp::Sum, vec![wave.clone(), spike.clone()]);
+        assert_eq!(3720, sum.overall_duration_sec());
+        let max = ComposedSchedule::new(CompositionOp::Max, vec![wave, spike.clone()]);
+        assert_eq!(3720, max.overall_duration_sec());
+
+        let only_once = ComposedSchedule::new(CompositionOp::Max, vec![spike.clone()]);
+        assert_eq!(3660, only_once.overall_duration_sec());
+
+        let odd = ComposedSchedule::new(CompositionOp::Sum, vec![segments(&[(1.0, 7)]), spike]);
+        assert_eq!(3661, odd.overall_duration_sec());
+    }
+
+    #[test]
+    fn test_overall_duration_capped() {
+        let long = segments(&[(1.0, u32::MAX - 1)]);
+        let sum = ComposedSchedule::new(CompositionOp::Sum, vec![long.clone(), segments(&[(1.0, 7)])]);
+        assert_eq!(u32::MAX, sum.overall_duration_sec());
+        let sequence = ComposedSchedule::new(CompositionOp::Sequence, vec![long.clone(), long]);
+        assert_eq!(u32::MAX, sequence.overall_duration_sec());
+    }
+}
@@ -7,13 +7,21 @@ use rand::distributions::{Alphanumeric, DistString};
 use rand::{thread_rng, Rng};
 use rdkafka::message::{Header, OwnedHeaders};
 use rdkafka::producer::FutureRecord;
+use serde::{de, Deserialize, Deserializer};
+
+use crate::avro::AvroSchema;
+use crate::json_schema::JsonSchema;
+use crate::protobuf::ProtobufSchema;
+use crate::random::{RandomStream, Randomness};
+use crate::sequence::Sequence;
+use crate::template::Template;
 
 /// Helps to generate a possible value used in [`RecordGenerator`].
 ///
 /// Specifically, this is used for the [`RecordGenerator::key_field`] and [`RecordGenerator::payload_field`],
 /// to specify what content should be generated for those fields when a Kafka record is generated
 /// (via [`RecordGenerator::generate_record`]).
-#[derive(Debug, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ValueGenerator {
     /// A user provided string.
     String(String),
@@ -32,11 +40,39 @@ pub enum ValueGenerator {
 
     /// A random float.
     RandFloat(f64, f64),
+
+    /// A text with placeholders, filled in for every record.
+    Template(Template),
+
+    /// A JSON document, valid against a JSON Schema.
+    JsonSchema(JsonSchema),
+
+    /// An Avro datum, valid against an Avro schema.
+    Avro(AvroSchema),
+
+    /// A Protobuf message, of a given message type.
+    Protobuf(ProtobufSchema),
+
+    /// A monotonic sequence of integers.
+    Sequence(Sequence),
+}
+
+/// What's known of a record, while its content is generated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct RecordContext<'a> {
+    /// Index of the record, since the beginning of the run
+    pub index: u64,
+
+    /// Key of the record, once generated
+    pub key: Option<&'a [u8]>,
 }
 
 impl ValueGenerator {
-    /// Generates a `Vec<u8>` of bytes containing the value created by this generator, or an error.
-    fn generate(&self) -> Result<Vec<u8>, Error> {
+    /// Generates a `Vec<u8>` of bytes containing the value created by this generator for the given `record`,
+    /// or an error.
+    ///
+    /// Random values are drawn from the given `rng`.
+    fn generate<R: Rng>(&self, rng: &mut R, record: &RecordContext) -> Result<Vec<u8>, Error> {
         match self {
             ValueGenerator::String(s) => Ok(s.as_bytes().to_vec()),
             ValueGenerator::File(bp) => {
@@ -48,25 +84,51 @@ impl ValueGenerator {
                 Ok(buf)
             },
             ValueGenerator::RandAlphaNum(l) => {
-                let rand_alpha = Alphanumeric.sample_string(&mut thread_rng(), *l);
+                let rand_alpha = Alphanumeric.sample_string(rng, *l);
 
                 Ok(rand_alpha.as_bytes().to_vec())
             },
             ValueGenerator::RandBytes(l) => {
-                let random_bytes: Vec<u8> = (0..*l).map(|_| thread_rng().gen::<u8>()).collect();
+                let random_bytes: Vec<u8> = (0..*l).map(|_| rng.gen::<u8>()).collect();
 
                 Ok(random_bytes)
             },
             ValueGenerator::RandInt(min, max) => {
-                let random_int = thread_rng().gen_range(*min..=*max);
+                let random_int = rng.gen_range(*min..=*max);
 
                 Ok(random_int.to_be_bytes().to_vec())
             },
             ValueGenerator::RandFloat(min, max) => {
-                let random_float = thread_rng().gen_range(*min..=*max);
+                let random_float = rng.gen_range(*min..=*max);
 
                 Ok(random_float.to_be_bytes().to_vec())
             },
+            ValueGenerator::Template(t) => Ok(t.render(rng, record).into_bytes()),
+            ValueGenerator::JsonSchema(s) => Ok(serde_json::to_vec(&s.sample(rng))?),
+            ValueGenerator::Avro(s) => Ok(s.sample(rng)),
+            ValueGenerator::Protobuf(s) => Ok(s.sample(rng)?),
+            ValueGenerator::Sequence(s) => Ok(s.generate(record)),
+        }
+    }
+
+    /// Upper bound of the number of distinct values generated: `None` if unbounded, or not known.
+    pub fn max_distinct(&self) -> Option<u64> {
+        match self {
+            ValueGenerator::String(_) | ValueGenerator::File(_) => Some(1),
+            ValueGenerator::RandAlphaNum(l) => u32::try_from(*l).ok().and_then(|l| 62u64.checked_pow(l)),
+            ValueGenerator::RandBytes(l) => u32::try_from(*l).ok().and_then(|l| 256u64.checked_pow(l)),
+            ValueGenerator::RandInt(min, max) => u64::try_from(*max as i128 - *min as i128 + 1).ok(),
+            ValueGenerator::JsonSchema(s) => Some(s.max_distinct()).filter(|d| *d < u64::MAX),
+            _ => None,
+        }
+    }
+
+    /// The [`Sequence`]s this generator draws values from: itself, or the `{{seq}}` placeholders of a template.
+    pub fn sequences(&self) -> Vec<&Sequence> {
+        match self {
+            ValueGenerator::Sequence(s) => vec![s],
+            ValueGenerator::Template(t) => t.sequences().collect(),
+            _ => vec![],
         }
     }
 
@@ -80,6 +142,12 @@ impl ValueGenerator {
     /// * `bytes:LENGTH`: `LENGTH` is parsed to `usize` length of a randomly generated bytes array
     /// * `int:MIN-MAX`: `MIN` and `MAX` are parsed to `i64`s of an inclusive range from which to pick a random `i64`
     /// * `float:MIN-MAX`: `MIN` and `MAX` are parsed to `f64`s of an inclusive range from which to pick a random `f64`
+    /// * `template:TEMPLATE`: `TEMPLATE` is parsed to a [`Template`], with placeholders filled in for every record
+    /// * `jsonschema:PATH`: `PATH` is parsed to `PathBuf` to a JSON Schema file, loaded to a [`JsonSchema`]
+    /// * `avro:PATH`: `PATH` is parsed to `PathBuf` to an Avro schema file (`.avsc`), loaded to an [`AvroSchema`]
+    /// * `protobuf:PATH:MESSAGE`: `PATH` is parsed to `PathBuf` to a `.proto` file (or descriptor set),
+    ///   from which the `MESSAGE` type is loaded to a [`ProtobufSchema`]
+    /// * `seq:OPTIONS`: `OPTIONS` is parsed to a [`Sequence`] (ex. `start=1,step=1,per=key,encoding=be`)
     ///
     /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
     ///
@@ -150,11 +218,25 @@ impl ValueGenerator {
                     Ok(ValueGenerator::RandFloat(min, max))
                 },
             },
+            "template" => Template::parse(val_gen_input).map(ValueGenerator::Template),
+            "jsonschema" => JsonSchema::load(&PathBuf::from(val_gen_input)).map(ValueGenerator::JsonSchema),
+            "avro" => AvroSchema::load(&PathBuf::from(val_gen_input)).map(ValueGenerator::Avro),
+            "protobuf" => ProtobufSchema::load(val_gen_input).map(ValueGenerator::Protobuf),
+            "seq" => Sequence::parse(val_gen_input).map(ValueGenerator::Sequence),
             _ => Err(format!("Unsupported TYPE '{val_gen_type}:...'")),
         }
     }
 }
 
+/// A [`ValueGenerator`] is deserialized from the same `TYPE:INPUT` format parsed by [`ValueGenerator::clap_parser`].
+impl<'de> Deserialize<'de> for ValueGenerator {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let val_gen_as_str = String::deserialize(deserializer)?;
+
+        ValueGenerator::clap_parser(&val_gen_as_str).map_err(de::Error::custom)
+    }
+}
+
 /// The data of a Kafka Record, as generated by [`RecordGenerator`].
 ///
 /// The `key` and `payload` value are `Vec<u8>`,
@@ -169,8 +251,17 @@ pub struct GeneratedRecord {
 }
 
 impl GeneratedRecord {
+    /// Size of the record content, in bytes: key, payload and headers (keys and values).
+    pub fn size_bytes(&self) -> usize {
+        let key = self.key.as_ref().map_or(0, |k| k.len());
+        let payload = self.payload.as_ref().map_or(0, |p| p.len());
+        let headers = self.headers.iter().map(|(k, v)| k.len() + v.len()).sum::<usize>();
+
+        key + payload + headers
+    }
+
     /// Converts the `GeneratedRecord` into a [`rdkafka::producer::FutureRecord`], usable with [`rdkafka::producer::FutureProducer`].
-    pub fn as_future_record(&self) -> FutureRecord<Vec<u8>, Vec<u8>> {
+    pub fn as_future_record(&self) -> FutureRecord<'_, Vec<u8>, Vec<u8>> {
         let mut rec: FutureRecord<Vec<u8>, Vec<u8>> = FutureRecord::to(self.topic.as_str());
 
         // Set record key (if available)
@@ -226,9 +317,15 @@ pub struct RecordGenerator {
     /// Headers that will be added to the record.
     headers: HashMap<String, String>,
 
+    /// Headers that will be added to the record, with the next value of a [`Sequence`].
+    sequence_headers: HashMap<String, Sequence>,
+
     /// Topic partition the record is destined to.
     /// If absent, this will be left to the Kafka Producer partitioner to determine.
     destination_partition: Option<i32>,
+
+    /// Source of the random content of the records.
+    randomness: Randomness,
 }
 
 impl RecordGenerator {
@@ -240,7 +337,9 @@ impl RecordGenerator {
             payload_generator: None,
             payload_generated_content: None,
             headers: HashMap::new(),
+            sequence_headers: HashMap::new(),
             destination_partition: None,
+            randomness: Randomness::default(),
         }
     }
 
@@ -248,10 +347,16 @@ impl RecordGenerator {
         self.headers.insert(k, v);
     }
 
+    pub fn add_record_sequence_header(&mut self, k: String, seq: Sequence) {
+        self.sequence_headers.insert(k, seq);
+    }
+
     pub fn set_key_generator(&mut self, key_generator: ValueGenerator) -> Result<(), Error> {
         // Memoize content, if appropriate
         self.key_generated_content = match key_generator {
-            ValueGenerator::String(_) | ValueGenerator::File(_) => Some(key_generator.generate()?),
+            ValueGenerator::String(_) | ValueGenerator::File(_) => {
+                Some(key_generator.generate(&mut thread_rng(), &RecordContext::default())?)
+            },
             _ => None,
         };
 
@@ -263,7 +368,9 @@ impl RecordGenerator {
     pub fn set_payload_generator(&mut self, payload_generator: ValueGenerator) -> Result<(), Error> {
         // Memoize content, if appropriate
         self.payload_generated_content = match payload_generator {
-            ValueGenerator::String(_) | ValueGenerator::File(_) => Some(payload_generator.generate()?),
+            ValueGenerator::String(_) | ValueGenerator::File(_) => {
+                Some(payload_generator.generate(&mut thread_rng(), &RecordContext::default())?)
+            },
             _ => None,
         };
 
@@ -276,28 +383,50 @@ impl RecordGenerator {
         self.destination_partition = Some(partition);
     }
 
-    pub fn generate_record(&self) -> Result<GeneratedRecord, Error> {
-        let rec = GeneratedRecord {
-            topic: self.destination_topic.clone(),
-            key: if let Some(k_mem) = &self.key_generated_content {
-                Some(k_mem.to_vec())
-            } else if let Some(k) = &self.key_generator {
-                Some(k.generate()?)
-            } else {
-                None
-            },
-            payload: if let Some(p_mem) = &self.payload_generated_content {
-                Some(p_mem.to_vec())
-            } else if let Some(p) = &self.payload_generator {
-                Some(p.generate()?)
-            } else {
-                None
-            },
-            partition: self.destination_partition,
-            headers: self.headers.clone(),
+    pub fn set_randomness(&mut self, randomness: Randomness) {
+        self.randomness = randomness;
+    }
+
+    /// Generates the `index`-th record.
+    ///
+    /// When the [`Randomness`] is seeded, the content of a record only depends on its `index`: records can
+    /// be generated in any order (or in parallel), and the same `index` always generates the same record.
+    /// The exception are [`Sequence`]s counted per key, that require records to be generated in order.
+    pub fn generate_record(&self, index: u64) -> Result<GeneratedRecord, Error> {
+        let mut record = RecordContext {
+            index,
+            key: None,
+        };
+
+        let key = if let Some(k_mem) = &self.key_generated_content {
+            Some(k_mem.to_vec())
+        } else if let Some(k) = &self.key_generator {
+            Some(k.generate(&mut self.randomness.rng(RandomStream::RecordKey, index), &record)?)
+        } else {
+            None
+        };
+        record.key = key.as_deref();
+
+        let payload = if let Some(p_mem) = &self.payload_generated_content {
+            Some(p_mem.to_vec())
+        } else if let Some(p) = &self.payload_generator {
+            Some(p.generate(&mut self.randomness.rng(RandomStream::RecordPayload, index), &record)?)
+        } else {
+            None
         };
 
-        Ok(rec)
+        let mut headers = self.headers.clone();
+        for (k, seq) in &self.sequence_headers {
+            headers.insert(k.clone(), seq.next(&record).to_string());
+        }
+
+        Ok(GeneratedRecord {
+            topic: self.destination_topic.clone(),
+            key,
+            payload,
+            partition: self.destination_partition,
+            headers,
+        })
     }
 }
 
@@ -312,7 +441,7 @@ mod tests {
         let mut generator = RecordGenerator::new("a_topic_name".to_string());
         assert!(generator.set_payload_generator(ValueGenerator::String("a payload content".to_string())).is_ok());
 
-        let rec = generator.generate_record().unwrap();
+        let rec = generator.generate_record(0).unwrap();
         assert_eq!("a_topic_name", rec.topic);
         assert_eq!(None, rec.key);
         assert_eq!("a payload content".as_bytes(), rec.payload.clone().unwrap());
@@ -328,7 +457,7 @@ mod tests {
         assert_eq!(0, fut_rec.headers.unwrap().count());
 
         generator.set_destination_partition(10);
-        let rec = generator.generate_record().unwrap();
+        let rec = generator.generate_record(0).unwrap();
         assert_eq!(Some(10), rec.partition);
 
         let fut_rec = rec.as_future_record();
@@ -346,7 +475,7 @@ mod tests {
 
         assert!(generator.set_key_generator(ValueGenerator::RandInt(10, 10)).is_ok());
 
-        let rec = generator.generate_record().unwrap();
+        let rec = generator.generate_record(0).unwrap();
         assert_eq!("another_topic", rec.topic);
         assert_eq!(10u64.to_be_bytes().to_vec(), rec.key.clone().unwrap());
         assert_eq!("another payload".as_bytes(), rec.payload.clone().unwrap());
@@ -358,6 +487,9 @@ mod tests {
 
         assert_eq!(None, rec.partition);
 
+        // 8 bytes key, 15 bytes payload, 3 headers of 2+2 bytes
+        assert_eq!(35, rec.size_bytes());
+
         let fut_rec = rec.as_future_record();
         assert_eq!("another_topic", fut_rec.topic);
         assert!(fut_rec.key.is_some());
@@ -373,7 +505,7 @@ mod tests {
         let mut generator = RecordGenerator::new("topic_zzz".to_string());
         assert!(generator.set_payload_generator(ValueGenerator::File(cargo_toml_path.clone())).is_ok());
 
-        let rec = generator.generate_record().unwrap();
+        let rec = generator.generate_record(0).unwrap();
         assert_eq!("topic_zzz", rec.topic);
         assert_eq!(None, rec.key);
         let mut f = File::open(cargo_toml_path).unwrap();
@@ -391,14 +523,14 @@ mod tests {
         assert!(generator.set_key_generator(ValueGenerator::RandBytes(20)).is_ok());
         assert!(generator.set_payload_generator(ValueGenerator::RandAlphaNum(20)).is_ok());
 
-        let rec = generator.generate_record().unwrap();
+        let rec = generator.generate_record(0).unwrap();
         assert_eq!(20, rec.key.unwrap().len());
         assert!(std::str::from_utf8(rec.payload.unwrap().as_slice()).is_ok());
 
         assert!(generator.set_payload_generator(ValueGenerator::RandInt(123, 125)).is_ok());
         assert!(generator.set_key_generator(ValueGenerator::RandFloat(1.5, 2.0)).is_ok());
 
-        let rec = generator.generate_record().unwrap();
+        let rec = generator.generate_record(0).unwrap();
         let rec_key = f64::from_be_bytes(rec.key.unwrap().as_slice().try_into().unwrap());
         assert!((1.5..=2.0).contains(&rec_key));
 
@@ -406,6 +538,82 @@ mod tests {
         assert!((123..=125).contains(&rec_payload));
     }
 
+    #[test]
+    fn test_seeded_randomizers() {
+        let mut generator = RecordGenerator::new("topic".to_string());
+        assert!(generator.set_key_generator(ValueGenerator::RandBytes(20)).is_ok());
+        assert!(generator.set_payload_generator(ValueGenerator::RandAlphaNum(20)).is_ok());
+        generator.set_randomness(Randomness::new(Some(42)));
+
+        // Records only depend on their index, regardless of the order they are generated in
+        let forward = (0..10).map(|i| generator.generate_record(i).unwrap()).collect::<Vec<_>>();
+        let mut backward = (0..10).rev().map(|i| generator.generate_record(i).unwrap()).collect::<Vec<_>>();
+        backward.reverse();
+        assert_eq!(forward, backward);
+
+        // Key and payload are independent, and differ across records
+        assert_ne!(forward[0].key, forward[0].payload);
+        assert_ne!(forward[0].key, forward[1].key);
+
+        // A different seed generates different records
+        generator.set_randomness(Randomness::new(Some(43)));
+        assert_ne!(forward[0], generator.generate_record(0).unwrap());
+    }
+
+    #[test]
+    fn test_template_payload() {
+        let mut generator = RecordGenerator::new("topic".to_string());
+        let template = ValueGenerator::clap_parser("template:{\"seq\":{{seq}},\"user\":\"{{alpha 8}}\"}").unwrap();
+        assert!(generator.set_payload_generator(template).is_ok());
+
+        // Filled in for every record: not memoized
+        let rec = generator.generate_record(3).unwrap();
+        let payload = String::from_utf8(rec.payload.unwrap()).unwrap();
+        assert!(payload.starts_with("{\"seq\":3,\"user\":\""));
+        assert_eq!(27, payload.len());
+        assert_ne!(generator.generate_record(4).unwrap().payload, generator.generate_record(5).unwrap().payload);
+    }
+
+    #[test]
+    fn test_sequences() {
+        let mut generator = RecordGenerator::new("topic".to_string());
+        assert!(generator.set_key_generator(ValueGenerator::clap_parser("int:1-2").unwrap()).is_ok());
+        assert!(generator
+            .set_payload_generator(ValueGenerator::clap_parser("seq:per=key,encoding=be").unwrap())
+            .is_ok());
+        generator.add_record_sequence_header("seq".to_string(), Sequence::parse("start=1").unwrap());
+        generator.set_randomness(Randomness::new(Some(42)));
+
+        let records: Vec<GeneratedRecord> = (0..20).map(|i| generator.generate_record(i).unwrap()).collect();
+
+        // Counted for all records
+        for (i, rec) in records.iter().enumerate() {
+            assert_eq!(Some(&(i + 1).to_string()), rec.headers.get("seq"));
+        }
+
+        // Counted for each key, without gaps
+        let mut counts = HashMap::new();
+        for rec in &records {
+            let count = counts.entry(rec.key.clone().unwrap()).or_insert(0i64);
+            assert_eq!(count.to_be_bytes().to_vec(), rec.payload.clone().unwrap());
+            *count += 1;
+        }
+        assert_eq!(2, counts.len());
+    }
+
+    #[test]
+    fn test_max_distinct() {
+        let max_distinct = |input| ValueGenerator::clap_parser(input).unwrap().max_distinct();
+        assert_eq!(Some(1), max_distinct("string:a"));
+        assert_eq!(Some(62 * 62), max_distinct("alpha:2"));
+        assert_eq!(Some(65536), max_distinct("bytes:2"));
+        assert_eq!(None, max_distinct("bytes:8"));
+        assert_eq!(Some(11), max_distinct("int:5-15"));
+        assert_eq!(None, ValueGenerator::RandInt(i64::MIN, i64::MAX).max_distinct());
+        assert_eq!(None, max_distinct("float:0-1"));
+        assert_eq!(None, max_distinct("seq:"));
+    }
+
     #[test]
     fn test_value_generator_clap_parser() {
         let res = ValueGenerator::clap_parser("string:StRiNgA");
@@ -431,6 +639,14 @@ mod tests {
         let res = ValueGenerator::clap_parser("float:11-213.1");
         assert!(res.is_ok());
         assert_eq!(ValueGenerator::RandFloat(11., 213.1), res.unwrap());
+
+        let res = ValueGenerator::clap_parser("template:id={{uuid}}");
+        assert!(res.is_ok());
+        assert_eq!(ValueGenerator::Template(Template::parse("id={{uuid}}").unwrap()), res.unwrap());
+
+        let res = ValueGenerator::clap_parser("seq:start=1,per=key");
+        assert!(res.is_ok());
+        assert_eq!(ValueGenerator::Sequence(Sequence::parse("start=1,per=key").unwrap()), res.unwrap());
     }
 
     #[test]
@@ -458,5 +674,25 @@ mod tests {
         let res = ValueGenerator::clap_parser("int:abc-asd");
         assert!(res.is_err());
         assert_eq!("Failed to parse INPUT 'MIN' from 'int:MIN-MAX': invalid digit found in string", res.unwrap_err());
+
+        let res = ValueGenerator::clap_parser("template:{{seq");
+        assert!(res.is_err());
+        assert_eq!("Unclosed placeholder at position 1 of TEMPLATE", res.unwrap_err());
+
+        let res = ValueGenerator::clap_parser("jsonschema:Cargo.toml");
+        assert!(res.is_err());
+        assert!(res.unwrap_err().starts_with("Failed to parse INPUT file 'Cargo.toml' as JSON"));
+
+        let res = ValueGenerator::clap_parser("avro:does_not_exist.avsc");
+        assert!(res.is_err());
+        assert!(res.unwrap_err().starts_with("Failed to read INPUT file 'does_not_exist.avsc'"));
+
+        let res = ValueGenerator::clap_parser("protobuf:does_not_exist.proto");
+        assert!(res.is_err());
+        assert_eq!("Should have 'PATH:MESSAGE' format", res.unwrap_err());
+
+        let res = ValueGenerator::clap_parser("seq:per=topic");
+        assert!(res.is_err());
+        assert_eq!("Unsupported value 'topic' of option 'per' of 'seq'", res.unwrap_err());
     }
 }
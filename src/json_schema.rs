@@ -0,0 +1,954 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, SecondsFormat};
+use rand::distributions::{Alphanumeric, DistString};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde_json::{Map, Value};
+
+use crate::random::{uuid_v4, DATE_TIME_RANGE_SEC, DEFAULT_MAX_ITEMS, DEFAULT_MAX_LENGTH};
+
+/// When not limited by the schema, how far apart the bounds of a random number are.
+const DEFAULT_NUMBER_RANGE: i64 = 1000;
+
+/// Number of characters random strings are made of (see [`Alphanumeric`]).
+const ALPHANUMERIC_CHARS: u64 = 62;
+
+/// Keywords that constrain documents in ways that the generated documents can't (yet) satisfy.
+const UNSUPPORTED_KEYWORDS: [&str; 11] = [
+    "allOf",
+    "not",
+    "if",
+    "pattern",
+    "patternProperties",
+    "contains",
+    "prefixItems",
+    "minProperties",
+    "dependentRequired",
+    "dependentSchemas",
+    "dependencies",
+];
+
+/// A [JSON Schema](https://json-schema.org/), loaded from a file, to generate valid JSON documents from.
+///
+/// The schema is loaded and validated once, via [`JsonSchema::load`], and then sampled for every record
+/// via [`JsonSchema::sample`]. Supported keywords are:
+///
+/// * `type` (also as an array of types), `enum` and `const`
+/// * `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum` and `multipleOf`, for `integer` and `number`
+/// * `minLength` and `maxLength`, or `format` (see [`StringFormat`]), for `string`
+/// * `items`, `minItems`, `maxItems` and `uniqueItems`, for `array`
+/// * `properties` and `required`, for `object`: optional properties are generated half of the times
+/// * `anyOf` and `oneOf`, generating from one of the subschemas, picked at random: those of `oneOf` must be
+///   disjoint (by `type`, or by `const` and `enum` values), so that documents match only one of them
+/// * `$ref`, to other parts of the same schema (ex. `#/$defs/address`), as long as they are not recursive
+///
+/// Annotations (ex. `title`, `description`) are ignored, while keywords listed in [`UNSUPPORTED_KEYWORDS`]
+/// are rejected: documents generated ignoring them, would not be valid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonSchema {
+    /// Path to the file the schema was loaded from
+    path: PathBuf,
+
+    /// The schema, compiled to what's needed to generate documents
+    root: Schema,
+}
+
+/// What to generate, as described by (a part of) a [`JsonSchema`].
+#[derive(Debug, Clone, PartialEq)]
+enum Schema {
+    Null,
+    Boolean,
+
+    /// A random integer in `min + k * step`, with `k` in `[0, steps]`.
+    Integer {
+        min: i64,
+        step: i64,
+        steps: u64,
+    },
+
+    /// A random number within `[min, max]`: bounds marked as exclusive are never generated.
+    Number {
+        min: f64,
+        max: f64,
+        exclusive_min: bool,
+        exclusive_max: bool,
+    },
+
+    /// A random multiple of `multiple_of`, in `multiple_of * k` with `k` in `[min_k, max_k]`.
+    NumberMultiple {
+        multiple_of: f64,
+        min_k: i64,
+        max_k: i64,
+    },
+
+    /// A random alphanumeric string, with length within `[min_length, max_length]`.
+    String {
+        min_length: usize,
+        max_length: usize,
+    },
+
+    Formatted(StringFormat),
+
+    Array {
+        items: Box<Schema>,
+        min_items: usize,
+        max_items: usize,
+        unique_items: bool,
+    },
+
+    /// An object with the given properties, each flagged as required or not.
+    Object(Vec<(String, Schema, bool)>),
+
+    /// One of the given values.
+    Enum(Vec<Value>),
+
+    /// Generated from one of the given schemas.
+    OneOf(Vec<Schema>),
+}
+
+/// The `format`s of `string` supported by [`JsonSchema`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum StringFormat {
+    DateTime,
+    Date,
+    Time,
+    Email,
+    Hostname,
+    Ipv4,
+    Ipv6,
+    Uri,
+    Uuid,
+}
+
+impl JsonSchema {
+    /// Loads the JSON Schema from the file at `path`, failing if it can't read, parse or support it.
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn load(path: &Path) -> Result<JsonSchema, String> {
+        let schema_as_str =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read INPUT file '{}': {e}", path.display()))?;
+        let schema_as_json: Value = serde_json::from_str(&schema_as_str)
+            .map_err(|e| format!("Failed to parse INPUT file '{}' as JSON: {e}", path.display()))?;
+
+        JsonSchema::from_json(path, &schema_as_json)
+    }
+
+    fn from_json(path: &Path, schema_as_json: &Value) -> Result<JsonSchema, String> {
+        let compiler = Compiler {
+            root: schema_as_json,
+        };
+
+        Ok(JsonSchema {
+            path: path.to_path_buf(),
+            root: compiler.compile(schema_as_json, "#", &mut Vec::new())?,
+        })
+    }
+
+    /// Samples a JSON document, valid against the schema, drawing random values from the given `rng`.
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> Value {
+        self.root.sample(rng)
+    }
+
+    /// Upper bound of the number of distinct documents generated: [`u64::MAX`] when (practically) unbounded.
+    pub fn max_distinct(&self) -> u64 {
+        self.root.max_distinct()
+    }
+}
+
+/// Compiles (parts of) the JSON Schema `root` into [`Schema`]s.
+struct Compiler<'a> {
+    root: &'a Value,
+}
+
+impl Compiler<'_> {
+    /// Compiles the `schema` found at `at` (a JSON Pointer, used to describe errors).
+    ///
+    /// `refs` are the `$ref` being compiled, used to detect recursive ones.
+    fn compile(&self, schema: &Value, at: &str, refs: &mut Vec<String>) -> Result<Schema, String> {
+        let err = |msg: String| format!("Invalid JSON Schema at '{at}': {msg}");
+
+        let Some(schema) = schema.as_object() else {
+            return Err(err("must be an object".to_string()));
+        };
+        if let Some(keyword) = UNSUPPORTED_KEYWORDS.iter().find(|k| schema.contains_key(**k)) {
+            return Err(err(format!("'{keyword}' is not supported")));
+        }
+
+        if let Some(reference) = schema.get("$ref") {
+            return self.compile_ref(reference, at, refs);
+        }
+        if let Some(value) = schema.get("const") {
+            return Ok(Schema::Enum(vec![value.clone()]));
+        }
+        if let Some(values) = schema.get("enum") {
+            return match values.as_array() {
+                Some(values) if !values.is_empty() => Ok(Schema::Enum(values.clone())),
+                _ => Err(err("'enum' must be a non-empty array".to_string())),
+            };
+        }
+        for keyword in ["anyOf", "oneOf"] {
+            if let Some(subschemas) = schema.get(keyword) {
+                let Some(subschemas) = subschemas.as_array().filter(|s| !s.is_empty()) else {
+                    return Err(err(format!("'{keyword}' must be a non-empty array")));
+                };
+                let subschemas = subschemas
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| self.compile(s, &format!("{at}/{keyword}/{i}"), refs))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // A document matching more than 1 subschema of `oneOf` would be invalid
+                if keyword == "oneOf" {
+                    for (i, a) in subschemas.iter().enumerate() {
+                        if let Some(j) = subschemas[i + 1..].iter().position(|b| !a.is_disjoint(b)) {
+                            return Err(err(format!(
+                                "'oneOf' subschemas {i} and {} must differ in 'type', or in 'const' and 'enum' values",
+                                i + 1 + j
+                            )));
+                        }
+                    }
+                }
+
+                return Ok(Schema::OneOf(subschemas));
+            }
+        }
+
+        let types = match schema.get("type") {
+            Some(Value::String(t)) => vec![t.as_str()],
+            Some(Value::Array(ts)) if !ts.is_empty() && ts.iter().all(Value::is_string) => {
+                ts.iter().filter_map(Value::as_str).collect()
+            },
+            Some(_) => return Err(err("'type' must be a string or a non-empty array of strings".to_string())),
+            None if schema.contains_key("properties") => vec!["object"],
+            None if schema.contains_key("items") => vec!["array"],
+            None => return Err(err("'type' is missing".to_string())),
+        };
+
+        let mut schemas = types
+            .into_iter()
+            .map(|t| match t {
+                "null" => Ok(Schema::Null),
+                "boolean" => Ok(Schema::Boolean),
+                "integer" => compile_integer(schema).map_err(err),
+                "number" => compile_number(schema).map_err(err),
+                "string" => compile_string(schema).map_err(err),
+                "array" => self.compile_array(schema, at, refs),
+                "object" => self.compile_object(schema, at, refs),
+                _ => Err(err(format!("'type' '{t}' is not supported"))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(if schemas.len() == 1 {
+            schemas.remove(0)
+        } else {
+            Schema::OneOf(schemas)
+        })
+    }
+
+    fn compile_ref(&self, reference: &Value, at: &str, refs: &mut Vec<String>) -> Result<Schema, String> {
+        let err = |msg: String| format!("Invalid JSON Schema at '{at}': {msg}");
+
+        let reference = match reference.as_str() {
+            Some(r) if r.starts_with('#') => r.to_string(),
+            _ => return Err(err("'$ref' must point to the same schema (ex. '#/$defs/NAME')".to_string())),
+        };
+        if refs.contains(&reference) {
+            return Err(err(format!("'$ref' '{reference}' is recursive, which is not supported")));
+        }
+        let Some(referenced) = self.root.pointer(&reference[1..]) else {
+            return Err(err(format!("'$ref' '{reference}' not found")));
+        };
+
+        refs.push(reference.clone());
+        let schema = self.compile(referenced, &reference, refs);
+        refs.pop();
+
+        schema
+    }
+
+    fn compile_array(&self, schema: &Map<String, Value>, at: &str, refs: &mut Vec<String>) -> Result<Schema, String> {
+        let err = |msg: String| format!("Invalid JSON Schema at '{at}': {msg}");
+
+        let items = match schema.get("items") {
+            Some(Value::Array(_)) => return Err(err("'items' as an array (tuple) is not supported".to_string())),
+            Some(items) => self.compile(items, &format!("{at}/items"), refs)?,
+            None => return Err(err("'items' is missing".to_string())),
+        };
+
+        let min_items = get_usize(schema, "minItems").map_err(err)?.unwrap_or(0);
+        let mut max_items = get_usize(schema, "maxItems").map_err(err)?.unwrap_or(min_items.max(DEFAULT_MAX_ITEMS));
+        if min_items > max_items {
+            return Err(err("must have 'minItems <= maxItems'".to_string()));
+        }
+
+        let unique_items = schema.get("uniqueItems").and_then(Value::as_bool).unwrap_or(false);
+        if unique_items {
+            let distinct = usize::try_from(items.max_distinct()).unwrap_or(usize::MAX);
+            if distinct < min_items {
+                return Err(err(format!(
+                    "'uniqueItems' requires {min_items} distinct items, more than 'items' can generate ({distinct})"
+                )));
+            }
+            max_items = max_items.min(distinct);
+        }
+
+        Ok(Schema::Array {
+            items: Box::new(items),
+            min_items,
+            max_items,
+            unique_items,
+        })
+    }
+
+    fn compile_object(&self, schema: &Map<String, Value>, at: &str, refs: &mut Vec<String>) -> Result<Schema, String> {
+        let err = |msg: String| format!("Invalid JSON Schema at '{at}': {msg}");
+
+        let required = match schema.get("required") {
+            None => Vec::new(),
+            Some(Value::Array(r)) if r.iter().all(Value::is_string) => r.iter().filter_map(Value::as_str).collect(),
+            Some(_) => return Err(err("'required' must be an array of strings".to_string())),
+        };
+
+        let no_properties = Map::new();
+        let properties = match schema.get("properties") {
+            None => &no_properties,
+            Some(Value::Object(p)) => p,
+            Some(_) => return Err(err("'properties' must be an object".to_string())),
+        };
+        if let Some(r) = required.iter().find(|r| !properties.contains_key(**r)) {
+            return Err(err(format!("required property '{r}' is not described in 'properties'")));
+        }
+
+        properties
+            .iter()
+            .map(|(name, s)| {
+                let property = self.compile(s, &format!("{at}/properties/{name}"), refs)?;
+                Ok((name.clone(), property, required.contains(&name.as_str())))
+            })
+            .collect::<Result<_, _>>()
+            .map(Schema::Object)
+    }
+}
+
+fn compile_integer(schema: &Map<String, Value>) -> Result<Schema, String> {
+    let (min, max) = get_bounds(schema)?;
+
+    // Integer bounds, made inclusive
+    let min = match min {
+        Some((v, true)) => Some(v.floor() + 1.0),
+        Some((v, false)) => Some(v.ceil()),
+        None => None,
+    };
+    let max = match max {
+        Some((v, true)) => Some(v.ceil() - 1.0),
+        Some((v, false)) => Some(v.floor()),
+        None => None,
+    };
+    let (min, max) = with_default_range(min, max);
+    if !(min >= i64::MIN as f64 && max <= i64::MAX as f64) {
+        return Err("'integer' bounds must be 64-bit integers".to_string());
+    }
+    let (min, max) = (min as i64, max as i64);
+
+    let step = match get_f64(schema, "multipleOf")? {
+        None => 1,
+        Some(m) if m.fract() == 0.0 && m >= 1.0 && m <= i64::MAX as f64 => m as i64,
+        Some(_) => return Err("'multipleOf' of 'integer' must be a positive integer".to_string()),
+    };
+
+    // First and last multiples of `step` within the bounds
+    let first = (min as i128).div_euclid(step as i128) * step as i128;
+    let first = if first < min as i128 {
+        first + step as i128
+    } else {
+        first
+    };
+    let last = (max as i128).div_euclid(step as i128) * step as i128;
+    if first > last {
+        return Err("no 'integer' satisfies the given bounds and 'multipleOf'".to_string());
+    }
+
+    Ok(Schema::Integer {
+        min: first as i64,
+        step,
+        steps: ((last - first) / step as i128) as u64,
+    })
+}
+
+fn compile_number(schema: &Map<String, Value>) -> Result<Schema, String> {
+    let (min, max) = get_bounds(schema)?;
+    let (exclusive_min, exclusive_max) = (min.is_some_and(|b| b.1), max.is_some_and(|b| b.1));
+    let (min, max) = with_default_range(min.map(|b| b.0), max.map(|b| b.0));
+
+    match get_f64(schema, "multipleOf")? {
+        None if min < max || (min == max && !exclusive_min && !exclusive_max) => Ok(Schema::Number {
+            min,
+            max,
+            exclusive_min,
+            exclusive_max,
+        }),
+        None => Err("no 'number' satisfies the given bounds".to_string()),
+        Some(m) if m > 0.0 => {
+            let min_k = (min / m).ceil()
+                + if exclusive_min && (min / m).fract() == 0.0 {
+                    1.0
+                } else {
+                    0.0
+                };
+            let max_k = (max / m).floor()
+                - if exclusive_max && (max / m).fract() == 0.0 {
+                    1.0
+                } else {
+                    0.0
+                };
+            if min_k > max_k || min_k < i64::MIN as f64 || max_k > i64::MAX as f64 {
+                return Err("no 'number' satisfies the given bounds and 'multipleOf'".to_string());
+            }
+
+            Ok(Schema::NumberMultiple {
+                multiple_of: m,
+                min_k: min_k as i64,
+                max_k: max_k as i64,
+            })
+        },
+        Some(_) => Err("'multipleOf' must be greater than 0".to_string()),
+    }
+}
+
+fn compile_string(schema: &Map<String, Value>) -> Result<Schema, String> {
+    if let Some(format) = schema.get("format") {
+        // Formatted strings have the length of their format: bounds would be ignored
+        if let Some(keyword) = ["minLength", "maxLength"].iter().find(|k| schema.contains_key(**k)) {
+            return Err(format!("'format' combined with '{keyword}' is not supported"));
+        }
+        let format = match format.as_str().unwrap_or_default() {
+            "date-time" => StringFormat::DateTime,
+            "date" => StringFormat::Date,
+            "time" => StringFormat::Time,
+            "email" => StringFormat::Email,
+            "hostname" => StringFormat::Hostname,
+            "ipv4" => StringFormat::Ipv4,
+            "ipv6" => StringFormat::Ipv6,
+            "uri" => StringFormat::Uri,
+            "uuid" => StringFormat::Uuid,
+            _ => return Err(format!("'format' {format} is not supported")),
+        };
+        return Ok(Schema::Formatted(format));
+    }
+
+    let min_length = get_usize(schema, "minLength")?.unwrap_or(0);
+    let max_length = get_usize(schema, "maxLength")?.unwrap_or(min_length.max(DEFAULT_MAX_LENGTH));
+    if min_length > max_length {
+        return Err("must have 'minLength <= maxLength'".to_string());
+    }
+
+    Ok(Schema::String {
+        min_length,
+        max_length,
+    })
+}
+
+/// A bound of a number, if any, flagged as exclusive or not.
+type Bound = Option<(f64, bool)>;
+
+/// Returns the lower and upper bounds of a number, each flagged as exclusive or not.
+///
+/// Supports both `exclusiveMinimum`/`exclusiveMaximum` as numbers, and as booleans (JSON Schema Draft 4).
+fn get_bounds(schema: &Map<String, Value>) -> Result<(Bound, Bound), String> {
+    // `is_stricter(a, b)` tells if bound `a` is more restrictive than `b`
+    let bound = |inclusive: &str, exclusive: &str, is_stricter: fn(f64, f64) -> bool| -> Result<Bound, String> {
+        match (get_f64(schema, inclusive)?, schema.get(exclusive)) {
+            (v, None | Some(Value::Bool(false))) => Ok(v.map(|v| (v, false))),
+            (Some(v), Some(Value::Bool(true))) => Ok(Some((v, true))),
+            (v, Some(_)) => match get_f64(schema, exclusive)? {
+                // The most restrictive of the two bounds prevails
+                Some(e) if v.is_none_or(|v| e == v || is_stricter(e, v)) => Ok(Some((e, true))),
+                _ => Ok(v.map(|v| (v, false))),
+            },
+        }
+    };
+
+    let min = bound("minimum", "exclusiveMinimum", |a, b| a > b)?;
+    let max = bound("maximum", "exclusiveMaximum", |a, b| a < b)?;
+    if let (Some((min, _)), Some((max, _))) = (min, max) {
+        if min > max {
+            return Err("must have 'minimum <= maximum'".to_string());
+        }
+    }
+
+    Ok((min, max))
+}
+
+/// Applies [`DEFAULT_NUMBER_RANGE`] to the missing bounds of a number.
+fn with_default_range(min: Option<f64>, max: Option<f64>) -> (f64, f64) {
+    let range = DEFAULT_NUMBER_RANGE as f64;
+
+    match (min, max) {
+        (Some(min), Some(max)) => (min, max),
+        (Some(min), None) => (min, min + range),
+        (None, Some(max)) => (max - range, max),
+        (None, None) => (0.0, range),
+    }
+}
+
+fn get_f64(schema: &Map<String, Value>, keyword: &str) -> Result<Option<f64>, String> {
+    match schema.get(keyword) {
+        None => Ok(None),
+        Some(v) => v.as_f64().map(Some).ok_or_else(|| format!("'{keyword}' must be a number")),
+    }
+}
+
+fn get_usize(schema: &Map<String, Value>, keyword: &str) -> Result<Option<usize>, String> {
+    match schema.get(keyword) {
+        None => Ok(None),
+        Some(v) => v
+            .as_u64()
+            .and_then(|v| usize::try_from(v).ok())
+            .map(Some)
+            .ok_or_else(|| format!("'{keyword}' must be a non-negative integer")),
+    }
+}
+
+impl Schema {
+    /// Upper bound of the number of distinct values this schema generates: [`u64::MAX`] when (practically) unbounded.
+    fn max_distinct(&self) -> u64 {
+        match self {
+            Schema::Null => 1,
+            Schema::Boolean => 2,
+            Schema::Integer {
+                steps,
+                ..
+            } => steps.saturating_add(1),
+            Schema::Number {
+                min,
+                max,
+                ..
+            } if min == max => 1,
+            Schema::Number {
+                ..
+            } => u64::MAX,
+            Schema::NumberMultiple {
+                min_k,
+                max_k,
+                ..
+            } => u64::try_from(*max_k as i128 - *min_k as i128 + 1).unwrap_or(u64::MAX),
+            Schema::String {
+                min_length,
+                max_length,
+            } => (*min_length..=*max_length)
+                .map(|l| u32::try_from(l).map_or(u64::MAX, |l| ALPHANUMERIC_CHARS.saturating_pow(l)))
+                .fold(0, u64::saturating_add),
+            Schema::Formatted(format) => format.max_distinct(),
+            Schema::Array {
+                items,
+                min_items,
+                max_items,
+                unique_items,
+            } => {
+                let distinct = items.max_distinct();
+                if distinct == 1 && !unique_items {
+                    return (max_items - min_items) as u64 + 1;
+                }
+
+                // Arrays of each length: with unique items, the first can take any value, the second one less...
+                let (mut arrays, mut arrays_of_length) = (0u64, 1u64);
+                for length in 0..=*max_items {
+                    if length >= *min_items {
+                        arrays = arrays.saturating_add(arrays_of_length);
+                    }
+                    if arrays == u64::MAX || arrays_of_length == u64::MAX {
+                        return u64::MAX;
+                    }
+                    let choices = if *unique_items {
+                        distinct.saturating_sub(length as u64)
+                    } else {
+                        distinct
+                    };
+                    arrays_of_length = arrays_of_length.saturating_mul(choices);
+                }
+
+                arrays
+            },
+            Schema::Object(properties) => properties
+                .iter()
+                .map(|(_, property, required)| property.max_distinct().saturating_add(u64::from(!required)))
+                .fold(1, u64::saturating_mul),
+            Schema::Enum(values) => distinct_values(values.iter()) as u64,
+            Schema::OneOf(schemas) => match schemas.iter().map(Schema::values).collect::<Option<Vec<_>>>() {
+                // Subschemas of values only (ex. `const`), might repeat the same values
+                Some(values) => distinct_values(values.into_iter().flatten()) as u64,
+                None => schemas.iter().map(Schema::max_distinct).fold(0, u64::saturating_add),
+            },
+        }
+    }
+
+    /// Whether no value generated by this schema could be generated by `other` too: either because they
+    /// are made of different values only, or because they generate different types.
+    fn is_disjoint(&self, other: &Schema) -> bool {
+        match (self.values(), other.values()) {
+            (Some(values), Some(others)) => values.iter().all(|v| !others.contains(v)),
+            _ => self.types().iter().all(|t| !other.types().contains(t)),
+        }
+    }
+
+    /// The JSON types of the values this schema generates (`integer` being a `number`).
+    fn types(&self) -> Vec<&'static str> {
+        match self {
+            Schema::Null => vec!["null"],
+            Schema::Boolean => vec!["boolean"],
+            Schema::Integer {
+                ..
+            }
+            | Schema::Number {
+                ..
+            }
+            | Schema::NumberMultiple {
+                ..
+            } => vec!["number"],
+            Schema::String {
+                ..
+            }
+            | Schema::Formatted(_) => vec!["string"],
+            Schema::Array {
+                ..
+            } => vec!["array"],
+            Schema::Object(_) => vec!["object"],
+            Schema::Enum(values) => values
+                .iter()
+                .map(|v| match v {
+                    Value::Null => "null",
+                    Value::Bool(_) => "boolean",
+                    Value::Number(_) => "number",
+                    Value::String(_) => "string",
+                    Value::Array(_) => "array",
+                    Value::Object(_) => "object",
+                })
+                .collect(),
+            Schema::OneOf(schemas) => schemas.iter().flat_map(Schema::types).collect(),
+        }
+    }
+
+    /// All the values this schema generates, if it's made of values only (ex. `enum`, `const`).
+    fn values(&self) -> Option<Vec<&Value>> {
+        match self {
+            Schema::Enum(values) => Some(values.iter().collect()),
+            Schema::OneOf(schemas) => {
+                schemas.iter().map(Schema::values).collect::<Option<Vec<_>>>().map(|v| v.concat())
+            },
+            _ => None,
+        }
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> Value {
+        match self {
+            Schema::Null => Value::Null,
+            Schema::Boolean => Value::Bool(rng.gen()),
+            Schema::Integer {
+                min,
+                step,
+                steps,
+            } => {
+                let k = rng.gen_range(0..=*steps);
+                Value::from((*min as i128 + k as i128 * *step as i128) as i64)
+            },
+            Schema::Number {
+                min,
+                max,
+                exclusive_min,
+                exclusive_max,
+            } => {
+                // When bounds are too far apart for their difference to be finite, it's interpolated in halves
+                let v = if (max - min).is_finite() {
+                    rng.gen_range(*min..=*max)
+                } else {
+                    let (half_min, half_max) = (min / 2.0, max / 2.0);
+                    ((half_min + rng.gen::<f64>() * (half_max - half_min)) * 2.0).clamp(*min, *max)
+                };
+                if (*exclusive_min && v == *min) || (*exclusive_max && v == *max) {
+                    Value::from(min / 2.0 + max / 2.0)
+                } else {
+                    Value::from(v)
+                }
+            },
+            Schema::NumberMultiple {
+                multiple_of,
+                min_k,
+                max_k,
+            } => Value::from(rng.gen_range(*min_k..=*max_k) as f64 * multiple_of),
+            Schema::String {
+                min_length,
+                max_length,
+            } => {
+                let length = rng.gen_range(*min_length..=*max_length);
+                Value::String(Alphanumeric.sample_string(rng, length))
+            },
+            Schema::Formatted(format) => Value::String(format.sample(rng)),
+            Schema::Array {
+                items,
+                min_items,
+                max_items,
+                unique_items,
+            } => {
+                let length = rng.gen_range(*min_items..=*max_items);
+                let mut array = Vec::with_capacity(length);
+
+                if *unique_items {
+                    // Items are drawn until `min_items` are distinct (there are enough values, as checked when
+                    // compiling): past that, duplicates are tolerated a few times, before giving up on `length`
+                    let mut duplicates = length * 10;
+                    while array.len() < length {
+                        let item = items.sample(rng);
+                        if !array.contains(&item) {
+                            array.push(item);
+                        } else if array.len() >= *min_items {
+                            if duplicates == 0 {
+                                break;
+                            }
+                            duplicates -= 1;
+                        }
+                    }
+                } else {
+                    array.extend((0..length).map(|_| items.sample(rng)));
+                }
+
+                Value::Array(array)
+            },
+            Schema::Object(properties) => {
+                let mut object = Map::new();
+                for (name, property, required) in properties {
+                    if *required || rng.gen_bool(0.5) {
+                        object.insert(name.clone(), property.sample(rng));
+                    }
+                }
+
+                Value::Object(object)
+            },
+            // Valid, as `Enum` and `OneOf` are never empty
+            Schema::Enum(values) => values.choose(rng).unwrap().clone(),
+            Schema::OneOf(schemas) => schemas.choose(rng).unwrap().sample(rng),
+        }
+    }
+}
+
+impl StringFormat {
+    /// Upper bound of the number of distinct strings generated: [`u64::MAX`] when (practically) unbounded.
+    fn max_distinct(&self) -> u64 {
+        let range_sec = (DATE_TIME_RANGE_SEC.1 - DATE_TIME_RANGE_SEC.0) as u64;
+
+        match self {
+            StringFormat::DateTime => range_sec,
+            StringFormat::Date => range_sec / 86_400,
+            StringFormat::Time => 86_400,
+            _ => u64::MAX,
+        }
+    }
+
+    fn sample<R: Rng>(&self, rng: &mut R) -> String {
+        let lowercase = |rng: &mut R, length: usize| Alphanumeric.sample_string(rng, length).to_lowercase();
+
+        match self {
+            StringFormat::DateTime | StringFormat::Date | StringFormat::Time => {
+                let sec = rng.gen_range(DATE_TIME_RANGE_SEC.0..DATE_TIME_RANGE_SEC.1);
+                // Valid, as the range is well within the one supported
+                let date_time = DateTime::from_timestamp(sec, 0).unwrap();
+
+                match self {
+                    StringFormat::DateTime => date_time.to_rfc3339_opts(SecondsFormat::Secs, true),
+                    StringFormat::Date => date_time.format("%Y-%m-%d").to_string(),
+                    _ => date_time.format("%H:%M:%SZ").to_string(),
+                }
+            },
+            StringFormat::Email => format!("{}@{}.com", lowercase(rng, 8), lowercase(rng, 6)),
+            StringFormat::Hostname => format!("{}.example.com", lowercase(rng, 8)),
+            StringFormat::Ipv4 => {
+                format!("{}.{}.{}.{}", rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>(), rng.gen::<u8>())
+            },
+            StringFormat::Ipv6 => (0..8).map(|_| format!("{:x}", rng.gen::<u16>())).collect::<Vec<_>>().join(":"),
+            StringFormat::Uri => format!("https://{}.example.com/{}", lowercase(rng, 8), lowercase(rng, 8)),
+            StringFormat::Uuid => uuid_v4(rng),
+        }
+    }
+}
+
+/// Number of distinct `values`.
+fn distinct_values<'a>(values: impl Iterator<Item = &'a Value>) -> usize {
+    let mut distinct: Vec<&Value> = Vec::new();
+    for value in values {
+        if !distinct.contains(&value) {
+            distinct.push(value);
+        }
+    }
+
+    distinct.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use serde_json::json;
+
+    use super::*;
+
+    fn compile(schema: Value) -> Result<JsonSchema, String> {
+        JsonSchema::from_json(Path::new("schema.json"), &schema)
+    }
+
+    fn samples(schema: Value) -> Vec<Value> {
+        let schema = compile(schema).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        (0..500).map(|_| schema.sample(&mut rng)).collect()
+    }
+
+    #[test]
+    fn test_sample() {
+        let docs = samples(json!({
+            "type": "object",
+            "properties": {
+                "id": { "type": "string", "format": "uuid" },
+                "age": { "type": "integer", "minimum": 18, "exclusiveMaximum": 21 },
+                "score": { "type": "number", "minimum": 0, "maximum": 1, "multipleOf": 0.25 },
+                "name": { "type": "string", "minLength": 2, "maxLength": 4 },
+                "tags": { "type": "array", "items": { "enum": ["a", "b", "c"] }, "minItems": 1, "maxItems": 3, "uniqueItems": true },
+                "status": { "$ref": "#/$defs/status" },
+                "when": { "type": "string", "format": "date-time" },
+                "note": { "type": ["string", "null"], "maxLength": 0 }
+            },
+            "required": ["id", "age", "score", "name", "tags", "status", "when"],
+            "$defs": { "status": { "const": "active" } }
+        }));
+
+        for doc in &docs {
+            assert_eq!(36, doc["id"].as_str().unwrap().len());
+            assert!((18..=20).contains(&doc["age"].as_i64().unwrap()));
+            assert!([0.0, 0.25, 0.5, 0.75, 1.0].contains(&doc["score"].as_f64().unwrap()));
+            assert!((2..=4).contains(&doc["name"].as_str().unwrap().len()));
+            let tags = doc["tags"].as_array().unwrap();
+            assert!((1..=3).contains(&tags.len()));
+            assert!(tags.iter().all(|t| tags.iter().filter(|o| *o == t).count() == 1));
+            assert_eq!("active", doc["status"]);
+            assert!(DateTime::parse_from_rfc3339(doc["when"].as_str().unwrap()).is_ok());
+            assert!(doc.get("note").is_none_or(|n| n.is_null() || n == ""));
+        }
+
+        // All values within the bounds are generated, and optional properties sometimes are
+        assert!(docs.iter().any(|d| d["age"] == 18) && docs.iter().any(|d| d["age"] == 20));
+        assert!(docs.iter().any(|d| d.get("note").is_some()) && docs.iter().any(|d| d.get("note").is_none()));
+    }
+
+    #[test]
+    fn test_sample_numbers() {
+        let docs = samples(json!({ "type": "integer", "minimum": -7, "maximum": 7, "multipleOf": 5 }));
+        assert!(docs.iter().all(|d| [-5, 0, 5].contains(&d.as_i64().unwrap())));
+
+        let docs = samples(json!({ "type": "number", "exclusiveMinimum": 1, "maximum": 2 }));
+        assert!(docs.iter().all(|d| d.as_f64().unwrap() > 1.0 && d.as_f64().unwrap() <= 2.0));
+
+        // Draft 4 exclusive bounds
+        let docs = samples(json!({ "type": "integer", "minimum": 1, "maximum": 3, "exclusiveMaximum": true }));
+        assert!(docs.iter().all(|d| [1, 2].contains(&d.as_i64().unwrap())));
+
+        let docs = samples(json!({ "type": "number", "minimum": -1e308, "maximum": 1e308 }));
+        assert!(docs.iter().all(|d| d.as_f64().unwrap().abs() <= 1e308));
+        let docs = samples(json!({ "type": "number", "minimum": f64::MIN, "exclusiveMaximum": f64::MAX }));
+        assert!(docs.iter().all(|d| d.as_f64().unwrap() < f64::MAX));
+
+        let docs = samples(json!({ "type": "integer", "minimum": 5000 }));
+        assert!(docs.iter().all(|d| (5000..=6000).contains(&d.as_i64().unwrap())));
+    }
+
+    #[test]
+    fn test_unique_items() {
+        // Only 3 distinct values: arrays can't be longer than that, nor shorter than `minItems`
+        let docs = samples(
+            json!({ "type": "array", "items": { "enum": ["a", "b", "c", "a"] }, "minItems": 3, "uniqueItems": true }),
+        );
+        for doc in &docs {
+            let items = doc.as_array().unwrap();
+            assert_eq!(3, items.len());
+            assert!(["a", "b", "c"].iter().all(|v| items.contains(&json!(v))));
+        }
+
+        let docs = samples(
+            json!({ "type": "array", "items": { "type": "integer", "minimum": 1, "maximum": 4 }, "minItems": 4, "maxItems": 9, "uniqueItems": true }),
+        );
+        assert!(docs.iter().all(|d| d.as_array().unwrap().len() == 4));
+
+        let docs = samples(
+            json!({ "type": "array", "items": { "type": ["boolean", "null"] }, "minItems": 2, "uniqueItems": true }),
+        );
+        assert!(docs.iter().all(|d| (2..=3).contains(&d.as_array().unwrap().len())));
+
+        assert_eq!(
+            Err("Invalid JSON Schema at '#': 'uniqueItems' requires 3 distinct items, more than 'items' can generate (2)"
+                .to_string()),
+            compile(json!({ "type": "array", "items": { "type": "boolean" }, "minItems": 3, "uniqueItems": true }))
+        );
+        assert_eq!(
+            Err("Invalid JSON Schema at '#': 'uniqueItems' requires 2 distinct items, more than 'items' can generate (1)"
+                .to_string()),
+            compile(
+                json!({ "items": { "anyOf": [{ "const": 1 }, { "enum": [1] }] }, "minItems": 2, "uniqueItems": true })
+            )
+        );
+        assert!(compile(json!({ "items": { "type": "null" }, "minItems": 2, "uniqueItems": true })).is_err());
+        assert!(compile(json!({ "items": { "type": "null" }, "minItems": 2 })).is_ok());
+
+        // Arrays (and objects) can be items too: `[]`, `[true]`, `[false]`, `[true, false]` and `[false, true]`
+        let booleans = json!({ "items": { "type": "boolean" }, "maxItems": 2, "uniqueItems": true });
+        assert!(compile(json!({ "items": booleans, "minItems": 5, "uniqueItems": true })).is_ok());
+        assert!(compile(json!({ "items": booleans, "minItems": 6, "uniqueItems": true })).is_err());
+    }
+
+    #[test]
+    fn test_compile_errors() {
+        assert_eq!(
+            Err("Invalid JSON Schema at '#/properties/a': 'pattern' is not supported".to_string()),
+            compile(json!({ "properties": { "a": { "type": "string", "pattern": "^a" } } }))
+        );
+        assert_eq!(
+            Err("Invalid JSON Schema at '#/items': 'type' is missing".to_string()),
+            compile(json!({ "type": "array", "items": {} }))
+        );
+        assert_eq!(
+            Err("Invalid JSON Schema at '#/$defs/node/items': '$ref' '#/$defs/node' is recursive, which is not supported"
+                .to_string()),
+            compile(json!({ "$ref": "#/$defs/node", "$defs": { "node": { "items": { "$ref": "#/$defs/node" } } } }))
+        );
+        assert_eq!(
+            Err("Invalid JSON Schema at '#': required property 'b' is not described in 'properties'".to_string()),
+            compile(json!({ "type": "object", "properties": { "a": { "type": "null" } }, "required": ["b"] }))
+        );
+        assert_eq!(
+            Err("Invalid JSON Schema at '#': must have 'minLength <= maxLength'".to_string()),
+            compile(json!({ "type": "string", "minLength": 3, "maxLength": 2 }))
+        );
+        assert_eq!(
+            Err("Invalid JSON Schema at '#': no 'integer' satisfies the given bounds and 'multipleOf'".to_string()),
+            compile(json!({ "type": "integer", "minimum": 1, "maximum": 4, "multipleOf": 5 }))
+        );
+        assert_eq!(
+            Err("Invalid JSON Schema at '#': 'format' \"phone\" is not supported".to_string()),
+            compile(json!({ "type": "string", "format": "phone" }))
+        );
+        assert_eq!(
+            Err("Invalid JSON Schema at '#': 'format' combined with 'maxLength' is not supported".to_string()),
+            compile(json!({ "type": "string", "format": "email", "maxLength": 10 }))
+        );
+        assert_eq!(
+            Err("Invalid JSON Schema at '#': 'oneOf' subschemas 1 and 2 must differ in 'type', or in 'const' and 'enum' \
+                 values"
+                .to_string()),
+            compile(json!({ "oneOf": [{ "type": "null" }, { "type": "integer" }, { "type": ["number", "string"] }] }))
+        );
+        assert!(compile(json!({ "oneOf": [{ "enum": ["a", 1] }, { "type": "string" }] })).is_err());
+        assert!(compile(json!({ "oneOf": [{ "const": "a" }, { "enum": ["b", "c"] }, { "type": "integer" }] })).is_ok());
+        assert!(compile(json!({ "anyOf": [{ "type": "integer" }, { "type": "number" }] })).is_ok());
+        assert!(compile(json!({ "type": "thing" })).is_err());
+        assert!(JsonSchema::load(Path::new("does_not_exist.json")).is_err());
+        assert!(JsonSchema::load(Path::new("Cargo.toml")).unwrap_err().contains("as JSON"));
+    }
+}
@@ -2,42 +2,95 @@
 extern crate log;
 
 use std::error::Error;
+use std::sync::Arc;
 
 use ::rdkafka::ClientConfig;
+use chrono::Utc;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 
 use cli::*;
 use generator::*;
+use scenario::*;
+use transition::Transition;
 use workload::*;
 
+use crate::anchor::AnchoredSchedule;
+use crate::bursts::BurstOverlay;
+use crate::composition::{ComposedSchedule, OnceSchedule};
+use crate::noise::NoiseOverlay;
+use crate::plot::PlotFormat;
 use crate::producer_sink::ProducerSink;
-use crate::records_tap::RecordsTap;
+use crate::ramp::WarmUpSchedule;
+use crate::random::Randomness;
+use crate::rate_expr::RateExprSchedule;
+use crate::records_tap::{RecordsTap, RunLimits, ThroughputUnit};
+use crate::schema_registry::SchemaRegistry;
+use crate::time_scale::TimeScaledSchedule;
+use crate::trace::RateTrace;
 
+mod anchor;
+mod avro;
+mod bursts;
 mod cli;
+mod composition;
 mod generator;
+mod json_schema;
 mod logging;
+mod noise;
+mod plot;
 mod producer_sink;
+mod protobuf;
+mod ramp;
+mod random;
+mod rate_expr;
 mod rdkafka;
 mod records_tap;
+mod scenario;
+mod schema_registry;
+mod sequence;
+mod template;
+mod time_scale;
+mod trace;
 mod transition;
+mod waveform;
 mod workload;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let cli = parse_cli_and_init_logging();
 
-    let workload = build_workload(&cli);
+    let scenario = resolve_scenario(&cli);
 
-    let generator = build_record_generator(&cli)?;
+    let randomness = Randomness::new(scenario.run.seed);
 
-    let producer_config = build_producer_config(&cli);
+    let workload = build_schedule(&scenario.workload, randomness)?;
+    let workload = warm_up_workload(&scenario.workload, workload);
 
-    let (records_tx, records_rx) = build_records_channel(cli.max as usize);
+    if let Some(format) = cli.dry_run {
+        dry_run(&scenario, workload.as_ref(), format);
+        return Ok(());
+    }
+
+    let limits = build_run_limits(&scenario, workload.as_ref());
+
+    let generator = build_record_generator(&scenario.records, randomness)?;
+
+    let producer_config = build_producer_config(&scenario.producer);
+
+    let (records_tx, records_rx) =
+        build_records_channel(records_channel_depth(workload.as_ref(), scenario.workload.unit));
     let shutdown_rx = build_shutdown_channel();
 
     // Create a "tap" of records, based on the workload and generator we just built
-    let mut records_tap = RecordsTap::new(workload, generator);
+    let mut records_tap = RecordsTap::new(
+        workload,
+        generator,
+        scenario.workload.pacing,
+        scenario.workload.unit,
+        limits,
+        scenario.workload.cool_down,
+    );
 
     // Configure a "sink" around a Kafka Producer, based on the producer config we just built
     let mut producer_sink = ProducerSink::new(producer_config)?;
@@ -46,7 +99,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let records_tap_handle = records_tap.spawn(records_tx, shutdown_rx);
     let producer_sink_handle = producer_sink.spawn(records_rx);
 
-    // Await async tasks: when finished, print out some basic stats
+    // Await async tasks (until shutdown, or the run limits are reached): when finished, print out some basic stats
     let sec = records_tap_handle.await?;
     let (success, fail) = producer_sink_handle.await?;
     info!("Records produced for {sec}s: {success} successfully, {fail} failed");
@@ -60,62 +113,355 @@ fn parse_cli_and_init_logging() -> Cli {
     logging::init(cli.verbosity_level());
 
     trace!("Created:\n{:#?}", cli);
+    cli
+}
+
+fn resolve_scenario(cli: &Cli) -> Scenario {
+    // Resolve the scenario to run, from the (optional) scenario file and the command line
+    let scenario = cli.resolve_scenario();
+
+    trace!("Created:\n{:#?}", scenario);
+    scenario
+}
+
+/// Builds the records/sec schedule described by the workload: its shape, time-scaled, anchored, with bursts and noise.
+fn build_schedule(ws: &WorkloadScenario, randomness: Randomness) -> Result<Arc<dyn RateSchedule>, String> {
+    let workload = build_workload(ws, randomness)?;
+    let workload = time_scale_workload(ws, workload);
+    let workload = anchor_workload(ws, workload);
+    let workload = inject_bursts(ws, workload, randomness);
+    let workload = add_noise(ws, workload, randomness);
+
+    Ok(workload)
+}
+
+fn build_workload(ws: &WorkloadScenario, randomness: Randomness) -> Result<Arc<dyn RateSchedule>, String> {
+    if let Some(compose) = &ws.compose {
+        // Log the production that Ksunami intends to do: each part logs its own
+        info!("");
+        info!("Records production will follow the {} of {} workloads", compose.op, compose.parts.len());
+
+        // Each part draws its own randomness
+        let parts = compose
+            .parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                let schedule = build_schedule(part, randomness.fork(i as u64))?;
+                Ok(if part.is_once_part() {
+                    Arc::new(OnceSchedule::new(schedule))
+                } else {
+                    schedule
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let composed = ComposedSchedule::new(compose.op, parts);
+
+        trace!("Created:\n{:#?}", composed);
+        return Ok(Arc::new(composed));
+    }
+
+    if let Some(rate_expr) = &ws.rate_expr {
+        let schedule = RateExprSchedule::new(rate_expr.clone(), ws.rate_expr_period);
+
+        // Log the production that Ksunami intends to do
+        info!("");
+        info!("Records production will follow the expression '{rate_expr}', every {} seconds", ws.rate_expr_period);
+        info!("");
+
+        trace!("Created:\n{:#?}", schedule);
+        return Ok(Arc::new(schedule));
+    }
+
+    if let Some(waveform) = ws.waveform {
+        // Log the production that Ksunami intends to do
+        info!("");
+        info!("Records production will follow the waveform '{waveform}'");
+        info!("");
+
+        trace!("Created:\n{:#?}", waveform);
+        return Ok(Arc::new(waveform));
+    }
+
+    if let Some(path) = &ws.trace {
+        let trace = RateTrace::from_file(path)?;
+        let workload = Workload::from_segments(trace.to_segments(ws.trace_transition));
+
+        // Log the production that Ksunami intends to do: a trace can be long, so no details
+        info!("");
+        info!(
+            "Records production will replay the rate trace '{}' ({} seconds){}",
+            path.display(),
+            workload.overall_duration_sec(),
+            if ws.trace_loop {
+                ", in a loop"
+            } else {
+                ""
+            }
+        );
+        info!("");
+
+        trace!("Created:\n{:#?}", workload);
+        return Ok(Arc::new(workload));
+    }
+
+    let workload = if ws.segments.is_empty() {
+        // Both are validated to be present when no segment or trace is provided
+        let (min, max) = (ws.min.unwrap(), ws.max.unwrap());
+
+        Workload::new(min, ws.min_sec, max, ws.max_sec, ws.up, ws.up_sec, ws.down, ws.down_sec)
+    } else {
+        Workload::from_segments(ws.segments.clone())
+    };
 
     // Log the production that Ksunami intends to do
     info!("");
     info!("Records production will follow this schedule:");
-    info!("  1. {} rec/sec for {} seconds", cli.min, cli.min_sec);
-    info!("  2. increase in rec/sec along a '{:?}' curve for {} seconds", cli.up, cli.up_sec);
-    info!("  3. {} rec/sec for {} seconds", cli.max, cli.max_sec);
-    info!("  4. decrease in rec/sec along a '{:?}' curve for {} seconds", cli.down, cli.down_sec);
-    info!("  5. repeat from 1.");
+    let mut prev_rate = workload.segments().last().unwrap().rate();
+    for (i, segment) in workload.segments().enumerate() {
+        if segment.transition() == Transition::None || segment.rate() == prev_rate {
+            info!("  {}. {} {}/sec for {} seconds", i + 1, segment.rate(), ws.unit, segment.duration_sec());
+        } else {
+            info!(
+                "  {}. {} in {}/sec to {} along a '{}' curve for {} seconds",
+                i + 1,
+                if segment.rate() > prev_rate {
+                    "increase"
+                } else {
+                    "decrease"
+                },
+                ws.unit,
+                segment.rate(),
+                segment.transition(),
+                segment.duration_sec()
+            );
+        }
+        prev_rate = segment.rate();
+    }
+    info!("  {}. repeat from 1.", workload.segments().count() + 1);
     info!("");
 
-    cli
+    trace!("Created:\n{:#?}", workload);
+    Ok(Arc::new(workload))
 }
 
-fn build_workload(cli: &Cli) -> Workload {
-    let workload =
-        Workload::new(cli.min, cli.min_sec, cli.max, cli.max_sec, cli.up, cli.up_sec, cli.down, cli.down_sec);
+fn time_scale_workload(ws: &WorkloadScenario, workload: Arc<dyn RateSchedule>) -> Arc<dyn RateSchedule> {
+    if ws.time_scale == 1.0 {
+        return workload;
+    }
 
-    trace!("Created:\n{:#?}", workload);
-    workload
+    let scaled = TimeScaledSchedule::new(workload, ws.time_scale, ws.time_scale_keep);
+    info!(
+        "Records production is time-scaled by {} (keeping the {}): a cycle lasts {} seconds",
+        ws.time_scale,
+        ws.time_scale_keep,
+        scaled.overall_duration_sec()
+    );
+
+    trace!("Created:\n{:#?}", scaled);
+    Arc::new(scaled)
+}
+
+fn anchor_workload(ws: &WorkloadScenario, workload: Arc<dyn RateSchedule>) -> Arc<dyn RateSchedule> {
+    let Some(tz) = ws.anchor_tz else {
+        return workload;
+    };
+
+    // Start from the point of the cycle that corresponds to the current wall-clock time
+    let anchored = AnchoredSchedule::new(workload, tz, ws.anchor_time, Utc::now());
+    info!(
+        "Records production is anchored to {} ({}): starting {} seconds into the cycle",
+        ws.anchor_time,
+        tz,
+        anchored.start_sec()
+    );
+
+    trace!("Created:\n{:#?}", anchored);
+    Arc::new(anchored)
+}
+
+fn inject_bursts(
+    ws: &WorkloadScenario,
+    workload: Arc<dyn RateSchedule>,
+    randomness: Randomness,
+) -> Arc<dyn RateSchedule> {
+    let Some(bursts) = ws.bursts else {
+        return workload;
+    };
+
+    let overlay = BurstOverlay::new(workload, bursts, randomness);
+    info!("Records production will have random bursts injected: '{bursts}'");
+
+    trace!("Created:\n{:#?}", overlay);
+    Arc::new(overlay)
+}
+
+fn add_noise(ws: &WorkloadScenario, workload: Arc<dyn RateSchedule>, randomness: Randomness) -> Arc<dyn RateSchedule> {
+    let Some(noise) = ws.noise else {
+        return workload;
+    };
+
+    let overlay = NoiseOverlay::new(workload, noise, randomness);
+    info!("Records production will have noise added: '{noise}'");
+
+    trace!("Created:\n{:#?}", overlay);
+    Arc::new(overlay)
+}
+
+fn warm_up_workload(ws: &WorkloadScenario, workload: Arc<dyn RateSchedule>) -> Arc<dyn RateSchedule> {
+    let Some(warm_up) = ws.warm_up else {
+        return workload;
+    };
+
+    let warm = WarmUpSchedule::new(workload, warm_up);
+    info!("Records production will warm-up from 0 for {} seconds, before the first cycle", warm_up.duration_sec());
+
+    trace!("Created:\n{:#?}", warm);
+    Arc::new(warm)
+}
+
+fn dry_run(scenario: &Scenario, workload: &dyn RateSchedule, format: PlotFormat) {
+    // Plot as many seconds as the run would last, or (by default) the warm-up and 1 cycle
+    let warm_up_sec = scenario.workload.warm_up.map_or(0, |w| w.duration_sec() as u64);
+    let duration_sec =
+        run_duration_sec(scenario, workload).unwrap_or(warm_up_sec + workload.overall_duration_sec() as u64);
+
+    print!("{}", plot::plot(workload, scenario.workload.unit, duration_sec, format));
+}
+
+/// How many seconds the run lasts, if limited in time: the shortest of its cycles, its duration, and
+/// the replay of a rate trace when not in a loop.
+///
+/// The cool-down is not included: it happens once the run is over.
+fn run_duration_sec(scenario: &Scenario, workload: &dyn RateSchedule) -> Option<u64> {
+    let cycle_sec = workload.overall_duration_sec() as u64;
+    let warm_up_sec = scenario.workload.warm_up.map_or(0, |w| w.duration_sec() as u64);
+
+    // Cycles are converted to the seconds it takes to complete them, after the warm-up
+    let cycles_sec = scenario.run.cycles.map(|c| warm_up_sec + c * cycle_sec);
+    let trace_sec =
+        (scenario.workload.trace.is_some() && !scenario.workload.trace_loop).then_some(warm_up_sec + cycle_sec);
+
+    [cycles_sec, scenario.run.duration, trace_sec].into_iter().flatten().min()
+}
+
+fn build_run_limits(scenario: &Scenario, workload: &dyn RateSchedule) -> RunLimits {
+    let rs = &scenario.run;
+    let duration_sec = run_duration_sec(scenario, workload);
+
+    let limits = RunLimits::new(duration_sec, rs.total_records);
+    if let Some(d) = duration_sec {
+        info!("Records production will stop after {d} seconds");
+    }
+    if let Some(t) = rs.total_records {
+        info!("Records production will stop after {t} records");
+    }
+    if let Some(c) = scenario.workload.cool_down {
+        info!("Records production will cool-down to 0 for {} seconds, before stopping", c.duration_sec());
+    }
+
+    trace!("Created:\n{:#?}", limits);
+    limits
 }
 
-fn build_record_generator(cli: &Cli) -> Result<RecordGenerator, std::io::Error> {
-    let mut generator = RecordGenerator::new(cli.topic.clone());
+fn build_record_generator(rs: &RecordsScenario, randomness: Randomness) -> Result<RecordGenerator, Box<dyn Error>> {
+    // Validated to be present
+    let mut generator = RecordGenerator::new(rs.topic.clone().unwrap());
+    generator.set_randomness(randomness);
 
-    if let Some(k_gen) = &cli.key {
-        generator.set_key_generator(k_gen.clone())?;
+    if let Some(k_gen) = &rs.key {
+        generator.set_key_generator(register_schema(k_gen, rs, "key")?)?;
     }
-    if let Some(p_gen) = &cli.payload {
-        generator.set_payload_generator(p_gen.clone())?;
+    if let Some(p_gen) = &rs.payload {
+        generator.set_payload_generator(register_schema(p_gen, rs, "value")?)?;
     }
-    if let Some(part) = cli.partition {
+    if let Some(part) = rs.partition {
         generator.set_destination_partition(part);
     }
-    for kv_pair in &cli.headers {
-        generator.add_record_header(kv_pair.0.clone(), kv_pair.1.clone());
+    for (k, v) in &rs.headers {
+        generator.add_record_header(k.clone(), v.clone());
+    }
+    for (k, seq) in &rs.header_seqs {
+        generator.add_record_sequence_header(k.clone(), seq.clone());
     }
 
     trace!("Created:\n{:#?}", generator);
     Ok(generator)
 }
 
-fn build_producer_config(cli: &Cli) -> ClientConfig {
+/// Registers (or looks up) the schema of an `avro` or `protobuf` value generator in the Schema Registry,
+/// under subject `TOPIC-key` or `TOPIC-value`: other value generators are returned as they are.
+///
+/// For `protobuf`, that's optional: without a Schema Registry, messages are produced without the wire format.
+fn register_schema(
+    val_gen: &ValueGenerator,
+    rs: &RecordsScenario,
+    key_or_value: &str,
+) -> Result<ValueGenerator, String> {
+    let (schema, schema_type) = match (val_gen, &rs.schema_registry) {
+        // Validated to be present, when producing 'avro' key or payload
+        (ValueGenerator::Avro(s), _) => (s.source(), "AVRO"),
+        (ValueGenerator::Protobuf(s), Some(_)) => match s.source() {
+            // Imported files would have to be registered too, and referenced: not supported
+            Some(_) if !s.imports().is_empty() => {
+                return Err(format!(
+                    "Schema Registry requires 'protobuf' to be loaded from a '.proto' file without imports, found: {}",
+                    s.imports().join(", ")
+                ))
+            },
+            Some(source) => (source, "PROTOBUF"),
+            None => return Err("Schema Registry requires 'protobuf' to be loaded from a '.proto' file".to_string()),
+        },
+        _ => return Ok(val_gen.clone()),
+    };
+
+    let registry = SchemaRegistry::new(rs.schema_registry.as_ref().unwrap());
+    let subject = format!("{}-{key_or_value}", rs.topic.as_ref().unwrap());
+    let schema_id = registry.register(&subject, schema, schema_type)?;
+    info!("{schema_type} schema of subject '{subject}' has ID {schema_id}");
+
+    Ok(match val_gen {
+        ValueGenerator::Avro(s) => ValueGenerator::Avro(s.clone().with_schema_id(schema_id)),
+        ValueGenerator::Protobuf(s) => ValueGenerator::Protobuf(s.clone().with_schema_id(schema_id)),
+        _ => unreachable!("Only 'avro' and 'protobuf' have a schema to register"),
+    })
+}
+
+fn build_producer_config(ps: &ProducerScenario) -> ClientConfig {
     let mut producer_config = ClientConfig::new();
     producer_config
-        .set("bootstrap.servers", cli.bootstrap_brokers.clone())
-        .set("client.id", cli.client_id.clone())
-        .set("partitioner", cli.partitioner.name());
-    for cfg in &cli.config {
-        producer_config.set(cfg.0.clone(), cfg.1.clone());
+        // Validated to be present
+        .set("bootstrap.servers", ps.brokers.clone().unwrap())
+        .set("client.id", ps.client_id.clone())
+        .set("partitioner", ps.partitioner.name());
+    for (k, v) in &ps.config {
+        producer_config.set(k.clone(), v.clone());
     }
 
     trace!("Created:\n{:#?}", producer_config);
     producer_config
 }
 
+/// Maximum number of records the channel between [`RecordsTap`] and the Producer can hold.
+const MAX_RECORDS_CHANNEL_DEPTH: usize = 100_000;
+
+/// Depth of the records channel: one second of the workload peak, in records.
+///
+/// When the workload describes bytes/sec, how many records that is is only known once they are generated,
+/// so the channel is as deep as allowed. Either way, the depth is clamped to [`MAX_RECORDS_CHANNEL_DEPTH`].
+fn records_channel_depth(workload: &dyn RateSchedule, unit: ThroughputUnit) -> usize {
+    let max_records_per_sec = match unit {
+        ThroughputUnit::Records => workload.max_records_per_sec(),
+        ThroughputUnit::Bytes => MAX_RECORDS_CHANNEL_DEPTH as f64,
+    };
+
+    // NaN compares as neither: `clamp` would keep it, and `as usize` would then turn it into 0
+    if max_records_per_sec.is_nan() {
+        return MAX_RECORDS_CHANNEL_DEPTH;
+    }
+    max_records_per_sec.ceil().clamp(1.0, MAX_RECORDS_CHANNEL_DEPTH as f64) as usize
+}
+
 fn build_records_channel(depth: usize) -> (mpsc::Sender<GeneratedRecord>, mpsc::Receiver<GeneratedRecord>) {
     mpsc::channel::<GeneratedRecord>(depth)
 }
@@ -0,0 +1,229 @@
+use std::fmt;
+use std::sync::Arc;
+
+use rand_distr::{Distribution, Normal, Poisson};
+use serde::{de, Deserialize, Deserializer};
+
+use crate::random::{RandomStream, Randomness};
+use crate::workload::{RateSchedule, WorkloadPhase};
+
+/// How many standard deviations away from the nominal records/sec the [`Noise::Gaussian`] is truncated at.
+const MAX_DEVIATIONS: f64 = 3.0;
+
+/// How many times a [`Noise::Gaussian`] is drawn again, when out of bounds, before giving up on the noise.
+const MAX_REDRAWS: u32 = 100;
+
+/// How many standard deviations above the nominal records/sec the [`Noise::Poisson`] is practically bound at:
+/// it's unbounded, but it goes beyond this with negligible probability.
+const POISSON_MAX_DEVIATIONS: f64 = 6.0;
+
+/// Model of the noise added to the records/sec of a workload: see [`NoiseOverlay`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Noise {
+    /// Records/sec deviate from the nominal ones following a normal distribution,
+    /// with standard deviation equal to this percentage of the nominal records/sec.
+    Gaussian(f64),
+
+    /// Records/sec are a count following a Poisson distribution, with mean equal to the nominal records/sec:
+    /// this is how the amount of events per second fluctuates, when events occur independently of each other.
+    Poisson,
+}
+
+impl Noise {
+    /// Implementation of [`clap::value_parser`], used to create an argument by parsing a user-provided value.
+    ///
+    /// The `&str` provided must be one of:
+    ///
+    /// * `gaussian:PCT`, where `PCT` is parsed to a non-negative `f64` percentage of the nominal records/sec
+    /// * `poisson`
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn clap_parser(noise_as_str: &str) -> Result<Noise, String> {
+        match noise_as_str.split_once(':') {
+            None if noise_as_str == "poisson" => Ok(Noise::Poisson),
+            Some(("gaussian", pct)) => match pct.parse::<f64>() {
+                Err(e) => Err(format!("Failed to parse INPUT 'PCT' from 'gaussian:PCT': {e}")),
+                Ok(v) if !(v.is_finite() && v >= 0.0) => {
+                    Err("INPUT 'PCT' of 'gaussian:PCT' must be a non-negative number".to_string())
+                },
+                Ok(v) => Ok(Noise::Gaussian(v)),
+            },
+            _ => Err(format!("Unsupported NOISE '{noise_as_str}'")),
+        }
+    }
+
+    /// Applies the noise to the nominal `rate`, drawing from `rng`.
+    ///
+    /// The result is never negative, and on average it's `rate`:
+    ///
+    /// * [`Noise::Gaussian`] is truncated symmetrically around `rate` (see [`Noise::gaussian_max_deviation`]),
+    ///   drawing again when out of bounds
+    /// * [`Noise::Poisson`] is a count, as drawn
+    fn apply<R: rand::Rng>(&self, rate: f64, rng: &mut R) -> f64 {
+        if rate == 0.0 {
+            return 0.0;
+        }
+
+        match self {
+            Noise::Gaussian(pct) => {
+                let max_deviation = Noise::gaussian_max_deviation(rate, *pct);
+                // Valid, as the standard deviation is finite and non-negative
+                let normal = Normal::new(rate, rate * pct / 100.0).unwrap();
+
+                // Giving up leaves `rate` as it is: that's the mean anyway
+                (0..MAX_REDRAWS)
+                    .map(|_| normal.sample(rng))
+                    .find(|noisy| (noisy - rate).abs() <= max_deviation)
+                    .unwrap_or(rate)
+            },
+            // Valid, as the mean is finite and greater than 0
+            Noise::Poisson => Poisson::new(rate).unwrap().sample(rng),
+        }
+    }
+
+    /// How far from `rate` a [`Noise::Gaussian`] of `pct` goes: [`MAX_DEVIATIONS`] standard deviations,
+    /// but never further than `rate` itself, so that records/sec are never negative.
+    fn gaussian_max_deviation(rate: f64, pct: f64) -> f64 {
+        (MAX_DEVIATIONS * rate * pct / 100.0).min(rate)
+    }
+
+    /// The highest records/sec the noise can turn `rate` into (practically, for [`Noise::Poisson`]).
+    fn max_of(&self, rate: f64) -> f64 {
+        match self {
+            _ if rate == 0.0 => 0.0,
+            Noise::Gaussian(pct) => rate + Noise::gaussian_max_deviation(rate, *pct),
+            // For low rates, the probability of any count is higher than the standard deviation suggests
+            Noise::Poisson => rate + POISSON_MAX_DEVIATIONS * rate.sqrt() + POISSON_MAX_DEVIATIONS,
+        }
+    }
+}
+
+impl fmt::Display for Noise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Noise::Gaussian(pct) => write!(f, "gaussian:{pct}"),
+            Noise::Poisson => write!(f, "poisson"),
+        }
+    }
+}
+
+/// A [`Noise`] is deserialized from the same format parsed by [`Noise::clap_parser`].
+impl<'de> Deserialize<'de> for Noise {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let noise_as_str = String::deserialize(deserializer)?;
+
+        Noise::clap_parser(&noise_as_str).map_err(de::Error::custom)
+    }
+}
+
+/// A [`RateSchedule`] with [`Noise`] applied to the nominal records/sec of each second.
+///
+/// The noise of a given second only depends on the [`Randomness`] and the second itself:
+/// with a seed, the same noise is applied, run after run.
+#[derive(Debug)]
+pub struct NoiseOverlay {
+    schedule: Arc<dyn RateSchedule>,
+    noise: Noise,
+    randomness: Randomness,
+}
+
+impl NoiseOverlay {
+    pub fn new(schedule: Arc<dyn RateSchedule>, noise: Noise, randomness: Randomness) -> NoiseOverlay {
+        NoiseOverlay {
+            schedule,
+            noise,
+            // The same second must always have the same noise
+            randomness: randomness.to_seeded(),
+        }
+    }
+}
+
+impl RateSchedule for NoiseOverlay {
+    fn records_per_sec_at(&self, sec: u64) -> f64 {
+        let rate = self.schedule.records_per_sec_at(sec);
+
+        self.noise.apply(rate, &mut self.randomness.rng(RandomStream::Noise, sec))
+    }
+
+    fn phase_at(&self, sec: u64) -> WorkloadPhase {
+        self.schedule.phase_at(sec)
+    }
+
+    fn max_records_per_sec(&self) -> f64 {
+        self.noise.max_of(self.schedule.max_records_per_sec())
+    }
+
+    fn overall_duration_sec(&self) -> u32 {
+        self.schedule.overall_duration_sec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transition::Transition;
+    use crate::workload::{Workload, WorkloadSegment};
+
+    use super::*;
+
+    fn noisy(rate: f64, noise: Noise, seed: u64) -> Vec<f64> {
+        let workload = Arc::new(Workload::from_segments(vec![WorkloadSegment::new(rate, 60, Transition::None)]));
+        let overlay = NoiseOverlay::new(workload, noise, Randomness::new(Some(seed)));
+
+        (0..2_000).map(|sec| overlay.records_per_sec_at(sec)).collect()
+    }
+
+    fn mean(rates: &[f64]) -> f64 {
+        rates.iter().sum::<f64>() / rates.len() as f64
+    }
+
+    #[test]
+    fn test_gaussian() {
+        let rates = noisy(1000.0, Noise::Gaussian(10.0), 42);
+
+        // Around the nominal rate, within bounds
+        assert!((mean(&rates) - 1000.0).abs() < 10.0);
+        assert!(rates.iter().all(|r| *r >= 0.0 && *r <= 1300.0));
+        assert!(rates.iter().any(|r| *r > 1100.0) && rates.iter().any(|r| *r < 900.0));
+
+        // Never negative, even when deviating a lot: truncated symmetrically, so still around the nominal rate
+        let rates = noisy(10.0, Noise::Gaussian(200.0), 42);
+        assert!(rates.iter().all(|r| *r >= 0.0 && *r <= 20.0));
+        assert!((mean(&rates) - 10.0).abs() < 0.5);
+        assert_eq!(20.0, Noise::Gaussian(200.0).max_of(10.0));
+        assert_eq!(1300.0, Noise::Gaussian(10.0).max_of(1000.0));
+
+        // Same seed, same noise
+        let rates = noisy(1000.0, Noise::Gaussian(10.0), 42);
+        assert_eq!(rates, noisy(1000.0, Noise::Gaussian(10.0), 42));
+        assert_ne!(rates, noisy(1000.0, Noise::Gaussian(10.0), 43));
+    }
+
+    #[test]
+    fn test_poisson() {
+        let rates = noisy(20.0, Noise::Poisson, 42);
+
+        // Counts around the nominal rate
+        assert!((mean(&rates) - 20.0).abs() < 0.5);
+        assert!(rates.iter().all(|r| r.fract() == 0.0 && *r >= 0.0));
+        assert!(rates.iter().all(|r| *r <= Noise::Poisson.max_of(20.0)));
+
+        // Not biased by low rates either
+        assert!((mean(&noisy(0.5, Noise::Poisson, 42)) - 0.5).abs() < 0.05);
+
+        // Nothing from nothing
+        assert!(noisy(0.0, Noise::Poisson, 42).iter().all(|r| *r == 0.0));
+    }
+
+    #[test]
+    fn test_clap_parser() {
+        assert_eq!(Ok(Noise::Gaussian(5.0)), Noise::clap_parser("gaussian:5"));
+        assert_eq!(Ok(Noise::Poisson), Noise::clap_parser("poisson"));
+        assert_eq!("gaussian:2.5", Noise::clap_parser("gaussian:2.5").unwrap().to_string());
+
+        assert_eq!(
+            Err("INPUT 'PCT' of 'gaussian:PCT' must be a non-negative number".to_string()),
+            Noise::clap_parser("gaussian:-5")
+        );
+        assert_eq!(Err("Unsupported NOISE 'pink'".to_string()), Noise::clap_parser("pink"));
+    }
+}
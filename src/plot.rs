@@ -0,0 +1,141 @@
+use std::fmt::Write;
+
+use clap::ValueEnum;
+
+use crate::records_tap::ThroughputUnit;
+use crate::workload::{RateSchedule, WorkloadPhase};
+
+/// Width of the ASCII chart, in columns: when plotting more seconds, multiple seconds share a column.
+const CHART_WIDTH: u64 = 100;
+
+/// Height of the ASCII chart, in rows.
+const CHART_HEIGHT: u32 = 20;
+
+/// Format in which a [`RateSchedule`] is plotted, when doing a dry-run.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Default)]
+pub enum PlotFormat {
+    /// ASCII chart of the records/sec (or bytes/sec) over time, annotated with the phases boundaries.
+    #[default]
+    Chart,
+
+    /// CSV with one line per second: 'sec,phase,records_per_sec' (or 'bytes_per_sec'), rounded to 3 decimals.
+    Csv,
+}
+
+/// Plots the given [`RateSchedule`], second by second, for the first `duration_sec` seconds.
+///
+/// The amounts described by the schedule are labeled with the given [`ThroughputUnit`].
+pub fn plot(workload: &dyn RateSchedule, unit: ThroughputUnit, duration_sec: u64, format: PlotFormat) -> String {
+    let schedule =
+        (0..duration_sec).map(|sec| (workload.phase_at(sec), workload.records_per_sec_at(sec))).collect::<Vec<_>>();
+
+    match format {
+        PlotFormat::Chart => plot_chart(&schedule, unit),
+        PlotFormat::Csv => plot_csv(&schedule, unit),
+    }
+}
+
+fn plot_csv(schedule: &[(WorkloadPhase, f64)], unit: ThroughputUnit) -> String {
+    let mut out = format!("sec,phase,{unit}_per_sec\n");
+    for (sec, (phase, records)) in schedule.iter().enumerate() {
+        writeln!(out, "{sec},{phase:?},{}", (records * 1000.0).round() / 1000.0).unwrap();
+    }
+
+    out
+}
+
+fn plot_chart(schedule: &[(WorkloadPhase, f64)], unit: ThroughputUnit) -> String {
+    let secs = schedule.len() as u64;
+    let width = secs.min(CHART_WIDTH);
+    let max = schedule.iter().map(|(_, records)| *records).fold(0f64, f64::max);
+
+    // Seconds at which a new phase begins
+    let boundaries = schedule
+        .iter()
+        .enumerate()
+        .filter(|(sec, (phase, _))| *sec == 0 || schedule[sec - 1].0 != *phase)
+        .map(|(sec, (phase, _))| (sec as u64, phase.clone()))
+        .collect::<Vec<_>>();
+
+    // Each column covers a range of seconds, and is as tall as the highest records/sec in it
+    let column_secs = |col: u64| (col * secs / width) as usize..((col + 1) * secs / width) as usize;
+    let heights = (0..width)
+        .map(|col| {
+            let records = schedule[column_secs(col)].iter().map(|(_, r)| *r).fold(0f64, f64::max);
+            if max == 0.0 {
+                0
+            } else {
+                (records / max * CHART_HEIGHT as f64).round() as u32
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let label_width = max.to_string().len();
+    let mut out = format!("{:>label_width$} {unit}/sec\n", "");
+    for row in (1..=CHART_HEIGHT).rev() {
+        let label = match row {
+            CHART_HEIGHT => max.to_string(),
+            _ => String::new(),
+        };
+        let bars = heights
+            .iter()
+            .map(|h| {
+                if *h >= row {
+                    '#'
+                } else {
+                    ' '
+                }
+            })
+            .collect::<String>();
+        writeln!(out, "{label:>label_width$} |{}", bars.trim_end()).unwrap();
+    }
+    writeln!(out, "{:>label_width$} +{}", 0, "-".repeat(width as usize)).unwrap();
+
+    // Mark the columns where a new phase begins
+    let marks = (0..width)
+        .map(|col| {
+            let range = column_secs(col);
+            if boundaries.iter().any(|(sec, _)| range.contains(&(*sec as usize))) {
+                '^'
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>();
+    writeln!(out, "{:>label_width$}  {}", "", marks.trim_end()).unwrap();
+    writeln!(out, "{:>label_width$}  0s{:>w$}", "", format!("{secs}s"), w = (width as usize).saturating_sub(2))
+        .unwrap();
+
+    writeln!(out).unwrap();
+    writeln!(out, "Phases (^):").unwrap();
+    for (sec, phase) in boundaries {
+        writeln!(out, "  {sec}s: {phase:?}").unwrap();
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transition::Transition;
+    use crate::workload::Workload;
+
+    #[test]
+    fn test_plot() {
+        let workload = Workload::new(0.5, 5, 100.0, 5, Transition::Linear, 5, Transition::None, 0);
+
+        let csv = plot(&workload, ThroughputUnit::Records, 15, PlotFormat::Csv);
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(16, lines.len());
+        assert_eq!("sec,phase,records_per_sec", lines[0]);
+        assert_eq!("0,Min,0.5", lines[1]);
+        assert_eq!("6,Up,10.848", lines[7]);
+        assert_eq!("10,Max,100", lines[11]);
+
+        let chart = plot(&workload, ThroughputUnit::Bytes, 30, PlotFormat::Chart);
+        assert!(chart.contains("bytes/sec"));
+        assert!(chart.contains("100 |"));
+        assert!(chart.contains("  0s: Min\n  5s: Up\n  10s: Max\n  15s: Min\n  20s: Up\n  25s: Max\n"));
+    }
+}
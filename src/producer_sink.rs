@@ -1,11 +1,9 @@
-use std::sync::{Arc, Mutex};
-
 use rdkafka::error::KafkaError;
 use rdkafka::producer::FutureProducer;
 use rdkafka::util::Timeout;
 use rdkafka::ClientConfig;
 use tokio::sync::mpsc;
-use tokio::task::JoinHandle;
+use tokio::task::{JoinError, JoinHandle, JoinSet};
 
 use crate::GeneratedRecord;
 
@@ -31,14 +29,18 @@ impl ProducerSink {
     /// Every [`GeneratedRecord`] from `records_rx` is converted to [`FutureRecord`] via  [`GeneratedRecord::as_future_record`],
     /// and then sent via the [`FutureProducer`].
     ///
-    /// The spawned [`tokio::task`] terminates once the sender side of the given `records_rx` is closed.
+    /// The spawned [`tokio::task`] terminates once the sender side of the given `records_rx` is closed,
+    /// and all the records received have been either delivered or failed to.
     /// On termination, it returns 2 numbers: the number of records successfully sent, and that failed to send.
     pub fn spawn(&mut self, mut records_rx: mpsc::Receiver<GeneratedRecord>) -> JoinHandle<(u64, u64)> {
         let producer = self.producer.clone();
 
         tokio::spawn(async move {
-            let send_success = Arc::new(Mutex::new(0u64));
-            let send_fail = Arc::new(Mutex::new(0u64));
+            let mut send_success = 0u64;
+            let mut send_fail = 0u64;
+
+            // Deliveries in flight: each one resolves to `true` if the record was delivered
+            let mut deliveries = JoinSet::new();
 
             // Stops when `records_rx` receives `None` back:
             // this means that the `records_tx` has been closed (dropped).
@@ -46,31 +48,49 @@ impl ProducerSink {
                 trace!("Generated Record received");
 
                 let producer = producer.clone();
-                let send_success = send_success.clone();
-                let send_fail = send_fail.clone();
 
-                tokio::spawn(async move {
+                deliveries.spawn(async move {
                     let rec = gen_rec.as_future_record();
 
                     // Finally, send the record (or wait if the producer internal queue is full)
                     match producer.send(rec, Timeout::Never).await {
                         Ok((partition, offset)) => {
-                            *(send_success.lock().unwrap()) += 1;
-                            trace!("Delivered => partition: {partition}, offset: {offset}")
+                            trace!("Delivered => partition: {partition}, offset: {offset}");
+                            true
                         },
                         Err((e, _)) => {
-                            *(send_fail.lock().unwrap()) += 1;
-                            error!("Failed record delivery: {:?}", e)
+                            error!("Failed record delivery: {:?}", e);
+                            false
                         },
                     }
                 });
+
+                // Collect the deliveries concluded so far
+                while let Some(delivered) = deliveries.try_join_next() {
+                    count_delivery(delivered, &mut send_success, &mut send_fail);
+                }
+            }
+
+            // Await the deliveries still in flight
+            while let Some(delivered) = deliveries.join_next().await {
+                count_delivery(delivered, &mut send_success, &mut send_fail);
             }
 
             // Return some basic stats:
             // how many did we sent, and how many we failed to send.
-            let success = send_success.lock().unwrap();
-            let fail = send_fail.lock().unwrap();
-            (*success, *fail)
+            (send_success, send_fail)
         })
     }
 }
+
+/// Counts the outcome of a concluded delivery task, towards either `success` or `fail`.
+fn count_delivery(delivered: Result<bool, JoinError>, success: &mut u64, fail: &mut u64) {
+    match delivered {
+        Ok(true) => *success += 1,
+        Ok(false) => *fail += 1,
+        Err(e) => {
+            error!("Failed record delivery task: {e}");
+            *fail += 1;
+        },
+    }
+}
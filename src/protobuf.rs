@@ -0,0 +1,361 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use protobuf::descriptor::FileDescriptorSet;
+use protobuf::reflect::{FileDescriptor, MessageDescriptor, ReflectValueBox, RuntimeFieldType, RuntimeType};
+use protobuf::{Message, MessageDyn};
+use protobuf_parse::Parser;
+use rand::distributions::{Alphanumeric, DistString};
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+use crate::random::{DEFAULT_MAX_ITEMS, DEFAULT_MAX_LENGTH};
+use crate::schema_registry::{wire_format_prefix, write_varint};
+
+/// How deep messages are nested into each other, before leaving optional message fields unset.
+///
+/// Messages can be recursive (ex. a tree): this guarantees generation comes to an end.
+const MAX_DEPTH: usize = 8;
+
+/// A [Protocol Buffers](https://protobuf.dev/) message type, to generate random messages of.
+///
+/// The message type is loaded once, via [`ProtobufSchema::load`], from a `.proto` file or a compiled descriptor set
+/// (ex. `protoc --include_imports --descriptor_set_out=...`), and then sampled for every record
+/// via [`ProtobufSchema::sample`]. Once the `.proto` file is registered in a Schema Registry
+/// (see [`ProtobufSchema::with_schema_id`]), messages are prefixed as per the Confluent wire format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProtobufSchema {
+    /// Path to the file the message type was loaded from
+    path: PathBuf,
+
+    /// The `.proto` file, if the message type was loaded from one (rather than from a descriptor set)
+    source: Option<String>,
+
+    /// The message type to generate
+    message: MessageDescriptor,
+
+    /// Position of the message type in its file, (first) among the top-level messages and then among nested ones
+    message_indexes: Vec<i64>,
+
+    /// ID of the schema in the Schema Registry, if registered
+    schema_id: Option<u32>,
+}
+
+impl ProtobufSchema {
+    /// Loads the message type from the `&str` provided, that must be of format `PATH:MESSAGE`:
+    ///
+    /// * `PATH` is the path to a `.proto` file (imports are relative to its directory),
+    ///   or to a descriptor set (any other extension)
+    /// * `MESSAGE` is the name of the message type, either full (ex. `shop.Order`) or relative to its package
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn load(path_and_message: &str) -> Result<ProtobufSchema, String> {
+        let Some((path, message_name)) = path_and_message.rsplit_once(':') else {
+            return Err("Should have 'PATH:MESSAGE' format".to_string());
+        };
+        let path = PathBuf::from(path);
+
+        let (files, source) = if path.extension().is_some_and(|ext| ext == "proto") {
+            let source = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read INPUT file '{}': {e}", path.display()))?;
+            let parsed = Parser::new()
+                .pure()
+                .include(path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new(".")))
+                .input(&path)
+                .parse_and_typecheck()
+                .map_err(|e| format!("Failed to parse INPUT file '{}': {e:#}", path.display()))?;
+
+            // Only message types of the file itself are looked up, not those of its imports
+            let input_file = parsed.relative_paths[0].as_path().to_str().to_string();
+            let files = build_files(parsed.file_descriptors, &path)?;
+            (files.into_iter().filter(|f| f.name() == input_file).collect(), Some(source))
+        } else {
+            let bytes = fs::read(&path).map_err(|e| format!("Failed to read INPUT file '{}': {e}", path.display()))?;
+            let descriptor_set = FileDescriptorSet::parse_from_bytes(&bytes)
+                .map_err(|e| format!("Failed to parse INPUT file '{}' as descriptor set: {e}", path.display()))?;
+
+            (build_files(descriptor_set.file, &path)?, None)
+        };
+
+        let Some(message) = files.iter().find_map(|f| {
+            f.message_by_full_name(&format!(".{message_name}"))
+                .or_else(|| f.message_by_package_relative_name(message_name))
+        }) else {
+            return Err(format!("Message '{message_name}' not found in INPUT file '{}'", path.display()));
+        };
+
+        Ok(ProtobufSchema {
+            path,
+            source,
+            message_indexes: message_indexes(&message),
+            message,
+            schema_id: None,
+        })
+    }
+
+    /// The `.proto` file, as found on disk: `None` if loaded from a descriptor set.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// The files imported by the file the message type is defined in (ex. `google/protobuf/timestamp.proto`).
+    pub fn imports(&self) -> &[String] {
+        &self.message.file_descriptor().proto().dependency
+    }
+
+    /// Returns this message type, with its `.proto` file registered in the Schema Registry with the given `schema_id`.
+    pub fn with_schema_id(self, schema_id: u32) -> ProtobufSchema {
+        ProtobufSchema {
+            schema_id: Some(schema_id),
+            ..self
+        }
+    }
+
+    /// Samples a message, with fields populated with random values drawn from the given `rng`, and encodes it.
+    ///
+    /// If the `.proto` file is registered, the message is prefixed as per the Confluent wire format
+    /// (i.e. magic byte `0`, followed by the 4 bytes of the schema ID and by the message indexes).
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> protobuf::Result<Vec<u8>> {
+        let mut encoded = Vec::new();
+
+        if let Some(id) = self.schema_id {
+            encoded.extend_from_slice(&wire_format_prefix(id));
+
+            // The (most common) case of the first message type, is encoded as just `0`
+            if self.message_indexes == [0] {
+                write_varint(&mut encoded, 0);
+            } else {
+                write_varint(&mut encoded, self.message_indexes.len() as i64);
+                self.message_indexes.iter().for_each(|i| write_varint(&mut encoded, *i));
+            }
+        }
+        encoded.extend(sample_message(&self.message, rng, 0).write_to_bytes_dyn()?);
+
+        Ok(encoded)
+    }
+}
+
+/// Builds the (dynamic) [`FileDescriptor`]s of the `files` loaded from `path`.
+fn build_files(
+    files: Vec<protobuf::descriptor::FileDescriptorProto>,
+    path: &Path,
+) -> Result<Vec<FileDescriptor>, String> {
+    FileDescriptor::new_dynamic_fds(files, &[])
+        .map_err(|e| format!("Failed to load message types from INPUT file '{}': {e}", path.display()))
+}
+
+/// Returns the position of the `message` type in its file, as expected by the Confluent wire format.
+fn message_indexes(message: &MessageDescriptor) -> Vec<i64> {
+    let mut indexes = Vec::new();
+
+    let mut current = message.clone();
+    loop {
+        let enclosing = current.enclosing_message();
+        let siblings: Vec<MessageDescriptor> = match &enclosing {
+            Some(e) => e.nested_messages().collect(),
+            None => current.file_descriptor().messages().collect(),
+        };
+        // Valid, as a message type is always among the messages of its parent
+        indexes.push(siblings.iter().position(|m| *m == current).unwrap() as i64);
+
+        match enclosing {
+            Some(e) => current = e,
+            None => break,
+        }
+    }
+
+    indexes.reverse();
+    indexes
+}
+
+/// Samples a message of type `message`, nested `depth` levels into the one being generated.
+///
+/// All fields are populated, except for optional ones (set half of the times), and for `oneof`s (one field each).
+/// Past [`MAX_DEPTH`], optional message fields are left unset.
+fn sample_message<R: Rng>(message: &MessageDescriptor, rng: &mut R, depth: usize) -> Box<dyn MessageDyn> {
+    let mut sampled = message.new_instance();
+
+    // Picks the one field of each `oneof` to set
+    let oneof_fields: Vec<_> = message.oneofs().filter_map(|o| o.fields().choose(rng)).collect();
+
+    for field in message.fields() {
+        let is_message = matches!(
+            field.runtime_field_type(),
+            RuntimeFieldType::Singular(RuntimeType::Message(_))
+                | RuntimeFieldType::Repeated(RuntimeType::Message(_))
+                | RuntimeFieldType::Map(_, RuntimeType::Message(_))
+        );
+        let is_set = match field.containing_oneof_including_synthetic() {
+            _ if field.is_required() => true,
+            _ if is_message && depth >= MAX_DEPTH => false,
+            Some(o) if o.is_synthetic() => rng.gen_bool(0.5),
+            Some(_) => oneof_fields.contains(&field),
+            None => true,
+        };
+        if !is_set {
+            continue;
+        }
+
+        match field.runtime_field_type() {
+            RuntimeFieldType::Singular(t) => {
+                let value = sample_value(&t, rng, depth);
+                field.set_singular_field(sampled.as_mut(), value);
+            },
+            RuntimeFieldType::Repeated(t) => {
+                let mut repeated = field.mut_repeated(sampled.as_mut());
+                for _ in 0..rng.gen_range(0..=DEFAULT_MAX_ITEMS) {
+                    repeated.push(sample_value(&t, rng, depth));
+                }
+            },
+            RuntimeFieldType::Map(k, v) => {
+                let mut map = field.mut_map(sampled.as_mut());
+                for _ in 0..rng.gen_range(0..=DEFAULT_MAX_ITEMS) {
+                    map.insert(sample_value(&k, rng, depth), sample_value(&v, rng, depth));
+                }
+            },
+        }
+    }
+
+    sampled
+}
+
+/// Samples a value of type `t`, for a field of a message nested `depth` levels into the one being generated.
+fn sample_value<R: Rng>(t: &RuntimeType, rng: &mut R, depth: usize) -> ReflectValueBox {
+    match t {
+        RuntimeType::I32 => ReflectValueBox::I32(rng.gen()),
+        RuntimeType::I64 => ReflectValueBox::I64(rng.gen()),
+        RuntimeType::U32 => ReflectValueBox::U32(rng.gen()),
+        RuntimeType::U64 => ReflectValueBox::U64(rng.gen()),
+        RuntimeType::F32 => ReflectValueBox::F32(rng.gen()),
+        RuntimeType::F64 => ReflectValueBox::F64(rng.gen()),
+        RuntimeType::Bool => ReflectValueBox::Bool(rng.gen()),
+        RuntimeType::String => {
+            let length = rng.gen_range(0..=DEFAULT_MAX_LENGTH);
+            ReflectValueBox::String(Alphanumeric.sample_string(rng, length))
+        },
+        RuntimeType::VecU8 => {
+            ReflectValueBox::Bytes((0..rng.gen_range(0..=DEFAULT_MAX_LENGTH)).map(|_| rng.gen()).collect())
+        },
+        RuntimeType::Enum(e) => {
+            // Valid, as enums have at least 1 value
+            let value = e.values().choose(rng).unwrap().value();
+            ReflectValueBox::Enum(e.clone(), value)
+        },
+        RuntimeType::Message(m) => ReflectValueBox::Message(sample_message(m, rng, depth + 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    const PROTO: &str = r#"
+        syntax = "proto3";
+        package shop;
+
+        message Customer { string name = 1; }
+
+        message Order {
+            message Line {
+                string sku = 1;
+                uint32 quantity = 2;
+            }
+            enum Status { NEW = 0; PAID = 1; }
+
+            string id = 1;
+            repeated Line lines = 2;
+            map<string, int64> totals = 3;
+            Status status = 4;
+            oneof payment { string card = 5; string voucher = 6; }
+            optional Order previous = 7;
+        }
+    "#;
+
+    /// Writes the test `.proto` file to a temporary directory, returning its path.
+    fn write_proto(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, PROTO).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_sample() {
+        let path = write_proto("ksunami_test_sample.proto");
+        let schema = ProtobufSchema::load(&format!("{}:shop.Order", path.display())).unwrap();
+        assert_eq!(vec![1], schema.message_indexes);
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for _ in 0..50 {
+            let encoded = schema.sample(&mut rng).unwrap();
+            let decoded = schema.message.parse_from_bytes(&encoded).unwrap();
+
+            let card = schema.message.field_by_name("card").unwrap();
+            let voucher = schema.message.field_by_name("voucher").unwrap();
+            // At most one field of a `oneof` (an empty string is indistinguishable from an unset field)
+            assert!(!(card.has_field(decoded.as_ref()) && voucher.has_field(decoded.as_ref())));
+            assert!(schema.message.field_by_name("lines").unwrap().get_repeated(decoded.as_ref()).len() <= 5);
+        }
+
+        // Once registered, in wire format
+        let encoded = schema.clone().with_schema_id(258).sample(&mut rng).unwrap();
+        assert_eq!(vec![0, 0, 0, 1, 2, 2, 2], encoded[..7]);
+        assert!(schema.message.parse_from_bytes(&encoded[7..]).is_ok());
+
+        // Nested message types, by package-relative name
+        let line = ProtobufSchema::load(&format!("{}:Order.Line", path.display())).unwrap();
+        assert_eq!(vec![1, 0], line.message_indexes);
+        let customer = ProtobufSchema::load(&format!("{}:Customer", path.display())).unwrap().with_schema_id(1);
+        assert_eq!(vec![0, 0, 0, 0, 1, 0], customer.sample(&mut rng).unwrap()[..6]);
+        assert!(customer.imports().is_empty());
+    }
+
+    #[test]
+    fn test_imports() {
+        write_proto("ksunami_test_imported.proto");
+        let path = std::env::temp_dir().join("ksunami_test_importing.proto");
+        fs::write(
+            &path,
+            r#"
+                syntax = "proto3";
+                import "ksunami_test_imported.proto";
+
+                message Cart { repeated shop.Order.Line lines = 1; }
+            "#,
+        )
+        .unwrap();
+
+        let schema = ProtobufSchema::load(&format!("{}:Cart", path.display())).unwrap();
+        assert_eq!(["ksunami_test_imported.proto".to_string()], schema.imports());
+        assert!(schema.sample(&mut StdRng::seed_from_u64(42)).is_ok());
+    }
+
+    #[test]
+    fn test_load_descriptor_set() {
+        let proto_path = write_proto("ksunami_test_descriptor_set.proto");
+        let descriptor_set =
+            Parser::new().pure().include(std::env::temp_dir()).input(&proto_path).file_descriptor_set().unwrap();
+        let path = std::env::temp_dir().join("ksunami_test_descriptor_set.desc");
+        fs::write(&path, descriptor_set.write_to_bytes().unwrap()).unwrap();
+
+        let schema = ProtobufSchema::load(&format!("{}:shop.Order.Line", path.display())).unwrap();
+        assert_eq!(None, schema.source());
+        assert_eq!(vec![1, 0], schema.message_indexes);
+        assert!(schema.sample(&mut StdRng::seed_from_u64(42)).is_ok());
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let path = write_proto("ksunami_test_errors.proto");
+
+        assert_eq!(
+            Err(format!("Message 'Nope' not found in INPUT file '{}'", path.display())),
+            ProtobufSchema::load(&format!("{}:Nope", path.display()))
+        );
+        assert_eq!(Err("Should have 'PATH:MESSAGE' format".to_string()), ProtobufSchema::load("order.proto"));
+        assert!(ProtobufSchema::load("does_not_exist.proto:Order").unwrap_err().starts_with("Failed to read"));
+        assert!(ProtobufSchema::load("Cargo.toml:Order").unwrap_err().contains("as descriptor set"));
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{de, Deserialize, Deserializer};
+
+use crate::transition::Transition;
+use crate::workload::{RateSchedule, Workload, WorkloadPhase, WorkloadSegment};
+
+/// A one-shot ramp of the records/sec, from one amount to another, lasting `duration_sec` seconds.
+///
+/// Used to warm-up before the first cycle of a workload (see [`WarmUpSchedule`]),
+/// and to cool-down at the end of a run.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ramp {
+    duration_sec: u32,
+    transition: Transition,
+}
+
+impl Ramp {
+    pub fn new(duration_sec: u32, transition: Transition) -> Ramp {
+        Ramp {
+            duration_sec,
+            transition,
+        }
+    }
+
+    /// Implementation of [`clap::value_parser`], used to create an argument by parsing a user-provided value.
+    ///
+    /// The `&str` provided must be of format `SEC[:TRANSITION_TYPE]`:
+    ///
+    /// * `SEC` is parsed to `u32` duration of the ramp, in seconds (greater than `0`)
+    /// * `TRANSITION_TYPE` (optional) is parsed to the [`Transition`] of the ramp, defaulting to `linear`
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn clap_parser(ramp_as_str: &str) -> Result<Ramp, String> {
+        let mut parts = ramp_as_str.splitn(2, ':');
+
+        let duration_sec = match parts.next().unwrap_or_default().parse::<u32>() {
+            Err(e) => return Err(format!("Failed to parse 'SEC' from 'SEC[:TRANSITION_TYPE]': {e}")),
+            Ok(0) => return Err("'SEC' must be greater than 0".to_string()),
+            Ok(v) => v,
+        };
+
+        let transition = match parts.next() {
+            None => Transition::Linear,
+            Some(s) => Transition::clap_parser(s)
+                .map_err(|e| format!("Failed to parse 'TRANSITION_TYPE' from 'SEC[:TRANSITION_TYPE]': {e}"))?,
+        };
+
+        Ok(Ramp::new(duration_sec, transition))
+    }
+
+    pub fn duration_sec(&self) -> u32 {
+        self.duration_sec
+    }
+
+    /// The records/sec at `sec` seconds into the ramp, going `from` one amount `to` another.
+    ///
+    /// At second `0` it's `from`, and it gets to `to` at `duration_sec` (the first second after the ramp).
+    pub fn records_per_sec_at(&self, from: f64, to: f64, sec: u64) -> f64 {
+        if sec >= self.duration_sec as u64 {
+            return to;
+        }
+
+        // The ramp is the transition into the first segment, coming from the (otherwise unused) last one
+        let ramp = Workload::from_segments(vec![
+            WorkloadSegment::new(to, self.duration_sec, self.transition),
+            WorkloadSegment::new(from, 1, Transition::None),
+        ]);
+        ramp.records_per_sec_at(sec)
+    }
+}
+
+impl fmt::Display for Ramp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.duration_sec, self.transition)
+    }
+}
+
+/// A [`Ramp`] is deserialized from the same format parsed by [`Ramp::clap_parser`].
+impl<'de> Deserialize<'de> for Ramp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let ramp_as_str = String::deserialize(deserializer)?;
+
+        Ramp::clap_parser(&ramp_as_str).map_err(de::Error::custom)
+    }
+}
+
+/// A [`RateSchedule`] preceded by a one-shot warm-up: a [`Ramp`] from `0` records/sec to where the schedule begins.
+///
+/// The schedule begins once the warm-up is over, and never warms-up again: its cycles repeat as usual.
+#[derive(Debug)]
+pub struct WarmUpSchedule {
+    schedule: Arc<dyn RateSchedule>,
+    warm_up: Ramp,
+}
+
+impl WarmUpSchedule {
+    pub fn new(schedule: Arc<dyn RateSchedule>, warm_up: Ramp) -> WarmUpSchedule {
+        WarmUpSchedule {
+            schedule,
+            warm_up,
+        }
+    }
+
+    fn is_warming_up(&self, sec: u64) -> bool {
+        sec < self.warm_up.duration_sec as u64
+    }
+}
+
+impl RateSchedule for WarmUpSchedule {
+    fn records_per_sec_at(&self, sec: u64) -> f64 {
+        if self.is_warming_up(sec) {
+            self.warm_up.records_per_sec_at(0.0, self.schedule.records_per_sec_at(0), sec)
+        } else {
+            self.schedule.records_per_sec_at(sec - self.warm_up.duration_sec as u64)
+        }
+    }
+
+    fn phase_at(&self, sec: u64) -> WorkloadPhase {
+        if self.is_warming_up(sec) {
+            WorkloadPhase::Up
+        } else {
+            self.schedule.phase_at(sec - self.warm_up.duration_sec as u64)
+        }
+    }
+
+    fn max_records_per_sec(&self) -> f64 {
+        self.schedule.max_records_per_sec()
+    }
+
+    /// The cycle is the one of the schedule: the warm-up happens only once, before the first cycle.
+    fn overall_duration_sec(&self) -> u32 {
+        self.schedule.overall_duration_sec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ramp() {
+        let ramp = Ramp::new(4, Transition::Linear);
+        let rates = (0..6).map(|sec| ramp.records_per_sec_at(100.0, 0.0, sec).round()).collect::<Vec<_>>();
+        assert_eq!(vec![100.0, 84.0, 50.0, 16.0, 0.0, 0.0], rates);
+
+        let ramp = Ramp::new(2, Transition::None);
+        assert_eq!(10.0, ramp.records_per_sec_at(0.0, 10.0, 0));
+    }
+
+    #[test]
+    fn test_warm_up() {
+        let workload = Arc::new(Workload::from_segments(vec![
+            WorkloadSegment::new(100.0, 2, Transition::None),
+            WorkloadSegment::new(200.0, 2, Transition::None),
+        ]));
+        let warm = WarmUpSchedule::new(workload, Ramp::new(4, Transition::Linear));
+
+        let rates = (0..12).map(|sec| warm.records_per_sec_at(sec).round()).collect::<Vec<_>>();
+        assert_eq!(vec![0.0, 16.0, 50.0, 84.0, 100.0, 100.0, 200.0, 200.0, 100.0, 100.0, 200.0, 200.0], rates);
+        assert_eq!(WorkloadPhase::Up, warm.phase_at(0));
+        assert_eq!(WorkloadPhase::Max, warm.phase_at(6));
+        assert_eq!(4, warm.overall_duration_sec());
+    }
+
+    #[test]
+    fn test_clap_parser() {
+        assert_eq!(Ok(Ramp::new(60, Transition::Linear)), Ramp::clap_parser("60"));
+        assert_eq!(Ok(Ramp::new(30, Transition::EaseIn)), Ramp::clap_parser("30:ease-in"));
+        assert_eq!("10:bezier:0.1,0.2,0.3,0.4", Ramp::clap_parser("10:bezier:0.1,0.2,0.3,0.4").unwrap().to_string());
+        assert_eq!(Err("'SEC' must be greater than 0".to_string()), Ramp::clap_parser("0"));
+        assert!(Ramp::clap_parser("abc:linear").is_err());
+    }
+}
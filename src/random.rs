@@ -0,0 +1,143 @@
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+
+/// When not limited otherwise (ex. by a schema), the maximum length of a random string or sequence of bytes.
+pub const DEFAULT_MAX_LENGTH: usize = 16;
+
+/// When not limited otherwise (ex. by a schema), the maximum number of items of a random array, list or map.
+pub const DEFAULT_MAX_ITEMS: usize = 5;
+
+/// Range of random dates and times: from 2000-01-01 (included) to 2030-01-01 (excluded).
+pub const DATE_TIME_RANGE_SEC: (i64, i64) = (946_684_800, 1_893_456_000);
+
+/// The independent streams of randomness used across Ksunami.
+///
+/// Each stream draws from its own sequence of random generators, so that adding randomness
+/// to one part of the system doesn't alter what is generated by another.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RandomStream {
+    /// Randomness used to generate record keys.
+    RecordKey,
+
+    /// Randomness used to generate record payloads.
+    RecordPayload,
+
+    /// Randomness used to inject bursts in the workload.
+    Bursts,
+
+    /// Randomness used to add noise to the workload.
+    Noise,
+}
+
+/// Source of all the randomness of a run: when seeded, the run is reproducible.
+///
+/// Random generators are requested for a [`RandomStream`] and an `index` (ex. the number of the record
+/// being generated). With a seed, the generator returned only depends on the seed, the stream and the
+/// index: not on the order, or the thread, it's requested from. Without a seed, it's always different.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Randomness {
+    seed: Option<u64>,
+}
+
+impl Randomness {
+    pub fn new(seed: Option<u64>) -> Randomness {
+        Randomness {
+            seed,
+        }
+    }
+
+    /// Returns a seeded [`Randomness`]: this one, if seeded, otherwise one with a random seed.
+    ///
+    /// Useful where the same random values must be drawn again for the same `index` (ex. to look back in time).
+    pub fn to_seeded(self) -> Randomness {
+        Randomness {
+            seed: Some(self.seed.unwrap_or_else(|| thread_rng().gen())),
+        }
+    }
+
+    /// Returns the [`Randomness`] of the `index`-th independent part of the run (ex. a part of a composed workload).
+    ///
+    /// With a seed, each part is seeded differently, but still only depending on the seed: without, it's unseeded too.
+    pub fn fork(&self, index: u64) -> Randomness {
+        Randomness {
+            seed: self.seed.map(|seed| mix(seed ^ mix(index))),
+        }
+    }
+
+    /// Returns the random generator for the `index`-th use of the given `stream`.
+    pub fn rng(&self, stream: RandomStream, index: u64) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(mix(mix(seed ^ stream as u64) ^ index)),
+            None => StdRng::from_rng(thread_rng()).expect("Failed to seed random generator"),
+        }
+    }
+}
+
+/// Returns a random UUID (version 4), drawn from `rng`, in its canonical hyphenated form.
+pub fn uuid_v4<R: Rng>(rng: &mut R) -> String {
+    let mut bytes = rng.gen::<[u8; 16]>();
+    // Version 4 (random), variant 1 (RFC 4122)
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let mut uuid = String::with_capacity(36);
+    for (i, b) in bytes.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            uuid.push('-');
+        }
+        uuid.push_str(&format!("{b:02x}"));
+    }
+    uuid
+}
+
+/// Scrambles the bits of `x` (the finalizer of the SplitMix64 generator): close inputs produce very different outputs.
+fn mix(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_seeded() {
+        let r = Randomness::new(Some(42));
+
+        // Same stream and index, same sequence
+        let a: Vec<u64> =
+            r.rng(RandomStream::RecordKey, 7).sample_iter(rand::distributions::Standard).take(5).collect();
+        let b: Vec<u64> =
+            r.rng(RandomStream::RecordKey, 7).sample_iter(rand::distributions::Standard).take(5).collect();
+        assert_eq!(a, b);
+
+        // Forks are seeded, but differently
+        assert_eq!(r.fork(1), Randomness::new(Some(42)).fork(1));
+        assert_ne!(r.fork(1), r.fork(2));
+        assert_ne!(r, r.fork(1));
+
+        // Different index, stream or seed, different sequence
+        assert_ne!(r.rng(RandomStream::RecordKey, 7).gen::<u64>(), r.rng(RandomStream::RecordKey, 8).gen::<u64>());
+        assert_ne!(r.rng(RandomStream::RecordKey, 7).gen::<u64>(), r.rng(RandomStream::RecordPayload, 7).gen::<u64>());
+        assert_ne!(
+            r.rng(RandomStream::RecordKey, 7).gen::<u64>(),
+            Randomness::new(Some(43)).rng(RandomStream::RecordKey, 7).gen::<u64>()
+        );
+    }
+
+    #[test]
+    fn test_unseeded() {
+        let r = Randomness::default();
+        assert_ne!(r.rng(RandomStream::RecordKey, 0).gen::<u64>(), r.rng(RandomStream::RecordKey, 0).gen::<u64>());
+
+        // Once seeded, same index, same sequence
+        let s = r.to_seeded();
+        assert_eq!(s.rng(RandomStream::Bursts, 0).gen::<u64>(), s.rng(RandomStream::Bursts, 0).gen::<u64>());
+        assert_eq!(Randomness::new(Some(42)), Randomness::new(Some(42)).to_seeded());
+        assert_eq!(Randomness::default(), r.fork(1));
+    }
+}
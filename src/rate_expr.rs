@@ -0,0 +1,664 @@
+use std::f64::consts::{E, PI};
+use std::fmt;
+
+use serde::{de, Deserialize, Deserializer};
+
+use crate::workload::{RateSchedule, WorkloadPhase};
+
+/// Records/sec described by a math expression of the time `t`, in seconds since the beginning of the cycle.
+///
+/// The expression is compiled once, when parsed, and then evaluated for every second: it can only do math,
+/// so it's safe to accept it from the user. It supports:
+///
+/// * numbers (ex. `500`, `0.5`, `1e3`), the variable `t` and the constants `pi` and `e`
+/// * arithmetic: `+`, `-`, `*`, `/`, `%` (remainder) and `^` (power)
+/// * comparisons: `<`, `<=`, `>`, `>=`, `==` and `!=`, that are `1` when true and `0` when false
+/// * logic: `&&`, `||` and `!`, where any value other than `0` is true
+/// * the functions in [`Function`], ex. `sin(t)`, `min(a, b)`, `clamp(x, lo, hi)` or `if(cond, a, b)`
+///
+/// For example, `500 + 400*sin(2*pi*t/300) * (t > 600)` is constant at `500` records/sec for 10 minutes,
+/// then oscillates between `100` and `900` every 5 minutes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateExpr {
+    /// The expression, as provided by the user
+    source: String,
+
+    /// The expression, compiled
+    root: Expr,
+}
+
+/// A compiled [`RateExpr`], evaluated recursively.
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    Time,
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    And,
+    Or,
+}
+
+/// The functions that can be called in a [`RateExpr`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sqrt,
+    Exp,
+    Ln,
+    Log10,
+    Abs,
+    Floor,
+    Ceil,
+    Round,
+    /// Smallest of its (1 or more) arguments
+    Min,
+    /// Largest of its (1 or more) arguments
+    Max,
+    /// `clamp(x, lo, hi)`: `x` within `[lo..hi]`
+    Clamp,
+    /// `if(cond, a, b)`: `a` if `cond` is not `0`, `b` otherwise
+    If,
+}
+
+/// Names of the [`Function`]s, and how many arguments they take (`None` for 1 or more).
+const FUNCTIONS: [(&str, Function, Option<usize>); 18] = [
+    ("sin", Function::Sin, Some(1)),
+    ("cos", Function::Cos, Some(1)),
+    ("tan", Function::Tan, Some(1)),
+    ("asin", Function::Asin, Some(1)),
+    ("acos", Function::Acos, Some(1)),
+    ("atan", Function::Atan, Some(1)),
+    ("sqrt", Function::Sqrt, Some(1)),
+    ("exp", Function::Exp, Some(1)),
+    ("ln", Function::Ln, Some(1)),
+    ("log10", Function::Log10, Some(1)),
+    ("abs", Function::Abs, Some(1)),
+    ("floor", Function::Floor, Some(1)),
+    ("ceil", Function::Ceil, Some(1)),
+    ("round", Function::Round, Some(1)),
+    ("min", Function::Min, None),
+    ("max", Function::Max, None),
+    ("clamp", Function::Clamp, Some(3)),
+    ("if", Function::If, Some(3)),
+];
+
+/// How long an expression can be: it bounds the recursion of evaluating long chains of operators (ex. `1+1+...`).
+const MAX_LEN: usize = 1_000;
+
+/// How deeply sub-expressions can be nested: it bounds the recursion of parsing.
+const MAX_DEPTH: usize = 64;
+
+/// Longest period of a [`RateExprSchedule`]: all its seconds are evaluated at start-up.
+pub const MAX_PERIOD_SEC: u32 = 366 * 24 * 60 * 60;
+
+impl RateExpr {
+    /// Implementation of [`clap::value_parser`], used to create an argument by parsing a user-provided value.
+    ///
+    /// The `&str` provided is compiled to a [`RateExpr`]: see its documentation for the supported syntax.
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn clap_parser(expr_as_str: &str) -> Result<RateExpr, String> {
+        if expr_as_str.len() > MAX_LEN {
+            return Err(format!("RATE_EXPR must be at most {MAX_LEN} characters long"));
+        }
+
+        let mut parser = Parser {
+            src: expr_as_str.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+
+        let root = parser.parse_expr().and_then(|root| match parser.peek() {
+            None => Ok(root),
+            Some(_) => Err(parser.error("Unexpected input")),
+        })?;
+
+        Ok(RateExpr {
+            source: expr_as_str.to_string(),
+            root,
+        })
+    }
+
+    /// Evaluates the expression at `t`: the result can be any `f64`, including negative or not finite.
+    pub fn eval(&self, t: f64) -> f64 {
+        self.root.eval(t)
+    }
+}
+
+impl fmt::Display for RateExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// A [`RateExpr`] is deserialized from the same format parsed by [`RateExpr::clap_parser`].
+impl<'de> Deserialize<'de> for RateExpr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let expr_as_str = String::deserialize(deserializer)?;
+
+        RateExpr::clap_parser(&expr_as_str).map_err(de::Error::custom)
+    }
+}
+
+impl Expr {
+    fn eval(&self, t: f64) -> f64 {
+        let bool_to_f64 = |b: bool| {
+            if b {
+                1.0
+            } else {
+                0.0
+            }
+        };
+
+        match self {
+            Expr::Number(n) => *n,
+            Expr::Time => t,
+            Expr::Neg(e) => -e.eval(t),
+            Expr::Not(e) => bool_to_f64(e.eval(t) == 0.0),
+            Expr::Binary(op, l, r) => {
+                let l = l.eval(t);
+
+                // Logic operators short-circuit
+                match op {
+                    BinaryOp::And if l == 0.0 => return 0.0,
+                    BinaryOp::Or if l != 0.0 => return 1.0,
+                    _ => {},
+                }
+
+                let r = r.eval(t);
+                match op {
+                    BinaryOp::Add => l + r,
+                    BinaryOp::Sub => l - r,
+                    BinaryOp::Mul => l * r,
+                    BinaryOp::Div => l / r,
+                    BinaryOp::Rem => l % r,
+                    BinaryOp::Pow => l.powf(r),
+                    BinaryOp::Lt => bool_to_f64(l < r),
+                    BinaryOp::Le => bool_to_f64(l <= r),
+                    BinaryOp::Gt => bool_to_f64(l > r),
+                    BinaryOp::Ge => bool_to_f64(l >= r),
+                    BinaryOp::Eq => bool_to_f64(l == r),
+                    BinaryOp::Ne => bool_to_f64(l != r),
+                    BinaryOp::And | BinaryOp::Or => bool_to_f64(r != 0.0),
+                }
+            },
+            Expr::Call(Function::If, args) => {
+                if args[0].eval(t) != 0.0 {
+                    args[1].eval(t)
+                } else {
+                    args[2].eval(t)
+                }
+            },
+            Expr::Call(f, args) => {
+                let mut args = args.iter().map(|a| a.eval(t));
+                // Valid, as the amount of arguments is checked when parsing
+                let x = args.next().unwrap();
+
+                match f {
+                    Function::Sin => x.sin(),
+                    Function::Cos => x.cos(),
+                    Function::Tan => x.tan(),
+                    Function::Asin => x.asin(),
+                    Function::Acos => x.acos(),
+                    Function::Atan => x.atan(),
+                    Function::Sqrt => x.sqrt(),
+                    Function::Exp => x.exp(),
+                    Function::Ln => x.ln(),
+                    Function::Log10 => x.log10(),
+                    Function::Abs => x.abs(),
+                    Function::Floor => x.floor(),
+                    Function::Ceil => x.ceil(),
+                    Function::Round => x.round(),
+                    Function::Min => args.fold(x, f64::min),
+                    Function::Max => args.fold(x, f64::max),
+                    Function::Clamp => {
+                        let (lo, hi) = (args.next().unwrap(), args.next().unwrap());
+                        x.max(lo).min(hi)
+                    },
+                    Function::If => unreachable!(),
+                }
+            },
+        }
+    }
+}
+
+/// Recursive descent parser of a [`RateExpr`], from the lowest to the highest precedence:
+///
+/// * `||`
+/// * `&&`
+/// * `<`, `<=`, `>`, `>=`, `==` and `!=`
+/// * `+` and `-`
+/// * `*`, `/` and `%`
+/// * unary `-`, `+` and `!`
+/// * `^` (right associative, so `-2^2` is `-4` and `2^3^2` is `512`)
+/// * numbers, `t`, constants, function calls and parenthesis
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> String {
+        format!("Failed to parse RATE_EXPR at position {}: {msg}", self.pos + 1)
+    }
+
+    fn skip_whitespaces(&mut self) {
+        while self.src.get(self.pos).is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    /// Next character that isn't a whitespace, without consuming it.
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespaces();
+        self.src.get(self.pos).copied()
+    }
+
+    /// Consumes `token` if it's next: `token` can't be a prefix of another operator that is also allowed here.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespaces();
+        if self.src[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("Too deeply nested expression"));
+        }
+        let expr = self.parse_or();
+        self.depth -= 1;
+        expr
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, String> {
+        let mut l = self.parse_and()?;
+        while self.eat("||") {
+            l = Expr::Binary(BinaryOp::Or, Box::new(l), Box::new(self.parse_and()?));
+        }
+        Ok(l)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, String> {
+        let mut l = self.parse_comparison()?;
+        while self.eat("&&") {
+            l = Expr::Binary(BinaryOp::And, Box::new(l), Box::new(self.parse_comparison()?));
+        }
+        Ok(l)
+    }
+
+    /// Comparisons don't chain: `a < b < c` is an error.
+    fn parse_comparison(&mut self) -> Result<Expr, String> {
+        let l = self.parse_sum()?;
+
+        // Longer operators first, as `<` is a prefix of `<=`
+        let ops = [
+            ("<=", BinaryOp::Le),
+            (">=", BinaryOp::Ge),
+            ("==", BinaryOp::Eq),
+            ("!=", BinaryOp::Ne),
+            ("<", BinaryOp::Lt),
+            (">", BinaryOp::Gt),
+        ];
+        match ops.iter().find(|(token, _)| self.eat(token)) {
+            None => Ok(l),
+            Some((_, op)) => Ok(Expr::Binary(*op, Box::new(l), Box::new(self.parse_sum()?))),
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Expr, String> {
+        let mut l = self.parse_product()?;
+        loop {
+            let op = match self.peek() {
+                Some(b'+') => BinaryOp::Add,
+                Some(b'-') => BinaryOp::Sub,
+                _ => return Ok(l),
+            };
+            self.pos += 1;
+            l = Expr::Binary(op, Box::new(l), Box::new(self.parse_product()?));
+        }
+    }
+
+    fn parse_product(&mut self) -> Result<Expr, String> {
+        let mut l = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(b'*') => BinaryOp::Mul,
+                Some(b'/') => BinaryOp::Div,
+                Some(b'%') => BinaryOp::Rem,
+                _ => return Ok(l),
+            };
+            self.pos += 1;
+            l = Expr::Binary(op, Box::new(l), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            Some(b'-') => {
+                self.pos += 1;
+                Ok(Expr::Neg(Box::new(self.parse_nested(Parser::parse_unary)?)))
+            },
+            Some(b'+') => {
+                self.pos += 1;
+                self.parse_nested(Parser::parse_unary)
+            },
+            // Not to be confused with `!=`, that can't begin an operand
+            Some(b'!') => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.parse_nested(Parser::parse_unary)?)))
+            },
+            _ => self.parse_power(),
+        }
+    }
+
+    fn parse_power(&mut self) -> Result<Expr, String> {
+        let base = self.parse_primary()?;
+        if self.eat("^") {
+            // Right associative, and the exponent can be negative (ex. `2^-1`)
+            let exp = self.parse_nested(Parser::parse_unary)?;
+            return Ok(Expr::Binary(BinaryOp::Pow, Box::new(base), Box::new(exp)));
+        }
+        Ok(base)
+    }
+
+    /// Parses with `parse`, counting towards the [`MAX_DEPTH`] of the expression.
+    fn parse_nested(&mut self, parse: fn(&mut Self) -> Result<Expr, String>) -> Result<Expr, String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(self.error("Too deeply nested expression"));
+        }
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.peek() {
+            None => Err(self.error("Unexpected end")),
+            Some(b'(') => {
+                self.pos += 1;
+                let expr = self.parse_expr()?;
+                if !self.eat(")") {
+                    return Err(self.error("Expected ')'"));
+                }
+                Ok(expr)
+            },
+            Some(c) if c.is_ascii_digit() || c == b'.' => self.parse_number(),
+            Some(c) if c.is_ascii_alphabetic() || c == b'_' => self.parse_identifier(),
+            Some(_) => Err(self.error("Unexpected input")),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Expr, String> {
+        let start = self.pos;
+        while self.src.get(self.pos).is_some_and(|c| c.is_ascii_digit() || *c == b'.') {
+            self.pos += 1;
+        }
+        // Exponent (ex. `1e3` or `2.5E-2`): only if followed by digits, otherwise `e` is something else
+        if let Some(b'e' | b'E') = self.src.get(self.pos) {
+            let sign = matches!(self.src.get(self.pos + 1), Some(b'+' | b'-')) as usize;
+            if self.src.get(self.pos + 1 + sign).is_some_and(|c| c.is_ascii_digit()) {
+                self.pos += 1 + sign;
+                while self.src.get(self.pos).is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos += 1;
+                }
+            }
+        }
+
+        // Valid, as it's made of ASCII characters only
+        let number = std::str::from_utf8(&self.src[start..self.pos]).unwrap();
+        number.parse::<f64>().map(Expr::Number).map_err(|_| {
+            self.pos = start;
+            self.error(&format!("Invalid number '{number}'"))
+        })
+    }
+
+    fn parse_identifier(&mut self) -> Result<Expr, String> {
+        let start = self.pos;
+        while self.src.get(self.pos).is_some_and(|c| c.is_ascii_alphanumeric() || *c == b'_') {
+            self.pos += 1;
+        }
+        // Valid, as it's made of ASCII characters only
+        let name = std::str::from_utf8(&self.src[start..self.pos]).unwrap();
+
+        match name {
+            "t" => return Ok(Expr::Time),
+            "pi" => return Ok(Expr::Number(PI)),
+            "e" => return Ok(Expr::Number(E)),
+            _ => {},
+        }
+
+        let Some((_, function, arity)) = FUNCTIONS.iter().find(|(n, _, _)| *n == name) else {
+            self.pos = start;
+            return Err(self.error(&format!("Unknown identifier '{name}'")));
+        };
+        if !self.eat("(") {
+            return Err(self.error(&format!("Expected '(' after function '{name}'")));
+        }
+
+        let mut args = vec![self.parse_expr()?];
+        while self.eat(",") {
+            args.push(self.parse_expr()?);
+        }
+        if !self.eat(")") {
+            return Err(self.error("Expected ')'"));
+        }
+
+        match arity {
+            Some(arity) if args.len() != *arity => {
+                self.pos = start;
+                Err(self.error(&format!("Function '{name}' takes {arity} argument(s), not {}", args.len())))
+            },
+            _ => Ok(Expr::Call(*function, args)),
+        }
+    }
+}
+
+/// A [`RateSchedule`] following a [`RateExpr`]: `t` goes from `0` to `period_sec - 1`, and then starts again.
+///
+/// Where the expression is negative or not a number (ex. `sqrt(-1)`), the records/sec are `0`.
+#[derive(Debug)]
+pub struct RateExprSchedule {
+    expr: RateExpr,
+    period_sec: u32,
+
+    /// Lowest and highest records/sec of the period, found by evaluating all its seconds
+    min: f64,
+    max: f64,
+}
+
+impl RateExprSchedule {
+    pub fn new(expr: RateExpr, period_sec: u32) -> RateExprSchedule {
+        assert!(period_sec > 0, "Rate expression requires a period greater than 0 seconds");
+
+        let mut schedule = RateExprSchedule {
+            expr,
+            period_sec,
+            min: f64::INFINITY,
+            max: 0.0,
+        };
+        for sec in 0..period_sec as u64 {
+            let rate = schedule.records_per_sec_at(sec);
+            schedule.min = schedule.min.min(rate);
+            schedule.max = schedule.max.max(rate);
+        }
+
+        schedule
+    }
+}
+
+impl RateSchedule for RateExprSchedule {
+    fn records_per_sec_at(&self, sec: u64) -> f64 {
+        let rate = self.expr.eval((sec % self.period_sec as u64) as f64);
+
+        // Infinite and NaN rates can't be produced: they are treated like negative ones
+        if rate.is_finite() && rate > 0.0 {
+            rate
+        } else {
+            0.0
+        }
+    }
+
+    /// At the highest and lowest records/sec, it's [`WorkloadPhase::Max`] and [`WorkloadPhase::Min`]:
+    /// otherwise, it depends on where the expression is headed.
+    fn phase_at(&self, sec: u64) -> WorkloadPhase {
+        let curr = self.records_per_sec_at(sec);
+        let next = self.records_per_sec_at(sec + 1);
+
+        if curr == self.max {
+            WorkloadPhase::Max
+        } else if curr == self.min {
+            WorkloadPhase::Min
+        } else if next > curr {
+            WorkloadPhase::Up
+        } else if next < curr {
+            WorkloadPhase::Down
+        } else {
+            WorkloadPhase::Hold
+        }
+    }
+
+    fn max_records_per_sec(&self) -> f64 {
+        self.max
+    }
+
+    fn overall_duration_sec(&self) -> u32 {
+        self.period_sec
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(expr: &str, t: f64) -> f64 {
+        RateExpr::clap_parser(expr).unwrap().eval(t)
+    }
+
+    #[test]
+    fn test_eval() {
+        // Precedence and associativity
+        assert_eq!(7.0, eval("1 + 2 * 3", 0.0));
+        assert_eq!(9.0, eval("(1 + 2) * 3", 0.0));
+        assert_eq!(2.0, eval("8 / 2 / 2", 0.0));
+        assert_eq!(-4.0, eval("-2^2", 0.0));
+        assert_eq!(512.0, eval("2^3^2", 0.0));
+        assert_eq!(0.5, eval("2^-1", 0.0));
+        assert_eq!(1.0, eval("10 % 3", 0.0));
+        assert_eq!(1500.0, eval("1.5e3", 0.0));
+        assert_eq!(2.0 * E, eval("2 * e", 0.0));
+        assert_eq!(0.025, eval("2.5E-2", 0.0));
+
+        // Time, comparisons and logic
+        assert_eq!(600.0, eval("t * 2", 300.0));
+        assert_eq!(1.0, eval("t > 600", 601.0));
+        assert_eq!(0.0, eval("t > 600", 600.0));
+        assert_eq!(1.0, eval("t >= 10 && t < 20 || t == 0", 0.0));
+        assert_eq!(0.0, eval("!(t != 5)", 4.0));
+
+        // Functions
+        assert!((eval("sin(pi / 2)", 0.0) - 1.0).abs() < 1e-12);
+        assert_eq!(2.0, eval("min(5, t, 3)", 2.0));
+        assert_eq!(5.0, eval("max(5, t)", 2.0));
+        assert_eq!(10.0, eval("clamp(t, 0, 10)", 50.0));
+        assert_eq!(100.0, eval("if(t < 60, 100, 200)", 30.0));
+        assert_eq!(200.0, eval("if(t < 60, 100, 200)", 90.0));
+
+        // The example from the documentation
+        let expr = "500 + 400*sin(2*pi*t/300) * (t > 600)";
+        assert_eq!(500.0, eval(expr, 75.0));
+        assert!((eval(expr, 675.0) - 900.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_clap_parser() {
+        assert_eq!("t * 2", RateExpr::clap_parser("t * 2").unwrap().to_string());
+
+        assert_eq!(
+            Err("Failed to parse RATE_EXPR at position 4: Unexpected end".to_string()),
+            RateExpr::clap_parser("1 +")
+        );
+        assert_eq!(
+            Err("Failed to parse RATE_EXPR at position 3: Unexpected input".to_string()),
+            RateExpr::clap_parser("1 2")
+        );
+        assert_eq!(
+            Err("Failed to parse RATE_EXPR at position 5: Unknown identifier 'x'".to_string()),
+            RateExpr::clap_parser("2 * x")
+        );
+        assert_eq!(
+            Err("Failed to parse RATE_EXPR at position 1: Function 'clamp' takes 3 argument(s), not 2".to_string()),
+            RateExpr::clap_parser("clamp(t, 1)")
+        );
+        assert!(RateExpr::clap_parser("2e").is_err());
+        assert!(RateExpr::clap_parser("(1 + 2").is_err());
+        assert!(RateExpr::clap_parser("sin t").is_err());
+        assert!(RateExpr::clap_parser("1 < 2 < 3").is_err());
+        assert!(RateExpr::clap_parser("").is_err());
+
+        // Nesting and length are bounded
+        assert!(RateExpr::clap_parser(&format!("{}1{}", "(".repeat(32), ")".repeat(32))).is_ok());
+        assert!(RateExpr::clap_parser(&format!("{}1{}", "(".repeat(400), ")".repeat(400))).is_err());
+        assert!(RateExpr::clap_parser(&format!("{}1", "-".repeat(400))).is_err());
+        assert_eq!(
+            Err("RATE_EXPR must be at most 1000 characters long".to_string()),
+            RateExpr::clap_parser(&"1+".repeat(501))
+        );
+    }
+
+    #[test]
+    fn test_schedule() {
+        let expr = RateExpr::clap_parser("if(t < 2, 10, 100 - 10 * t)").unwrap();
+        let schedule = RateExprSchedule::new(expr, 12);
+
+        // Never negative, and repeating every period
+        let rates = (0..14).map(|sec| schedule.records_per_sec_at(sec)).collect::<Vec<_>>();
+        assert_eq!(vec![10.0, 10.0, 80.0, 70.0, 60.0, 50.0, 40.0, 30.0, 20.0, 10.0, 0.0, 0.0, 10.0, 10.0], rates);
+        assert_eq!(80.0, schedule.max_records_per_sec());
+        assert_eq!(12, schedule.overall_duration_sec());
+        assert_eq!(WorkloadPhase::Max, schedule.phase_at(2));
+        assert_eq!(WorkloadPhase::Down, schedule.phase_at(3));
+        assert_eq!(WorkloadPhase::Min, schedule.phase_at(10));
+
+        // Not a number
+        let schedule = RateExprSchedule::new(RateExpr::clap_parser("sqrt(-1)").unwrap(), 2);
+        assert_eq!(0.0, schedule.records_per_sec_at(0));
+
+        // Infinite, only at second 0
+        let schedule = RateExprSchedule::new(RateExpr::clap_parser("10 / t").unwrap(), 4);
+        assert_eq!(vec![0.0, 10.0, 5.0], (0..3).map(|sec| schedule.records_per_sec_at(sec)).collect::<Vec<_>>());
+        assert_eq!(10.0, schedule.max_records_per_sec());
+        let schedule = RateExprSchedule::new(RateExpr::clap_parser("exp(1000)").unwrap(), 2);
+        assert_eq!(0.0, schedule.records_per_sec_at(1));
+        assert_eq!(0.0, schedule.max_records_per_sec());
+    }
+}
@@ -1,11 +1,13 @@
 use clap::ValueEnum;
+use serde::Deserialize;
 
 /// The possible `partitioner` configuration value that the [`librdkafka`](https://github.com/edenhill/librdkafka) library can handle.
 ///
 /// The documentation is lifted directly from the `librdkafka` configuration
 /// [page](https://github.com/edenhill/librdkafka/blob/master/CONFIGURATION.md).
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Default, Deserialize)]
 #[value(rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
 pub enum PartitionerConfig {
     /// Random distribution.
     Random,
@@ -1,40 +1,206 @@
+use std::fmt;
+use std::sync::Arc;
 use std::time::Duration;
 
+use clap::ValueEnum;
 use log::Level::Warn;
+use serde::{de, Deserialize, Deserializer};
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tokio::time;
 
-use crate::{GeneratedRecord, RecordGenerator, Workload};
+use crate::ramp::Ramp;
+use crate::{GeneratedRecord, RateSchedule, RecordGenerator, WorkloadPhase};
 
-/// Using a [`Workload`] and a [`RecordGenerator`], it generates records and sends them via a given channel.
+/// Upper limit to the amount of slices a second is divided in, when using [`Pacing::Even`].
+///
+/// The resolution of the [`tokio::time`] timers is 1 millisecond: slicing further would be pointless.
+const MAX_SLICES_PER_SEC: u32 = 1000;
+
+/// Tolerance when taking whole units out of the accumulated fractions (see [`take_whole_units`]).
+///
+/// Fractions don't add up exactly as `f64` (ex. `0.1` ten times is less than `1.0`): without it, units would be lost.
+const UNITS_CARRY_EPSILON: f64 = 1e-9;
+
+/// Describes how the records of each second are spread across the second itself.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum Pacing {
+    /// All the records of the second are sent as fast as possible, at the beginning of the second.
+    #[default]
+    Burst,
+
+    /// Records are spaced evenly across the second (with a resolution of 1 millisecond).
+    Even,
+
+    /// The second is divided in N slices: the records are split equally across them,
+    /// and each slice sends its share as fast as possible, at the beginning of the slice.
+    Slices(u32),
+}
+
+impl Pacing {
+    /// Implementation of [`clap::value_parser`], used to create an argument by parsing a user-provided value.
+    ///
+    /// The `&str` provided must be one of `burst`, `even` or `slices:N`, where `N` is parsed to a
+    /// `u32` greater than `0`.
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn clap_parser(pacing_as_str: &str) -> Result<Pacing, String> {
+        match pacing_as_str.split_once(':') {
+            None if pacing_as_str == "burst" => Ok(Pacing::Burst),
+            None if pacing_as_str == "even" => Ok(Pacing::Even),
+            Some(("slices", n)) => match n.parse::<u32>() {
+                Err(e) => Err(format!("Failed to parse INPUT 'N' from 'slices:N': {e}")),
+                Ok(0) => Err("INPUT 'N' of 'slices:N' must be greater than 0".to_string()),
+                Ok(n) => Ok(Pacing::Slices(n)),
+            },
+            _ => Err(format!("Unsupported PACING '{pacing_as_str}'")),
+        }
+    }
+
+    /// How many slices a second is divided in, given the amount of `records` to send in that second.
+    fn slices_per_sec(&self, records: u64) -> u32 {
+        match self {
+            Pacing::Burst => 1,
+            Pacing::Even => records.clamp(1, MAX_SLICES_PER_SEC as u64) as u32,
+            Pacing::Slices(n) => *n,
+        }
+    }
+}
+
+impl fmt::Display for Pacing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pacing::Burst => write!(f, "burst"),
+            Pacing::Even => write!(f, "even"),
+            Pacing::Slices(n) => write!(f, "slices:{n}"),
+        }
+    }
+}
+
+/// A [`Pacing`] is deserialized from the same format parsed by [`Pacing::clap_parser`].
+impl<'de> Deserialize<'de> for Pacing {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pacing_as_str = String::deserialize(deserializer)?;
+
+        Pacing::clap_parser(&pacing_as_str).map_err(de::Error::custom)
+    }
+}
+
+/// Unit of the amounts described by the [`RateSchedule`] a [`RecordsTap`] follows.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThroughputUnit {
+    /// The workload describes records/sec.
+    #[default]
+    Records,
+
+    /// The workload describes bytes/sec: how many records that is depends on the size of each generated record.
+    Bytes,
+}
+
+impl ThroughputUnit {
+    /// Returns the "name" for the enum value, as used in arguments and logs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ThroughputUnit::Records => "records",
+            ThroughputUnit::Bytes => "bytes",
+        }
+    }
+
+    /// How many units the given `record` amounts to.
+    fn units_of(&self, record: &GeneratedRecord) -> u64 {
+        match self {
+            ThroughputUnit::Records => 1,
+            // Even an empty record has a cost: otherwise, there would be no limit to how many to send
+            ThroughputUnit::Bytes => (record.size_bytes() as u64).max(1),
+        }
+    }
+}
+
+impl fmt::Display for ThroughputUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Limits to the run of a [`RecordsTap`]: once any of them is reached, it stops producing records.
+///
+/// With no limit set, the [`RecordsTap`] produces records until a shutdown is requested.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct RunLimits {
+    duration_sec: Option<u64>,
+    total_records: Option<u64>,
+}
+
+impl RunLimits {
+    pub fn new(duration_sec: Option<u64>, total_records: Option<u64>) -> RunLimits {
+        RunLimits {
+            duration_sec,
+            total_records,
+        }
+    }
+
+    /// Whether the limit to the total amount of records is reached, after `records_sent` records.
+    fn is_total_records_reached(&self, records_sent: u64) -> bool {
+        self.total_records.is_some_and(|t| records_sent >= t)
+    }
+
+    /// Whether any limit is reached, after `sec` seconds and `records_sent` records.
+    fn is_reached(&self, sec: u64, records_sent: u64) -> bool {
+        self.duration_sec.is_some_and(|d| sec >= d) || self.is_total_records_reached(records_sent)
+    }
+}
+
+/// Using a [`RateSchedule`] and a [`RecordGenerator`], it generates records and sends them via a given channel.
 ///
 /// This receives the [`mpsc::Sender`] part of the channel: the [`mpsc::Receiver`] part is
 /// assigned to a [`ProducerSink`].
 pub struct RecordsTap {
-    workload: Workload,
+    workload: Arc<dyn RateSchedule>,
     generator: RecordGenerator,
+    pacing: Pacing,
+    unit: ThroughputUnit,
+    limits: RunLimits,
+    cool_down: Option<Ramp>,
 }
 
 impl RecordsTap {
-    pub fn new(workload: Workload, generator: RecordGenerator) -> RecordsTap {
+    pub fn new(
+        workload: Arc<dyn RateSchedule>,
+        generator: RecordGenerator,
+        pacing: Pacing,
+        unit: ThroughputUnit,
+        limits: RunLimits,
+        cool_down: Option<Ramp>,
+    ) -> RecordsTap {
         RecordsTap {
             workload,
             generator,
+            pacing,
+            unit,
+            limits,
+            cool_down,
         }
     }
 
     /// Instantiates a record-producing loop as async [`tokio::task`].
     ///
-    /// Once per second, it queries the internal [`Workload`] for how many records are supposed
-    /// to be produced in that instant, and then invokes the internal [`RecordGenerator`] an equal
-    /// amount of time. Each record is then sent to the "sink" via the given `records_tx` side of
-    /// a channel.
+    /// Once per second, it queries the internal [`RateSchedule`] for how many records (or bytes, depending
+    /// on the [`ThroughputUnit`]) are supposed to be produced in that instant, and then invokes the
+    /// internal [`RecordGenerator`] until that amount is reached. Each record is then sent to the "sink"
+    /// via the given `records_tx` side of a channel. How the records are spread across the second
+    /// depends on the [`Pacing`].
     ///
     /// Additionally, when a `()` is received over the `shutdown_rx` [`broadcast::Receiver`], it
     /// initiates a shutdown: stops producing records and causes the the `records_tx` to be dropped.
     /// This in turn causes the receiver to stop expecting records and shutdown as well.
+    ///
+    /// The same shutdown is initiated once any of the [`RunLimits`] is reached.
+    ///
+    /// With a cool-down [`Ramp`], the shutdown is preceded by the cool-down: the records/sec go from where
+    /// they are to `0`, and then the shutdown happens. A further `()` received during the cool-down
+    /// initiates the shutdown straight away.
     pub fn spawn(
         &mut self,
         records_tx: mpsc::Sender<GeneratedRecord>,
@@ -42,65 +208,173 @@ impl RecordsTap {
     ) -> JoinHandle<u64> {
         let workload = self.workload.clone();
         let generator = self.generator.clone();
+        let pacing = self.pacing;
+        let unit = self.unit;
+        let limits = self.limits;
+        let cool_down = self.cool_down;
 
         tokio::spawn(async move {
             // Seconds since we started producing
             let mut sec = 0u64;
 
+            // Records generated since we started producing: each is generated from its index
+            let mut records_generated = 0u64;
+
+            // Records, and units they amount to, sent to the sink since we started producing
+            let mut records_sent = 0u64;
+            let mut units_sent = 0u64;
+
+            // Units not yet produced: fractions of units accumulate across seconds, and when
+            // the records of a slice exceed its units, the excess is deducted from the following slices
+            let mut units_carry = 0f64;
+
             // This is used to set the pace of the records production
             let mut interval = time::interval(time::Duration::from_secs(1));
 
+            // Once cooling down, the second it started at, and the records/sec it started from
+            let mut cooling_down: Option<(u64, f64)> = None;
+
             let mut shutdown_requested = false;
             while !shutdown_requested {
-                // Figure out how many records we need to produce in this second
-                let records_at = workload.records_per_sec_at(sec);
-                info!("{sec} sec: sending {records_at} recs...");
-
-                for _ in 0..records_at {
-                    if log_enabled!(Warn) {
-                        // Warn if we have less then 20% capacity on the internal records channel
-                        let cap = records_tx.capacity() as f64;
-                        let max_cap = records_tx.max_capacity() as f64;
-                        let remaining_cap_perc = cap / max_cap;
-                        if remaining_cap_perc < 0.2 {
-                            warn!(
-                                "Remaining capacity of (internal) Records Channel: {:.2}% ({}/{})",
-                                remaining_cap_perc * 100f64,
-                                cap,
-                                max_cap
-                            );
+                // Figure out how many units we need to produce in this second
+                let (rate, phase) = match (cooling_down, cool_down) {
+                    (Some((start_sec, start_rate)), Some(ramp)) => {
+                        (ramp.records_per_sec_at(start_rate, 0.0, sec - start_sec), WorkloadPhase::Down)
+                    },
+                    _ => (workload.records_per_sec_at(sec), workload.phase_at(sec)),
+                };
+                let units_at = take_whole_units(&mut units_carry, rate);
+                info!("{sec} sec ({phase:?}): sending {units_at} {unit}...");
+
+                // Figure out how to spread those units across this second, estimating how many records they are
+                let records_estimate = match units_sent {
+                    0 => units_at,
+                    _ => units_at * records_sent / units_sent,
+                };
+                let sec_start = time::Instant::now();
+                let sec_records_sent = records_sent;
+                let slices = pacing.slices_per_sec(records_estimate);
+                let slice_duration = Duration::from_secs(1) / slices;
+
+                for slice in 0..slices {
+                    if shutdown_requested {
+                        break;
+                    }
+
+                    // Await the beginning of the slice: the first begins immediately
+                    if slice > 0 {
+                        tokio::select! {
+                            _ = time::sleep_until(sec_start + slice_duration * slice) => {},
+
+                            _ = shutdown_rx.recv() => {
+                                info!("Received shutdown signal");
+                                shutdown_requested = true;
+                                break;
+                            },
                         }
                     }
 
-                    match generator.generate_record() {
-                        Ok(gen_rec) => {
-                            tokio::select! {
-                                // Send record to the sink (producer)
-                                send_res = records_tx.send_timeout(gen_rec, Duration::from_millis(10)) => {
-                                    if let Err(e) = send_res {
-                                        error!("Failed to send record to producer: {e}");
-                                    }
-                                },
-
-                                // Initiate shutdown: by letting this task conclude,
-                                // the "tap" `records_tx` will close, causing the "sink" `records_rx`
-                                // to return `None` and conclude its own task.
-                                _ = shutdown_rx.recv() => {
-                                    info!("Received shutdown signal");
-                                    shutdown_requested = true;
-                                },
+                    // Units are split equally across slices: the remainder goes to the latter slices
+                    let slice_units =
+                        (units_at * (slice + 1) as u64 / slices as u64) - (units_at * slice as u64 / slices as u64);
+
+                    let mut slice_units_sent = 0u64;
+                    // Once cooling down, the records of the cool-down go beyond the limits
+                    while slice_units_sent < slice_units
+                        && !shutdown_requested
+                        && (cooling_down.is_some() || !limits.is_total_records_reached(records_sent))
+                    {
+                        if log_enabled!(Warn) {
+                            // Warn if we have less then 20% capacity on the internal records channel
+                            let cap = records_tx.capacity() as f64;
+                            let max_cap = records_tx.max_capacity() as f64;
+                            let remaining_cap_perc = cap / max_cap;
+                            if remaining_cap_perc < 0.2 {
+                                warn!(
+                                    "Remaining capacity of (internal) Records Channel: {:.2}% ({}/{})",
+                                    remaining_cap_perc * 100f64,
+                                    cap,
+                                    max_cap
+                                );
                             }
-                        },
-                        Err(e) => error!("Failed to generate record: {e}"),
+                        }
+
+                        let gen_res = generator.generate_record(records_generated);
+                        records_generated += 1;
+
+                        match gen_res {
+                            Ok(gen_rec) => {
+                                let rec_units = unit.units_of(&gen_rec);
+                                slice_units_sent += rec_units;
+
+                                tokio::select! {
+                                    // Send record to the sink (producer)
+                                    send_res = records_tx.send_timeout(gen_rec, Duration::from_millis(10)) => {
+                                        match send_res {
+                                            Ok(_) => {
+                                                records_sent += 1;
+                                                units_sent += rec_units;
+                                            },
+                                            Err(e) => error!("Failed to send record to producer: {e}"),
+                                        }
+                                    },
+
+                                    // Initiate shutdown: by letting this task conclude,
+                                    // the "tap" `records_tx` will close, causing the "sink" `records_rx`
+                                    // to return `None` and conclude its own task.
+                                    _ = shutdown_rx.recv() => {
+                                        info!("Received shutdown signal");
+                                        shutdown_requested = true;
+                                    },
+                                }
+                            },
+                            Err(e) => {
+                                // Failing to generate is not transient: skip the rest of the slice
+                                error!("Failed to generate record: {e}");
+                                break;
+                            },
+                        }
                     }
+
+                    // Deduct the units sent in excess from what's left to produce
+                    units_carry -= slice_units_sent.saturating_sub(slice_units) as f64;
                 }
-                info!("{sec} sec: sent {records_at} recs");
+                info!("{sec} sec: sent {} recs", records_sent - sec_records_sent);
 
                 // Await next cycle: we do the awaiting at this stage, so that we can start producing
                 // for this second as soon as possible, instead of using some of that time to produce the
-                // records.
-                interval.tick().await;
+                // records. At low rates this is most of the time spent, so it gives way to a shutdown.
+                tokio::select! {
+                    _ = interval.tick() => {},
+
+                    _ = shutdown_rx.recv() => {
+                        info!("Received shutdown signal");
+                        shutdown_requested = true;
+                    },
+                }
                 sec += 1;
+
+                // Initiate shutdown, exactly as if it was requested, once the run reached its limits
+                if !shutdown_requested && cooling_down.is_none() && limits.is_reached(sec, records_sent) {
+                    info!("Reached run limits after {sec} sec and {records_sent} recs");
+                    shutdown_requested = true;
+                }
+
+                // Cool-down before shutting down, unless already cooling down
+                if let Some(ramp) = cool_down {
+                    match cooling_down {
+                        None if shutdown_requested => {
+                            info!("Cooling down for {} sec, before shutting down", ramp.duration_sec());
+                            cooling_down = Some((sec, rate));
+                            shutdown_requested = false;
+                        },
+                        Some((start_sec, _)) if sec - start_sec >= ramp.duration_sec() as u64 => {
+                            info!("Cooled down after {} sec", ramp.duration_sec());
+                            shutdown_requested = true;
+                        },
+                        _ => {},
+                    }
+                }
             }
 
             // Return for how many seconds has this been producing records
@@ -108,3 +382,103 @@ impl RecordsTap {
         })
     }
 }
+
+/// Adds the `rate` of a second to the `units_carry` (the fractions of units not yet produced), and takes
+/// the whole units out of it: those are the units to produce in that second.
+fn take_whole_units(units_carry: &mut f64, rate: f64) -> u64 {
+    *units_carry += rate;
+    let units_whole = (*units_carry + UNITS_CARRY_EPSILON).floor().max(0.0);
+    *units_carry -= units_whole;
+
+    units_whole as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacing_clap_parser() {
+        assert_eq!(Ok(Pacing::Burst), Pacing::clap_parser("burst"));
+        assert_eq!(Ok(Pacing::Even), Pacing::clap_parser("even"));
+        assert_eq!(Ok(Pacing::Slices(10)), Pacing::clap_parser("slices:10"));
+
+        // Display produces the same format
+        for p in ["burst", "even", "slices:4"] {
+            assert_eq!(p, Pacing::clap_parser(p).unwrap().to_string());
+        }
+
+        assert_eq!(Err("Unsupported PACING 'smooth'".to_string()), Pacing::clap_parser("smooth"));
+        assert_eq!(Err("Unsupported PACING 'even:3'".to_string()), Pacing::clap_parser("even:3"));
+        assert_eq!(Err("INPUT 'N' of 'slices:N' must be greater than 0".to_string()), Pacing::clap_parser("slices:0"));
+        assert_eq!(
+            Err("Failed to parse INPUT 'N' from 'slices:N': invalid digit found in string".to_string()),
+            Pacing::clap_parser("slices:many")
+        );
+    }
+
+    #[test]
+    fn test_pacing_slices_per_sec() {
+        assert_eq!(1, Pacing::Burst.slices_per_sec(5000));
+
+        assert_eq!(1, Pacing::Even.slices_per_sec(0));
+        assert_eq!(7, Pacing::Even.slices_per_sec(7));
+        assert_eq!(MAX_SLICES_PER_SEC, Pacing::Even.slices_per_sec(5000));
+
+        assert_eq!(10, Pacing::Slices(10).slices_per_sec(3));
+        assert_eq!(10, Pacing::Slices(10).slices_per_sec(5000));
+    }
+
+    #[test]
+    fn test_throughput_unit() {
+        let rec = GeneratedRecord {
+            topic: "a_topic".to_string(),
+            key: Some(vec![0; 10]),
+            payload: Some(vec![0; 100]),
+            partition: None,
+            headers: Default::default(),
+        };
+
+        assert_eq!(1, ThroughputUnit::Records.units_of(&rec));
+        assert_eq!(110, ThroughputUnit::Bytes.units_of(&rec));
+    }
+
+    #[test]
+    fn test_take_whole_units() {
+        // 1 record every ~3 seconds
+        let mut units_carry = 0f64;
+        let units: Vec<u64> = (0..10).map(|_| take_whole_units(&mut units_carry, 0.3)).collect();
+        assert_eq!(vec![0, 0, 0, 1, 0, 0, 1, 0, 0, 1], units);
+
+        // Totals track the sum of the rates, however fractional
+        let mut units_carry = 0f64;
+        let (mut total_units, mut total_rate) = (0u64, 0f64);
+        for sec in 0..1000 {
+            let rate = [0.1, 2.7, 0.0, 13.35, 0.05][sec % 5];
+            total_units += take_whole_units(&mut units_carry, rate);
+            total_rate += rate;
+            assert!((total_rate - total_units as f64) < 1.0 && (total_rate - total_units as f64) > -1e-6);
+        }
+        assert_eq!(3240, total_units);
+
+        // Units sent in excess are deducted from the following seconds
+        let mut units_carry = -2.5;
+        assert_eq!(0, take_whole_units(&mut units_carry, 2.0));
+        assert_eq!(1, take_whole_units(&mut units_carry, 2.0));
+    }
+
+    #[test]
+    fn test_run_limits() {
+        let unlimited = RunLimits::default();
+        assert!(!unlimited.is_total_records_reached(u64::MAX));
+        assert!(!unlimited.is_reached(u64::MAX, u64::MAX));
+
+        let limits = RunLimits::new(Some(60), Some(1000));
+        assert!(!limits.is_total_records_reached(999));
+        assert!(limits.is_total_records_reached(1000));
+
+        assert!(!limits.is_reached(59, 999));
+        assert!(limits.is_reached(60, 0));
+        assert!(limits.is_reached(0, 1000));
+    }
+}
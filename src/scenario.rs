@@ -0,0 +1,821 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::anchor::{AnchorTime, AnchorTimezone};
+use crate::bursts::Bursts;
+use crate::composition::CompositionOp;
+use crate::generator::ValueGenerator;
+use crate::noise::Noise;
+use crate::ramp::Ramp;
+use crate::rate_expr::{RateExpr, MAX_PERIOD_SEC};
+use crate::rdkafka::PartitionerConfig;
+use crate::records_tap::{Pacing, ThroughputUnit};
+use crate::sequence::{Sequence, SequenceEncoding, SequenceScope, MAX_SEQUENCE_KEYS};
+use crate::time_scale::{is_valid_time_scale, TimeScaleKeep, MAX_TIME_SCALE};
+use crate::transition::Transition;
+use crate::waveform::Waveform;
+use crate::workload::{is_valid_rate, WorkloadSegment};
+
+/// Declarative description of a whole Ksunami run: the producer, the records and the workload.
+///
+/// It can be loaded from a TOML or YAML file via [`Scenario::from_file`], and it's the model
+/// that Ksunami is built from. Any section or field missing from the file assumes the same default
+/// value of the corresponding command line argument.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Scenario {
+    /// Configuration of the internal Kafka Producer
+    pub producer: ProducerScenario,
+
+    /// Destination and content of the records
+    pub records: RecordsScenario,
+
+    /// Amount of records to produce, over time
+    pub workload: WorkloadScenario,
+
+    /// When to stop producing records
+    pub run: RunScenario,
+}
+
+/// The `[producer]` section of a [`Scenario`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProducerScenario {
+    /// Initial Kafka Brokers to connect to (format: 'HOST:PORT,...')
+    pub brokers: Option<String>,
+
+    /// Client identifier used by the internal Kafka Producer
+    pub client_id: String,
+
+    /// Partitioner used by the internal Kafka Producer
+    pub partitioner: PartitionerConfig,
+
+    /// Additional configuration used by the internal Kafka Producer
+    pub config: BTreeMap<String, String>,
+}
+
+/// The `[records]` section of a [`Scenario`].
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecordsScenario {
+    /// Destination Topic
+    pub topic: Option<String>,
+
+    /// Records Key (format: 'KEY_TYPE:INPUT')
+    pub key: Option<ValueGenerator>,
+
+    /// Records Payload (format: 'PAYLOAD_TYPE:INPUT')
+    pub payload: Option<ValueGenerator>,
+
+    /// Destination Topic Partition
+    pub partition: Option<i32>,
+
+    /// Records Header(s)
+    pub headers: BTreeMap<String, String>,
+
+    /// Records Header(s) with a monotonic sequence of integers, as text
+    pub header_seqs: BTreeMap<String, Sequence>,
+
+    /// Schema Registry URL, where to register (or look up) the schema of 'avro' and 'protobuf' key and payload
+    pub schema_registry: Option<String>,
+}
+
+/// The `[workload]` section of a [`Scenario`].
+///
+/// The workload is described either by the 4 phases (`min`, `up`, `max` and `down`), by `segments`,
+/// by a rate `trace` to replay, by a `waveform`, by a math expression (`rate_expr`), or by the composition
+/// of other workloads (`compose`).
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkloadScenario {
+    /// Minimum amount of records/sec
+    pub min: Option<f64>,
+
+    /// How long to produce at minimum records/sec, before ramp-up
+    pub min_sec: u32,
+
+    /// Maximum amount of records/sec
+    pub max: Option<f64>,
+
+    /// How long to produce at maximum records/sec, before ramp-down
+    pub max_sec: u32,
+
+    /// Ramp-up transition from minimum to maximum records/sec
+    pub up: Transition,
+
+    /// How long the ramp-up transition should last
+    pub up_sec: u32,
+
+    /// Ramp-down transition from maximum to minimum records/sec
+    pub down: Transition,
+
+    /// How long the ramp-down transition should last
+    pub down_sec: u32,
+
+    /// Workload segments, alternative to the 4 phases
+    pub segments: Vec<WorkloadSegment>,
+
+    /// Rate trace file to replay (format: CSV or JSON), alternative to the 4 phases and to `segments`
+    pub trace: Option<PathBuf>,
+
+    /// Transition between the points of the rate trace
+    pub trace_transition: Transition,
+
+    /// Replay the rate trace in a loop, instead of once
+    pub trace_loop: bool,
+
+    /// Periodic waveform (format: 'TYPE:AMPLITUDE:OFFSET:PERIOD[:SHIFT]'), alternative to all the above
+    pub waveform: Option<Waveform>,
+
+    /// Math expression of the time 't', in seconds since the beginning of the cycle, alternative to all the above
+    pub rate_expr: Option<RateExpr>,
+
+    /// How long the cycle of the `rate_expr` lasts, in seconds
+    pub rate_expr_period: u32,
+
+    /// Composition of other workloads, alternative to all the above
+    pub compose: Option<CompositionScenario>,
+
+    /// Follow the workload only once: only for the parts of a composition
+    pub once: bool,
+
+    /// Timezone (or UTC offset) of the wall-clock the workload is anchored to
+    pub anchor_tz: Option<AnchorTimezone>,
+
+    /// Time of the day, in `anchor_tz`, that the workload cycle begins at
+    pub anchor_time: AnchorTime,
+
+    /// Seconds of the workload that each real second covers: greater than 1 compresses the workload, lower stretches it
+    pub time_scale: f64,
+
+    /// What to keep of the workload when time-scaled: the rate (records/sec) or the volume (records overall)
+    pub time_scale_keep: TimeScaleKeep,
+
+    /// Random bursts injected on top of the workload (format: 'MEAN_INTERVAL_SEC:HEIGHT:SEC[:TRANSITION_TYPE]')
+    pub bursts: Option<Bursts>,
+
+    /// Noise added to the records/sec of each second (format: 'gaussian:PCT' or 'poisson')
+    pub noise: Option<Noise>,
+
+    /// One-shot warm-up from 0 records/sec, before the first cycle (format: 'SEC[:TRANSITION_TYPE]')
+    pub warm_up: Option<Ramp>,
+
+    /// Cool-down to 0 records/sec, once shutdown is requested or the run limits are reached (format: 'SEC[:TRANSITION_TYPE]')
+    pub cool_down: Option<Ramp>,
+
+    /// How the records of each second are spread across the second itself
+    pub pacing: Pacing,
+
+    /// Unit of `min`, `max` and `segments` rates: records/sec or bytes/sec
+    pub unit: ThroughputUnit,
+}
+
+/// The `[workload.compose]` section of a [`Scenario`]: a workload composed of other workloads.
+///
+/// Each part is described like a `[workload]` section, with its own cycle, time scale, bursts, noise
+/// (and even composition): only `pacing` and `unit` apply to the whole workload, and can't be set in the parts.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CompositionScenario {
+    /// How the parts are combined
+    pub op: CompositionOp,
+
+    /// The workloads combined
+    pub parts: Vec<WorkloadScenario>,
+}
+
+/// The `[run]` section of a [`Scenario`].
+///
+/// When any of the limits is set, the run stops as soon as the first one is reached.
+/// Otherwise, it goes on until Ksunami is interrupted.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunScenario {
+    /// Stop after this many cycles of the workload
+    pub cycles: Option<u64>,
+
+    /// Stop after this many seconds
+    pub duration: Option<u64>,
+
+    /// Stop after this many records have been produced
+    pub total_records: Option<u64>,
+
+    /// Seed of all the randomness of the run, to make it reproducible
+    pub seed: Option<u64>,
+}
+
+impl Default for ProducerScenario {
+    fn default() -> Self {
+        ProducerScenario {
+            brokers: None,
+            client_id: env!("CARGO_PKG_NAME").to_string(),
+            partitioner: PartitionerConfig::default(),
+            config: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for WorkloadScenario {
+    fn default() -> Self {
+        WorkloadScenario {
+            min: None,
+            min_sec: 60,
+            max: None,
+            max_sec: 60,
+            up: Transition::Linear,
+            up_sec: 10,
+            down: Transition::None,
+            down_sec: 10,
+            segments: Vec::new(),
+            trace: None,
+            trace_transition: Transition::None,
+            trace_loop: false,
+            waveform: None,
+            rate_expr: None,
+            rate_expr_period: 24 * 60 * 60,
+            compose: None,
+            once: false,
+            warm_up: None,
+            cool_down: None,
+            anchor_tz: None,
+            anchor_time: AnchorTime::default(),
+            time_scale: 1.0,
+            time_scale_keep: TimeScaleKeep::default(),
+            bursts: None,
+            noise: None,
+            pacing: Pacing::default(),
+            unit: ThroughputUnit::default(),
+        }
+    }
+}
+
+impl Scenario {
+    /// Loads a [`Scenario`] from the file at `path`.
+    ///
+    /// The format is determined by the file extension: `.toml`, or `.yaml`/`.yml`.
+    /// In case of error, it returns a `String` describing what went wrong.
+    pub fn from_file(path: &Path) -> Result<Scenario, String> {
+        let content = match fs::read_to_string(path) {
+            Err(e) => return Err(format!("Failed to read scenario file '{}': {e}", path.display())),
+            Ok(c) => c,
+        };
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Scenario::from_toml(&content),
+            Some("yaml") | Some("yml") => Scenario::from_yaml(&content),
+            _ => Err(format!(
+                "Unsupported scenario file '{}': expected extension '.toml', '.yaml' or '.yml'",
+                path.display()
+            )),
+        }
+        .map_err(|e| format!("Failed to parse scenario file '{}': {e}", path.display()))
+    }
+
+    fn from_toml(content: &str) -> Result<Scenario, String> {
+        toml::from_str(content).map_err(|e| e.to_string())
+    }
+
+    fn from_yaml(content: &str) -> Result<Scenario, String> {
+        serde_yaml::from_str(content).map_err(|e| e.to_string())
+    }
+
+    /// Validates the [`Scenario`], so that Ksunami can be built from it.
+    ///
+    /// In case of error, it returns a `String` describing the problem.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.producer.brokers.is_none() {
+            return Err("Producer 'brokers' must be provided".to_string());
+        }
+        if self.records.topic.is_none() {
+            return Err("Records 'topic' must be provided".to_string());
+        }
+        let is_avro = |g: &Option<ValueGenerator>| matches!(g, Some(ValueGenerator::Avro(_)));
+        if (is_avro(&self.records.key) || is_avro(&self.records.payload)) && self.records.schema_registry.is_none() {
+            return Err("Records 'schema_registry' must be provided, to produce 'avro' key or payload".to_string());
+        }
+        self.records.validate_sequences()?;
+
+        self.workload.validate()?;
+        self.run.validate()
+    }
+}
+
+impl RecordsScenario {
+    /// Validates the [`Sequence`]s of key, payload and headers: those counted per key require records to have one,
+    /// out of no more than [`MAX_SEQUENCE_KEYS`] distinct keys.
+    fn validate_sequences(&self) -> Result<(), String> {
+        let per = |seqs: &[Sequence], scope| seqs.iter().any(|s| s.scope() == scope);
+
+        let key_sequences: Vec<Sequence> = self.key.iter().flat_map(|k| k.sequences()).cloned().collect();
+        if per(&key_sequences, SequenceScope::Key) {
+            return Err("Records 'key' can't contain a 'seq' counted per key".to_string());
+        }
+
+        let mut sequences = key_sequences;
+        sequences.extend(self.payload.iter().flat_map(|p| p.sequences()).cloned());
+        for (k, seq) in &self.header_seqs {
+            if self.headers.contains_key(k) {
+                return Err(format!("Records header '{k}' can't be both a value and a 'seq'"));
+            }
+            if seq.encoding() != SequenceEncoding::Text {
+                return Err(format!("Records header '{k}' only supports 'seq' with 'encoding=text'"));
+            }
+            sequences.push(seq.clone());
+        }
+
+        if per(&sequences, SequenceScope::Key) {
+            match self.key.as_ref().map(ValueGenerator::max_distinct) {
+                None => return Err("Records 'key' must be provided, to count 'seq' per key".to_string()),
+                Some(Some(keys)) if keys <= MAX_SEQUENCE_KEYS => {},
+                Some(_) => {
+                    return Err(format!(
+                    "Records 'key' must have no more than {MAX_SEQUENCE_KEYS} distinct values, to count 'seq' per key"
+                ))
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl WorkloadScenario {
+    /// Validates the `[workload]` section alone: see [`Scenario::validate`].
+    pub fn validate(&self) -> Result<(), String> {
+        if self.once {
+            return Err("Workload 'once' is only supported by the 'parts' of a composition".to_string());
+        }
+
+        self.validate_shape()
+    }
+
+    /// Whether, as a part of a composition, the workload is followed only once: when `once`, or when it
+    /// replays a rate trace not in a loop (at the top level, the run ends with the trace instead).
+    pub fn is_once_part(&self) -> bool {
+        self.once || (self.trace.is_some() && !self.trace_loop)
+    }
+
+    /// Validates what describes the records/sec of the workload: the same applies to the parts of a composition.
+    fn validate_shape(&self) -> Result<(), String> {
+        // Validate `time_scale`, that applies to any workload
+        if !is_valid_time_scale(self.time_scale) {
+            return Err(format!("Workload 'time_scale' must be a number greater than 0, and up to {MAX_TIME_SCALE}"));
+        }
+
+        // Validate `compose`, that replaces all the other values
+        if let Some(compose) = &self.compose {
+            if self.min.is_some()
+                || self.max.is_some()
+                || !self.segments.is_empty()
+                || self.trace.is_some()
+                || self.waveform.is_some()
+                || self.rate_expr.is_some()
+            {
+                return Err(
+                    "Workload 'compose' can't be combined with 'min', 'max', 'segments', 'trace', 'waveform' and 'rate_expr'"
+                        .to_string(),
+                );
+            }
+            if compose.parts.is_empty() {
+                return Err("Workload 'compose' must have at least 1 part".to_string());
+            }
+            for (i, part) in compose.parts.iter().enumerate() {
+                if part.pacing != Pacing::default()
+                    || part.unit != ThroughputUnit::default()
+                    || part.warm_up.is_some()
+                    || part.cool_down.is_some()
+                {
+                    return Err(
+                        "Workload 'pacing', 'unit', 'warm_up' and 'cool_down' can't be set in the 'parts' of a composition"
+                            .to_string(),
+                    );
+                }
+                part.validate_shape().map_err(|e| format!("Workload 'compose' part {}: {e}", i + 1))?;
+            }
+
+            return Ok(());
+        }
+
+        // Validate `rate_expr`, that replaces all the other values
+        if self.rate_expr.is_some() {
+            if self.min.is_some()
+                || self.max.is_some()
+                || !self.segments.is_empty()
+                || self.trace.is_some()
+                || self.waveform.is_some()
+            {
+                return Err(
+                    "Workload 'rate_expr' can't be combined with 'min', 'max', 'segments', 'trace' and 'waveform'"
+                        .to_string(),
+                );
+            }
+            if self.rate_expr_period == 0 || self.rate_expr_period > MAX_PERIOD_SEC {
+                return Err(format!("Workload 'rate_expr_period' must be within [1..{MAX_PERIOD_SEC}]"));
+            }
+
+            return Ok(());
+        }
+
+        // Validate `waveform`, that replaces all the other values
+        if self.waveform.is_some() {
+            if self.min.is_some() || self.max.is_some() || !self.segments.is_empty() || self.trace.is_some() {
+                return Err(
+                    "Workload 'waveform' can't be combined with 'min', 'max', 'segments' and 'trace'".to_string()
+                );
+            }
+
+            return Ok(());
+        }
+
+        // Validate `trace`, that replaces all the other values
+        if self.trace.is_some() {
+            if self.min.is_some() || self.max.is_some() || !self.segments.is_empty() {
+                return Err("Workload 'trace' can't be combined with 'min', 'max' and 'segments'".to_string());
+            }
+
+            return Ok(());
+        }
+
+        // Validate `segments`, that replace all the other values
+        if !self.segments.is_empty() {
+            if self.min.is_some() || self.max.is_some() {
+                return Err("Workload 'segments' can't be combined with 'min' and 'max'".to_string());
+            }
+            if self.segments.iter().any(|s| s.transition() != Transition::None && s.duration_sec() == 0) {
+                return Err(
+                    "Workload 'segment' must last more than 0 seconds when 'transition' is not 'none'".to_string()
+                );
+            }
+            if self.segments.iter().any(|s| !is_valid_rate(s.rate())) {
+                return Err("Workload 'segment' rate must be a non-negative number".to_string());
+            }
+            if self.segments.iter().all(|s| s.duration_sec() == 0) {
+                return Err("At least one workload 'segment' must last more than 0 seconds".to_string());
+            }
+
+            return Ok(());
+        }
+
+        // Validate values provided for `min` and `max`
+        match (self.min, self.max) {
+            (Some(min), Some(max)) if !is_valid_rate(min) || !is_valid_rate(max) => {
+                return Err("Workload 'min' and 'max' must be non-negative numbers".to_string())
+            },
+            (Some(min), Some(max)) if min < max => {},
+            (Some(_), Some(_)) => return Err("Workload 'min' must be less than 'max'".to_string()),
+            _ => return Err(
+                "Workload 'min' and 'max' must be provided, unless 'segments', 'trace', 'waveform', 'rate_expr' or 'compose' are"
+                    .to_string(),
+            ),
+        }
+
+        // Validate `(up|down)` transition in respect to their `(up|down)_sec` value
+        if self.up != Transition::None && self.up_sec == 0 {
+            return Err("Workload 'up-sec' must be greater than 0 when 'up' transition is not 'none'".to_string());
+        }
+        if self.down != Transition::None && self.down_sec == 0 {
+            return Err("Workload 'down-sec' must be greater than 0 when 'down' transition is not 'none'".to_string());
+        }
+
+        // Validate that non-zero values are assigned to `min_sec` and `max_sec`
+        if self.min_sec == 0 || self.max_sec == 0 {
+            return Err("Workload 'min-sec/max-sec' must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+impl RunScenario {
+    /// Validates the `[run]` section alone: see [`Scenario::validate`].
+    pub fn validate(&self) -> Result<(), String> {
+        if self.cycles == Some(0) || self.duration == Some(0) || self.total_records == Some(0) {
+            return Err("Run 'cycles/duration/total-records' must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_toml() {
+        let scenario = Scenario::from_toml(
+            r#"
+            [producer]
+            brokers = "localhost:9092"
+            partitioner = "murmur2_random"
+
+            [producer.config]
+            "linger.ms" = "200"
+
+            [records]
+            topic = "a_topic"
+            key = "alpha:10"
+            payload = "int:1-100"
+
+            [records.headers]
+            source = "ksunami"
+
+            [workload]
+            min = 10
+            max = 1000
+            up = "ease-in"
+            down_sec = 20
+            unit = "bytes"
+            anchor_tz = "Europe/London"
+            anchor_time = "06:30"
+            bursts = "3600:10x:60:spike-in"
+            noise = "gaussian:5"
+            warm_up = "30"
+            cool_down = "60:ease-out"
+
+            [run]
+            cycles = 3
+            seed = 42
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(Some("localhost:9092".to_string()), scenario.producer.brokers);
+        assert_eq!("ksunami", scenario.producer.client_id);
+        assert_eq!(PartitionerConfig::Murmur2Random, scenario.producer.partitioner);
+        assert_eq!(Some(&"200".to_string()), scenario.producer.config.get("linger.ms"));
+
+        assert_eq!(Some("a_topic".to_string()), scenario.records.topic);
+        assert_eq!(Some(ValueGenerator::RandAlphaNum(10)), scenario.records.key);
+        assert_eq!(Some(ValueGenerator::RandInt(1, 100)), scenario.records.payload);
+        assert_eq!(None, scenario.records.partition);
+        assert_eq!(1, scenario.records.headers.len());
+
+        assert_eq!(Some(10.0), scenario.workload.min);
+        assert_eq!(60, scenario.workload.min_sec);
+        assert_eq!(Some(1000.0), scenario.workload.max);
+        assert_eq!(Transition::EaseIn, scenario.workload.up);
+        assert_eq!(Transition::None, scenario.workload.down);
+        assert_eq!(20, scenario.workload.down_sec);
+        assert_eq!(ThroughputUnit::Bytes, scenario.workload.unit);
+        assert_eq!(AnchorTimezone::clap_parser("Europe/London").ok(), scenario.workload.anchor_tz);
+        assert_eq!(AnchorTime::clap_parser("06:30").ok(), Some(scenario.workload.anchor_time));
+        assert_eq!(Bursts::clap_parser("3600:10x:60:spike-in").ok(), scenario.workload.bursts);
+        assert_eq!(Some(Noise::Gaussian(5.0)), scenario.workload.noise);
+        assert_eq!(Some(Ramp::new(30, Transition::Linear)), scenario.workload.warm_up);
+        assert_eq!(Some(Ramp::new(60, Transition::EaseOut)), scenario.workload.cool_down);
+
+        assert_eq!(Some(3), scenario.run.cycles);
+        assert_eq!(None, scenario.run.duration);
+        assert_eq!(None, scenario.run.total_records);
+        assert_eq!(Some(42), scenario.run.seed);
+
+        assert!(scenario.validate().is_ok());
+    }
+
+    #[test]
+    fn test_from_yaml() {
+        let scenario = Scenario::from_yaml(
+            r#"
+            producer:
+              brokers: localhost:9092
+            records:
+              topic: a_topic
+            workload:
+              segments:
+                - { rate: 0.5, sec: 300 }
+                - { rate: 200, sec: 30, transition: ease-in-out }
+                - { rate: 200, sec: 600 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                WorkloadSegment::new(0.5, 300, Transition::None),
+                WorkloadSegment::new(200.0, 30, Transition::EaseInOut),
+                WorkloadSegment::new(200.0, 600, Transition::None),
+            ],
+            scenario.workload.segments
+        );
+
+        assert!(scenario.validate().is_ok());
+    }
+
+    #[test]
+    fn test_avro_requires_schema_registry() {
+        let schema_path = std::env::temp_dir().join("ksunami_test_scenario.avsc");
+        std::fs::write(&schema_path, r#""long""#).unwrap();
+
+        let toml = format!(
+            r#"
+            [producer]
+            brokers = "localhost:9092"
+
+            [records]
+            topic = "a_topic"
+            payload = "avro:{}"
+
+            [workload]
+            min = 10
+            max = 100
+            "#,
+            schema_path.display()
+        );
+        let mut scenario = Scenario::from_toml(&toml).unwrap();
+        assert_eq!(
+            Err("Records 'schema_registry' must be provided, to produce 'avro' key or payload".to_string()),
+            scenario.validate()
+        );
+
+        scenario.records.schema_registry = Some("http://localhost:8081".to_string());
+        assert!(scenario.validate().is_ok());
+    }
+
+    #[test]
+    fn test_sequences_validation() {
+        let mut scenario = Scenario::from_toml(
+            r#"
+            [producer]
+            brokers = "localhost:9092"
+
+            [records]
+            topic = "a_topic"
+            payload = "template:{\"seq\":{{seq per=key}}}"
+
+            [records.headers]
+            plain = "seq:start=1"
+
+            [records.header_seqs]
+            seq = "start=1"
+
+            [workload]
+            min = 10
+            max = 100
+            "#,
+        )
+        .unwrap();
+        assert_eq!(Err("Records 'key' must be provided, to count 'seq' per key".to_string()), scenario.validate());
+
+        scenario.records.key = Some(ValueGenerator::clap_parser("seq:per=key").unwrap());
+        assert_eq!(Err("Records 'key' can't contain a 'seq' counted per key".to_string()), scenario.validate());
+
+        scenario.records.key = Some(ValueGenerator::clap_parser("seq:").unwrap());
+        assert_eq!(
+            Err("Records 'key' must have no more than 1000000 distinct values, to count 'seq' per key".to_string()),
+            scenario.validate()
+        );
+        scenario.records.key = Some(ValueGenerator::clap_parser("alpha:4").unwrap());
+        assert!(scenario.validate().is_err());
+
+        scenario.records.key = Some(ValueGenerator::clap_parser("alpha:3").unwrap());
+        assert!(scenario.validate().is_ok());
+        scenario.records.key = Some(ValueGenerator::clap_parser("int:1-1000000").unwrap());
+        assert!(scenario.validate().is_ok());
+
+        // Plain headers are left as they are
+        assert_eq!(Some(&"seq:start=1".to_string()), scenario.records.headers.get("plain"));
+        assert_eq!(Some(&Sequence::parse("start=1").unwrap()), scenario.records.header_seqs.get("seq"));
+
+        scenario.records.header_seqs.insert("bad".to_string(), Sequence::parse("encoding=be").unwrap());
+        assert_eq!(
+            Err("Records header 'bad' only supports 'seq' with 'encoding=text'".to_string()),
+            scenario.validate()
+        );
+
+        scenario.records.header_seqs.insert("plain".to_string(), Sequence::default());
+        scenario.records.header_seqs.remove("bad");
+        assert_eq!(Err("Records header 'plain' can't be both a value and a 'seq'".to_string()), scenario.validate());
+    }
+
+    #[test]
+    fn test_composition() {
+        let scenario = Scenario::from_toml(
+            r#"
+            [workload.compose]
+            op = "sum"
+
+            [[workload.compose.parts]]
+            segments = [{ rate = 200, sec = 3600 }]
+
+            [[workload.compose.parts]]
+            waveform = "sine:2500:2500:600"
+
+            [[workload.compose.parts]]
+            rate_expr = "100 * (t % 60 < 10)"
+            rate_expr_period = 3600
+
+            [[workload.compose.parts]]
+            once = true
+            segments = [{ rate = 0, sec = 1800 }, { rate = 5000, sec = 60, transition = "spike-in" }]
+
+            [[workload.compose.parts]]
+            trace = "spike.csv"
+
+            [[workload.compose.parts]]
+            trace = "daily.csv"
+            trace_loop = true
+            "#,
+        )
+        .unwrap();
+
+        let compose = scenario.workload.compose.as_ref().unwrap();
+        assert_eq!(CompositionOp::Sum, compose.op);
+        assert_eq!(6, compose.parts.len());
+        // Only the parts followed once, explicitly or replaying a trace not in a loop
+        let once_parts: Vec<bool> = compose.parts.iter().map(WorkloadScenario::is_once_part).collect();
+        assert_eq!(vec![false, false, false, true, true, false], once_parts);
+        assert_eq!(Waveform::clap_parser("sine:2500:2500:600").ok(), compose.parts[1].waveform);
+        assert_eq!("100 * (t % 60 < 10)", compose.parts[2].rate_expr.as_ref().unwrap().to_string());
+        assert_eq!(3600, compose.parts[2].rate_expr_period);
+        assert!(compose.parts[3].once);
+        assert!(scenario.workload.validate().is_ok());
+
+        let mut workload = scenario.workload.clone();
+        workload.compose.as_mut().unwrap().parts[1].min = Some(10.0);
+        assert_eq!(
+            Err("Workload 'compose' part 2: Workload 'waveform' can't be combined with 'min', 'max', 'segments' and 'trace'"
+                .to_string()),
+            workload.validate()
+        );
+
+        let mut workload = scenario.workload.clone();
+        workload.compose.as_mut().unwrap().parts[0].unit = ThroughputUnit::Bytes;
+        assert_eq!(
+            Err("Workload 'pacing', 'unit', 'warm_up' and 'cool_down' can't be set in the 'parts' of a composition"
+                .to_string()),
+            workload.validate()
+        );
+
+        let mut workload = scenario.workload.clone();
+        workload.once = true;
+        assert_eq!(
+            Err("Workload 'once' is only supported by the 'parts' of a composition".to_string()),
+            workload.validate()
+        );
+
+        let workload = Scenario::from_toml("[workload.compose]\nop = \"max\"\nparts = []").unwrap().workload;
+        assert_eq!(Err("Workload 'compose' must have at least 1 part".to_string()), workload.validate());
+    }
+
+    #[test]
+    fn test_failure_scenario() {
+        let res = Scenario::from_toml("[producer]\nbrokerz = \"localhost:9092\"");
+        assert!(res.is_err());
+        assert!(res.unwrap_err().contains("unknown field `brokerz`"));
+
+        let res = Scenario::from_toml("[records]\nkey = \"stringz:abc\"");
+        assert!(res.is_err());
+        assert!(res.unwrap_err().contains("Unsupported TYPE 'stringz:...'"));
+
+        let mut scenario = Scenario::from_toml("[workload]\nmin = 100\nmax = 10").unwrap();
+        assert_eq!(Err("Producer 'brokers' must be provided".to_string()), scenario.validate());
+
+        scenario.producer.brokers = Some("localhost:9092".to_string());
+        scenario.records.topic = Some("a_topic".to_string());
+        assert_eq!(Err("Workload 'min' must be less than 'max'".to_string()), scenario.validate());
+
+        scenario.workload.segments.push(WorkloadSegment::new(10.0, 10, Transition::None));
+        assert_eq!(Err("Workload 'segments' can't be combined with 'min' and 'max'".to_string()), scenario.validate());
+
+        scenario.workload.waveform = Waveform::clap_parser("sine:10:20:60").ok();
+        assert_eq!(
+            Err("Workload 'waveform' can't be combined with 'min', 'max', 'segments' and 'trace'".to_string()),
+            scenario.validate()
+        );
+
+        scenario.workload.rate_expr = RateExpr::clap_parser("10 * t").ok();
+        assert_eq!(
+            Err("Workload 'rate_expr' can't be combined with 'min', 'max', 'segments', 'trace' and 'waveform'"
+                .to_string()),
+            scenario.validate()
+        );
+
+        scenario.workload.waveform = None;
+        scenario.workload.segments.clear();
+        scenario.workload.min = None;
+        scenario.workload.max = None;
+        scenario.workload.rate_expr_period = 0;
+        assert_eq!(Err("Workload 'rate_expr_period' must be within [1..31622400]".to_string()), scenario.validate());
+
+        scenario.workload.rate_expr = None;
+        scenario.workload.segments.push(WorkloadSegment::new(10.0, 10, Transition::None));
+        scenario.workload.time_scale = 0.0;
+        assert_eq!(
+            Err("Workload 'time_scale' must be a number greater than 0, and up to 86400".to_string()),
+            scenario.validate()
+        );
+        scenario.workload.time_scale = 1e12;
+        assert!(scenario.validate().is_err());
+
+        scenario.workload.time_scale = 1.0;
+        scenario.run.total_records = Some(0);
+        assert_eq!(Err("Run 'cycles/duration/total-records' must be greater than 0".to_string()), scenario.validate());
+
+        let res = Scenario::from_file(Path::new("README.md"));
+        assert!(res.is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+use ureq::{Agent, AgentBuilder};
+
+/// Content type of the requests to the Schema Registry REST API.
+const CONTENT_TYPE: &str = "application/vnd.schemaregistry.v1+json";
+
+/// How long to wait for a response from the Schema Registry, before giving up.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// First byte of the Confluent wire format.
+const WIRE_FORMAT_MAGIC_BYTE: u8 = 0;
+
+/// Returns what records in the Confluent wire format begin with: the magic byte `0`, followed by the 4 bytes
+/// (big-endian) of the `schema_id`. What follows depends on the type of schema.
+pub fn wire_format_prefix(schema_id: u32) -> [u8; 5] {
+    let id = schema_id.to_be_bytes();
+    [WIRE_FORMAT_MAGIC_BYTE, id[0], id[1], id[2], id[3]]
+}
+
+/// Appends to `buf` the zig-zag, variable-length encoding of `v`: how Avro encodes `int` and `long`, and how
+/// the Confluent wire format encodes the message indexes of Protobuf records.
+pub fn write_varint(buf: &mut Vec<u8>, v: i64) {
+    let mut zig_zag = ((v << 1) ^ (v >> 63)) as u64;
+    while zig_zag >= 0x80 {
+        buf.push((zig_zag as u8 & 0x7f) | 0x80);
+        zig_zag >>= 7;
+    }
+    buf.push(zig_zag as u8);
+}
+
+/// Client of a [Confluent Schema Registry](https://docs.confluent.io/platform/current/schema-registry/index.html)
+/// (or compatible), used to obtain the ID that the Confluent wire format prefixes records with.
+#[derive(Debug)]
+pub struct SchemaRegistry {
+    url: String,
+    agent: Agent,
+}
+
+/// The part of the Schema Registry responses that Ksunami needs.
+#[derive(Debug, Deserialize)]
+struct SchemaIdResponse {
+    id: u32,
+}
+
+impl SchemaRegistry {
+    pub fn new(url: &str) -> SchemaRegistry {
+        SchemaRegistry {
+            url: url.trim_end_matches('/').to_string(),
+            agent: AgentBuilder::new().timeout(TIMEOUT).build(),
+        }
+    }
+
+    /// Returns the ID of the `schema` under `subject`: looked up, if already registered, or registered.
+    ///
+    /// The `schema_type` is one of those supported by the Schema Registry (ex. `AVRO`, `PROTOBUF`).
+    ///
+    /// In case of error, it returns a `String` describing what went wrong.
+    pub fn register(&self, subject: &str, schema: &str, schema_type: &str) -> Result<u32, String> {
+        match self.post(&format!("/subjects/{subject}"), schema, schema_type)? {
+            Some(id) => Ok(id),
+            None => {
+                debug!("Schema not found under subject '{subject}': registering it");
+                self.post(&format!("/subjects/{subject}/versions"), schema, schema_type)?
+                    .ok_or_else(|| format!("Failed to register schema under subject '{subject}': not found"))
+            },
+        }
+    }
+
+    /// Posts the `schema` to the `path` of the Schema Registry, returning the ID in the response
+    /// or `None` if the response was `404 Not Found`.
+    fn post(&self, path: &str, schema: &str, schema_type: &str) -> Result<Option<u32>, String> {
+        let url = format!("{}{path}", self.url);
+        let err = |msg: String| format!("Failed to POST schema to Schema Registry '{url}': {msg}");
+
+        match self
+            .agent
+            .post(&url)
+            .set("Content-Type", CONTENT_TYPE)
+            .send_json(json!({ "schema": schema, "schemaType": schema_type }))
+        {
+            Ok(response) => {
+                response.into_json::<SchemaIdResponse>().map(|r| Some(r.id)).map_err(|e| err(e.to_string()))
+            },
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(ureq::Error::Status(status, response)) => {
+                Err(err(format!("status {status}: {}", response.into_string().unwrap_or_default())))
+            },
+            Err(e) => Err(err(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    /// Starts a stub of the Schema Registry, that gives the `responses` (status and body) in order.
+    ///
+    /// Returns its URL, and a handle to join to get the requests it received (request line and body).
+    fn stub(responses: Vec<(u16, &'static str)>) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|(status, body)| {
+                    let (mut stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).unwrap();
+                    let mut content_length = 0;
+                    loop {
+                        let mut header = String::new();
+                        reader.read_line(&mut header).unwrap();
+                        if header == "\r\n" {
+                            break;
+                        }
+                        if let Some(len) = header.to_lowercase().strip_prefix("content-length:") {
+                            content_length = len.trim().parse().unwrap();
+                        }
+                    }
+                    let mut request_body = vec![0; content_length];
+                    reader.read_exact(&mut request_body).unwrap();
+
+                    write!(
+                        stream,
+                        "HTTP/1.1 {status} Stub\r\nContent-Type: {CONTENT_TYPE}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                    .unwrap();
+
+                    format!("{} {}", request_line.trim(), String::from_utf8(request_body).unwrap())
+                })
+                .collect()
+        });
+
+        (url, handle)
+    }
+
+    #[test]
+    fn test_register() {
+        // Already registered: looked up
+        let (url, handle) = stub(vec![(200, r#"{"subject":"t-value","version":1,"id":7,"schema":"\"int\""}"#)]);
+        assert_eq!(Ok(7), SchemaRegistry::new(&url).register("t-value", r#""int""#, "AVRO"));
+        assert_eq!(
+            vec![r#"POST /subjects/t-value HTTP/1.1 {"schema":"\"int\"","schemaType":"AVRO"}"#],
+            handle.join().unwrap()
+        );
+
+        // Not registered yet: registered
+        let (url, handle) =
+            stub(vec![(404, r#"{"error_code":40403,"message":"Schema not found"}"#), (200, r#"{"id":8}"#)]);
+        assert_eq!(Ok(8), SchemaRegistry::new(&format!("{url}/")).register("t-key", r#""long""#, "AVRO"));
+        let requests = handle.join().unwrap();
+        assert!(requests[0].starts_with("POST /subjects/t-key HTTP/1.1"));
+        assert!(requests[1].starts_with("POST /subjects/t-key/versions HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_wire_format_prefix() {
+        assert_eq!([0, 0, 0, 1, 2], wire_format_prefix(258));
+        assert_eq!([0, 255, 255, 255, 255], wire_format_prefix(u32::MAX));
+    }
+
+    #[test]
+    fn test_write_varint() {
+        for (v, expected) in [(0, vec![0x00]), (-1, vec![0x01]), (1, vec![0x02]), (64, vec![0x80, 0x01])] {
+            let mut buf = Vec::new();
+            write_varint(&mut buf, v);
+            assert_eq!(expected, buf);
+        }
+
+        let mut buf = Vec::new();
+        write_varint(&mut buf, i64::MIN);
+        assert_eq!(vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01], buf);
+    }
+
+    #[test]
+    fn test_register_errors() {
+        let (url, handle) =
+            stub(vec![(404, "{}"), (409, r#"{"error_code":409,"message":"Schema being registered is incompatible"}"#)]);
+        let res = SchemaRegistry::new(&url).register("t-value", r#""int""#, "AVRO");
+        handle.join().unwrap();
+        assert_eq!(
+            Err(format!(
+                "Failed to POST schema to Schema Registry '{url}/subjects/t-value/versions': status 409: \
+                {{\"error_code\":409,\"message\":\"Schema being registered is incompatible\"}}"
+            )),
+            res
+        );
+
+        let (url, handle) = stub(vec![(200, "not json")]);
+        assert!(SchemaRegistry::new(&url).register("t-value", r#""int""#, "AVRO").is_err());
+        handle.join().unwrap();
+    }
+}
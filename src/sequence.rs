@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use serde::{de, Deserialize, Deserializer};
+
+use crate::generator::RecordContext;
+
+/// Most distinct keys a [`Sequence`] counted per key can be used with, as it keeps a counter for each.
+pub const MAX_SEQUENCE_KEYS: u64 = 1_000_000;
+
+/// What the values of a [`Sequence`] are counted per.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SequenceScope {
+    /// A single counter, for all the records.
+    #[default]
+    Global,
+
+    /// A counter for each (distinct) record key.
+    Key,
+}
+
+/// How the values of a [`Sequence`] are encoded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SequenceEncoding {
+    /// Decimal number, as text (ex. `42`).
+    #[default]
+    Text,
+
+    /// Signed 64-bit integer, in 8 bytes (big-endian).
+    BigEndian,
+}
+
+/// A monotonic sequence of integers (ex. `0, 1, 2...`), to detect downstream if records are lost,
+/// duplicated or reordered.
+///
+/// Values are `start + step * N`, where `N` is the number of records generated before, counted according
+/// to the [`SequenceScope`]. For [`SequenceScope::Global`], `N` is the index of the record: for the others,
+/// it's counted as records are generated, so records must be generated in order. Counting per key, requires
+/// the keys to be no more than [`MAX_SEQUENCE_KEYS`].
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    start: i64,
+    step: i64,
+    scope: SequenceScope,
+    encoding: SequenceEncoding,
+
+    /// Records generated so far, for each key (unused for [`SequenceScope::Global`])
+    counts: Arc<Mutex<HashMap<Vec<u8>, u64>>>,
+}
+
+impl Sequence {
+    /// Parses the `&str` provided, a (possibly empty) comma-separated list of options in the format `NAME=VALUE`:
+    ///
+    /// * `start=START`: first value of the sequence (default: `0`)
+    /// * `step=STEP`: difference between consecutive values (default: `1`)
+    /// * `per=key`: counts values for each record key (default: for all records): `per=partition` is rejected,
+    ///   as the partition of a record is only chosen by the Kafka Producer partitioner, once generated
+    /// * `encoding=text|be`: values as text, or as big-endian 64-bit integers (default: `text`)
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn parse(options: &str) -> Result<Sequence, String> {
+        let mut seq = Sequence::default();
+
+        for option in options.split(',').filter(|o| !o.is_empty()) {
+            let Some((name, value)) = option.split_once('=') else {
+                return Err(format!("Option '{option}' of 'seq' should have 'NAME=VALUE' format"));
+            };
+
+            match (name, value) {
+                ("start", v) => seq.start = v.parse().map_err(|e| format!("Failed to parse 'start' of 'seq': {e}"))?,
+                ("step", v) => seq.step = v.parse().map_err(|e| format!("Failed to parse 'step' of 'seq': {e}"))?,
+                ("per", "key") => seq.scope = SequenceScope::Key,
+                ("per", "partition") => {
+                    return Err("Unsupported value 'partition' of option 'per' of 'seq': records are generated before \
+                         the Kafka Producer partitioner assigns them a partition"
+                        .to_string())
+                },
+                ("encoding", "text") => seq.encoding = SequenceEncoding::Text,
+                ("encoding", "be") => seq.encoding = SequenceEncoding::BigEndian,
+                ("per" | "encoding", v) => return Err(format!("Unsupported value '{v}' of option '{name}' of 'seq'")),
+                _ => return Err(format!("Unsupported option '{name}' of 'seq'")),
+            }
+        }
+
+        Ok(seq)
+    }
+
+    pub fn scope(&self) -> SequenceScope {
+        self.scope
+    }
+
+    pub fn encoding(&self) -> SequenceEncoding {
+        self.encoding
+    }
+
+    /// Returns the next value of the sequence, for the given `record`.
+    pub fn next(&self, record: &RecordContext) -> i64 {
+        let n = match self.scope {
+            SequenceScope::Global => record.index,
+            SequenceScope::Key => self.count(record.key.unwrap_or_default()),
+        };
+
+        self.start.wrapping_add(self.step.wrapping_mul(n as i64))
+    }
+
+    /// Returns the next value of the sequence for the given `record`, encoded.
+    pub fn generate(&self, record: &RecordContext) -> Vec<u8> {
+        let value = self.next(record);
+
+        match self.encoding {
+            SequenceEncoding::Text => value.to_string().into_bytes(),
+            SequenceEncoding::BigEndian => value.to_be_bytes().to_vec(),
+        }
+    }
+
+    /// Returns how many records were counted so far for `counter`, and counts one more.
+    fn count(&self, counter: &[u8]) -> u64 {
+        // If the lock is poisoned, the counts are still valid: they are only ever incremented
+        let mut counts = self.counts.lock().unwrap_or_else(PoisonError::into_inner);
+
+        match counts.get_mut(counter) {
+            Some(count) => {
+                *count += 1;
+                *count - 1
+            },
+            None => {
+                counts.insert(counter.to_vec(), 1);
+                0
+            },
+        }
+    }
+}
+
+impl Default for Sequence {
+    fn default() -> Self {
+        Sequence {
+            start: 0,
+            step: 1,
+            scope: SequenceScope::default(),
+            encoding: SequenceEncoding::default(),
+            counts: Arc::default(),
+        }
+    }
+}
+
+/// A [`Sequence`] is deserialized from the same `OPTIONS` format parsed by [`Sequence::parse`].
+impl<'de> Deserialize<'de> for Sequence {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let options_as_str = String::deserialize(deserializer)?;
+
+        Sequence::parse(&options_as_str).map_err(de::Error::custom)
+    }
+}
+
+/// Sequences are equal if they generate the same values: what they counted so far is ignored.
+impl PartialEq for Sequence {
+    fn eq(&self, other: &Self) -> bool {
+        (self.start, self.step, self.scope, self.encoding) == (other.start, other.step, other.scope, other.encoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(index: u64, key: Option<&[u8]>) -> RecordContext<'_> {
+        RecordContext {
+            index,
+            key,
+        }
+    }
+
+    #[test]
+    fn test_global() {
+        let seq = Sequence::parse("start=100,step=10").unwrap();
+        assert_eq!(100, seq.next(&record(0, None)));
+        assert_eq!(130, seq.next(&record(3, None)));
+        // Only depends on the index of the record
+        assert_eq!(130, seq.next(&record(3, None)));
+
+        assert_eq!(b"7".to_vec(), Sequence::default().generate(&record(7, None)));
+        let seq = Sequence::parse("encoding=be,step=-1").unwrap();
+        assert_eq!((-7i64).to_be_bytes().to_vec(), seq.generate(&record(7, None)));
+    }
+
+    #[test]
+    fn test_per_key() {
+        let seq = Sequence::parse("start=1,per=key").unwrap();
+        let values: Vec<i64> = [b"a", b"b", b"a", b"a", b"b"]
+            .iter()
+            .enumerate()
+            .map(|(i, k)| seq.next(&record(i as u64, Some(k.as_slice()))))
+            .collect();
+        assert_eq!(vec![1, 1, 2, 3, 2], values);
+
+        // Clones share the counts
+        assert_eq!(4, seq.clone().next(&record(5, Some(b"a"))));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Sequence::default(), Sequence::parse("").unwrap());
+        assert_eq!(SequenceScope::Key, Sequence::parse("per=key").unwrap().scope());
+        assert_eq!(SequenceEncoding::BigEndian, Sequence::parse("encoding=be").unwrap().encoding());
+
+        assert_eq!(
+            Err("Failed to parse 'start' of 'seq': invalid digit found in string".to_string()),
+            Sequence::parse("start=one")
+        );
+        assert_eq!(Err("Option 'step' of 'seq' should have 'NAME=VALUE' format".to_string()), Sequence::parse("step"));
+        assert_eq!(Err("Unsupported value 'topic' of option 'per' of 'seq'".to_string()), Sequence::parse("per=topic"));
+        assert_eq!(
+            Err("Unsupported value 'partition' of option 'per' of 'seq': records are generated before the Kafka \
+                 Producer partitioner assigns them a partition"
+                .to_string()),
+            Sequence::parse("per=partition")
+        );
+        assert_eq!(Err("Unsupported option 'stop' of 'seq'".to_string()), Sequence::parse("stop=10"));
+    }
+}
@@ -0,0 +1,266 @@
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::{SecondsFormat, Utc};
+use rand::distributions::{Alphanumeric, DistString};
+use rand::Rng;
+
+use crate::generator::RecordContext;
+use crate::random::uuid_v4;
+use crate::sequence::{Sequence, SequenceEncoding};
+
+/// A text with placeholders (ex. `{"id":"{{uuid}}","user":"{{alpha 8}}"}`), filled in for every record.
+///
+/// The template is parsed once, when provided, and then rendered for every record via [`Template::render`]:
+/// each [`Placeholder`] is replaced with a value generated for that record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    /// The template, as provided by the user
+    source: String,
+
+    /// The template, parsed
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TemplatePart {
+    /// Text copied as-is.
+    Text(String),
+
+    /// Value generated for each record.
+    Placeholder(Placeholder),
+}
+
+/// The placeholders supported by a [`Template`], in the format `{{NAME [ARGS...]}}`.
+#[derive(Debug, Clone, PartialEq)]
+enum Placeholder {
+    /// `{{uuid}}`: a random UUID (version 4).
+    Uuid,
+
+    /// `{{now_ms}}`: milliseconds since the Unix epoch, when the record is generated.
+    NowMs,
+
+    /// `{{now}}`: RFC 3339 timestamp (UTC, in milliseconds), when the record is generated.
+    Now,
+
+    /// `{{seq [OPTIONS]}}`: a [`Sequence`], as text. Without options, the index of the record since the beginning
+    /// of the run, starting from `0`.
+    Seq(Sequence),
+
+    /// `{{alpha LENGTH}}`: a random alphanumeric string.
+    Alpha(usize),
+
+    /// `{{int MIN MAX}}`: a random (signed) integer within an inclusive range.
+    Int(i64, i64),
+
+    /// `{{float MIN MAX}}`: a random float within an inclusive range.
+    Float(f64, f64),
+}
+
+impl Template {
+    /// Parses the `template_as_str`: see [`Placeholder`] for the supported placeholders.
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn parse(template_as_str: &str) -> Result<Template, String> {
+        let mut parts = Vec::new();
+
+        let mut rest = template_as_str;
+        while let Some(start) = rest.find("{{") {
+            if start > 0 {
+                parts.push(TemplatePart::Text(rest[..start].to_string()));
+            }
+
+            let Some(len) = rest[start + 2..].find("}}") else {
+                let pos = template_as_str.len() - rest.len() + start + 1;
+                return Err(format!("Unclosed placeholder at position {pos} of TEMPLATE"));
+            };
+            parts.push(TemplatePart::Placeholder(Placeholder::parse(&rest[start + 2..start + 2 + len])?));
+
+            rest = &rest[start + 2 + len + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Text(rest.to_string()));
+        }
+
+        Ok(Template {
+            source: template_as_str.to_string(),
+            parts,
+        })
+    }
+
+    /// Renders the template for the given `record`, drawing random values from the given `rng`.
+    pub fn render<R: Rng>(&self, rng: &mut R, record: &RecordContext) -> String {
+        let mut rendered = String::with_capacity(self.source.len());
+
+        for part in &self.parts {
+            match part {
+                TemplatePart::Text(t) => rendered.push_str(t),
+                TemplatePart::Placeholder(p) => p.render(rng, record, &mut rendered),
+            }
+        }
+
+        rendered
+    }
+
+    /// The [`Sequence`]s of the `{{seq}}` placeholders.
+    pub fn sequences(&self) -> impl Iterator<Item = &Sequence> {
+        self.parts.iter().filter_map(|part| match part {
+            TemplatePart::Placeholder(Placeholder::Seq(seq)) => Some(seq),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Display for Template {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl Placeholder {
+    /// Parses what's between `{{` and `}}`: the name of the placeholder, followed by its arguments (if any).
+    fn parse(placeholder_as_str: &str) -> Result<Placeholder, String> {
+        let mut tokens = placeholder_as_str.split_whitespace();
+        let name = tokens.next().unwrap_or_default();
+        let args: Vec<&str> = tokens.collect();
+
+        match (name, args.as_slice()) {
+            ("uuid", []) => Ok(Placeholder::Uuid),
+            ("now_ms", []) => Ok(Placeholder::NowMs),
+            ("now", []) => Ok(Placeholder::Now),
+            ("seq", []) => Ok(Placeholder::Seq(Sequence::default())),
+            ("seq", [options]) => match Sequence::parse(options)? {
+                seq if seq.encoding() != SequenceEncoding::Text => {
+                    Err("Placeholder 'seq OPTIONS' only supports 'encoding=text'".to_string())
+                },
+                seq => Ok(Placeholder::Seq(seq)),
+            },
+            ("alpha", [length]) => Ok(Placeholder::Alpha(parse_arg(length, "LENGTH", "alpha LENGTH")?)),
+            ("int", [min, max]) => match (parse_arg(min, "MIN", "int MIN MAX")?, parse_arg(max, "MAX", "int MIN MAX")?)
+            {
+                (min, max) if min > max => Err("Placeholder 'int MIN MAX' must have 'MIN <= MAX'".to_string()),
+                (min, max) => Ok(Placeholder::Int(min, max)),
+            },
+            ("float", [min, max]) => {
+                match (parse_arg(min, "MIN", "float MIN MAX")?, parse_arg(max, "MAX", "float MIN MAX")?) {
+                    (min, max) if !(f64::is_finite(min) && f64::is_finite(max) && min <= max) => {
+                        Err("Placeholder 'float MIN MAX' must have finite 'MIN <= MAX'".to_string())
+                    },
+                    (min, max) => Ok(Placeholder::Float(min, max)),
+                }
+            },
+            _ => Err(format!("Unsupported placeholder '{{{{{}}}}}'", placeholder_as_str.trim())),
+        }
+    }
+
+    /// Renders the placeholder for the given `record`, appending it to `rendered`.
+    fn render<R: Rng>(&self, rng: &mut R, record: &RecordContext, rendered: &mut String) {
+        // Writing to a `String` never fails
+        match self {
+            Placeholder::Uuid => rendered.push_str(&uuid_v4(rng)),
+            Placeholder::NowMs => {
+                let now_ms = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+                write!(rendered, "{now_ms}").unwrap();
+            },
+            Placeholder::Now => rendered.push_str(&Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)),
+            Placeholder::Seq(seq) => write!(rendered, "{}", seq.next(record)).unwrap(),
+            Placeholder::Alpha(l) => Alphanumeric.append_string(rng, rendered, *l),
+            Placeholder::Int(min, max) => write!(rendered, "{}", rng.gen_range(*min..=*max)).unwrap(),
+            Placeholder::Float(min, max) => write!(rendered, "{}", rng.gen_range(*min..=*max)).unwrap(),
+        }
+    }
+}
+
+/// Parses the argument `arg` of the placeholder `format`: `arg_name` is used to describe it in case of error.
+fn parse_arg<T: FromStr>(arg: &str, arg_name: &str, format: &str) -> Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    arg.parse::<T>().map_err(|e| format!("Failed to parse '{arg_name}' of placeholder '{format}': {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    /// The `index`-th record, without key.
+    fn record(index: u64) -> RecordContext<'static> {
+        RecordContext {
+            index,
+            ..RecordContext::default()
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let template =
+            Template::parse(r#"{"id":"{{uuid}}","ts":{{now_ms}},"seq":{{seq}},"user":"{{ alpha 8 }}"}"#).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let rendered = template.render(&mut rng, &record(7));
+        let json: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        let id = json["id"].as_str().unwrap();
+        assert_eq!(36, id.len());
+        assert_eq!(Some('4'), id.chars().nth(14));
+        assert!(json["ts"].as_u64().unwrap() > 1_600_000_000_000);
+        assert_eq!(7, json["seq"].as_u64().unwrap());
+        assert_eq!(8, json["user"].as_str().unwrap().len());
+
+        // Every record is different
+        assert_ne!(rendered, template.render(&mut rng, &record(7)));
+
+        // Random values within their range
+        let template = Template::parse("{{int -3 3}}|{{float 0.5 1}}|{{now}}").unwrap();
+        let rendered = template.render(&mut rng, &record(0));
+        let values: Vec<&str> = rendered.split('|').collect();
+        assert!((-3..=3).contains(&values[0].parse::<i64>().unwrap()));
+        assert!((0.5..=1.0).contains(&values[1].parse::<f64>().unwrap()));
+        assert!(values[2].ends_with('Z'));
+
+        // No placeholders, no changes
+        assert_eq!("plain text", Template::parse("plain text").unwrap().render(&mut rng, &record(0)));
+        assert_eq!("}} {", Template::parse("}} {").unwrap().render(&mut rng, &record(0)));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("a {{seq}}", Template::parse("a {{seq}}").unwrap().to_string());
+
+        assert_eq!(Err("Unclosed placeholder at position 3 of TEMPLATE".to_string()), Template::parse("ab{{seq"));
+        assert_eq!(Err("Unsupported placeholder '{{nope}}'".to_string()), Template::parse("{{ nope }}"));
+        assert_eq!(Err("Unsupported placeholder '{{alpha}}'".to_string()), Template::parse("{{alpha}}"));
+        assert_eq!(Err("Unsupported placeholder '{{}}'".to_string()), Template::parse("{{}}"));
+        assert_eq!(
+            Err("Failed to parse 'LENGTH' of placeholder 'alpha LENGTH': invalid digit found in string".to_string()),
+            Template::parse("{{alpha x}}")
+        );
+        assert_eq!(Err("Placeholder 'int MIN MAX' must have 'MIN <= MAX'".to_string()), Template::parse("{{int 3 1}}"));
+        assert!(Template::parse("{{float 1 nan}}").is_err());
+        assert_eq!(
+            Err("Placeholder 'seq OPTIONS' only supports 'encoding=text'".to_string()),
+            Template::parse("{{seq encoding=be}}")
+        );
+        assert_eq!(Err("Unsupported option 'from' of 'seq'".to_string()), Template::parse("{{seq from=1}}"));
+    }
+
+    #[test]
+    fn test_render_sequences() {
+        let template = Template::parse("{{seq}}|{{seq start=100,step=10}}|{{seq per=key}}").unwrap();
+        assert_eq!(3, template.sequences().count());
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let keyed = |index, key: &'static [u8]| RecordContext {
+            index,
+            key: Some(key),
+        };
+        assert_eq!("0|100|0", template.render(&mut rng, &keyed(0, b"a")));
+        assert_eq!("1|110|0", template.render(&mut rng, &keyed(1, b"b")));
+        assert_eq!("2|120|1", template.render(&mut rng, &keyed(2, b"a")));
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt;
+use std::sync::Arc;
+
+use clap::ValueEnum;
+use serde::Deserialize;
+
+use crate::workload::{RateSchedule, WorkloadPhase};
+
+/// What a [`TimeScaledSchedule`] keeps of the schedule it scales, when compressing or stretching it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeScaleKeep {
+    /// Records/sec stay the same: compressing the schedule produces fewer records overall.
+    #[default]
+    Rate,
+
+    /// Records overall stay the same: compressing the schedule produces more records/sec.
+    Volume,
+}
+
+impl TimeScaleKeep {
+    /// Returns the "name" for the enum value, as used in arguments and logs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeScaleKeep::Rate => "rate",
+            TimeScaleKeep::Volume => "volume",
+        }
+    }
+}
+
+impl fmt::Display for TimeScaleKeep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Highest time scale factor: a day of the schedule for each real second.
+///
+/// Each real second averages the `factor` schedule seconds it covers, so the factor must be bound.
+pub const MAX_TIME_SCALE: f64 = 86_400.0;
+
+/// Whether `factor` can be used to scale the time of a [`RateSchedule`]: greater than `0`, up to [`MAX_TIME_SCALE`].
+pub fn is_valid_time_scale(factor: f64) -> bool {
+    factor > 0.0 && factor <= MAX_TIME_SCALE
+}
+
+/// Implementation of [`clap::value_parser`], used to create an argument by parsing a user-provided value.
+///
+/// The `&str` provided is parsed to a `f64` factor, greater than `0` and up to [`MAX_TIME_SCALE`].
+///
+/// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+pub fn time_scale_clap_parser(factor_as_str: &str) -> Result<f64, String> {
+    match factor_as_str.parse::<f64>() {
+        Err(e) => Err(format!("Failed to parse time scale FACTOR: {e}")),
+        Ok(f) if !is_valid_time_scale(f) => {
+            Err(format!("Time scale FACTOR must be a number greater than 0, and up to {MAX_TIME_SCALE}"))
+        },
+        Ok(f) => Ok(f),
+    }
+}
+
+/// A [`RateSchedule`] compressed or stretched in time: each real second covers `factor` seconds of the schedule.
+///
+/// With a `factor` of `24`, a 24 hours schedule lasts 1 hour; with a `factor` of `0.5`, it lasts 48 hours.
+/// The records/sec of each real second are the average of the schedule seconds it covers (weighted by how much
+/// of each it covers), and when keeping the [`TimeScaleKeep::Volume`], they are multiplied by `factor` as well.
+#[derive(Debug)]
+pub struct TimeScaledSchedule {
+    schedule: Arc<dyn RateSchedule>,
+    factor: f64,
+    keep: TimeScaleKeep,
+}
+
+impl TimeScaledSchedule {
+    pub fn new(schedule: Arc<dyn RateSchedule>, factor: f64, keep: TimeScaleKeep) -> TimeScaledSchedule {
+        TimeScaledSchedule {
+            schedule,
+            factor,
+            keep,
+        }
+    }
+
+    /// The seconds of the schedule covered by the real second `sec` (at least 1), each with the fraction
+    /// of it that is covered: fractions add up to `factor`.
+    fn schedule_secs(&self, sec: u64) -> impl Iterator<Item = (u64, f64)> {
+        let (start, end) = (sec as f64 * self.factor, (sec + 1) as f64 * self.factor);
+
+        (start.floor() as u64..(end.ceil() as u64).max(start.floor() as u64 + 1)).map(move |s| {
+            let covered = end.min((s + 1) as f64) - start.max(s as f64);
+            (s, covered)
+        })
+    }
+
+    /// Multiplier of the records/sec of the schedule.
+    fn rate_factor(&self) -> f64 {
+        match self.keep {
+            TimeScaleKeep::Rate => 1.0,
+            TimeScaleKeep::Volume => self.factor,
+        }
+    }
+}
+
+impl RateSchedule for TimeScaledSchedule {
+    fn records_per_sec_at(&self, sec: u64) -> f64 {
+        let sum: f64 = self.schedule_secs(sec).map(|(s, covered)| self.schedule.records_per_sec_at(s) * covered).sum();
+
+        sum / self.factor * self.rate_factor()
+    }
+
+    fn phase_at(&self, sec: u64) -> WorkloadPhase {
+        self.schedule.phase_at((sec as f64 * self.factor).floor() as u64)
+    }
+
+    fn max_records_per_sec(&self) -> f64 {
+        self.schedule.max_records_per_sec() * self.rate_factor()
+    }
+
+    /// The real seconds a cycle of the schedule takes, rounded up.
+    fn overall_duration_sec(&self) -> u32 {
+        ((self.schedule.overall_duration_sec() as f64 / self.factor).ceil() as u32).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transition::Transition;
+    use crate::workload::{Workload, WorkloadSegment};
+
+    use super::*;
+
+    #[test]
+    fn test_time_scale() {
+        let workload: Arc<dyn RateSchedule> = Arc::new(Workload::from_segments(vec![
+            WorkloadSegment::new(10.0, 4, Transition::None),
+            WorkloadSegment::new(30.0, 4, Transition::None),
+        ]));
+        // Records/sec of each real second of a cycle
+        fn rates(s: &TimeScaledSchedule) -> Vec<f64> {
+            (0..s.overall_duration_sec() as u64).map(|sec| s.records_per_sec_at(sec)).collect()
+        }
+
+        // Compressed: same rates, fewer records overall
+        let compressed = TimeScaledSchedule::new(workload.clone(), 4.0, TimeScaleKeep::Rate);
+        assert_eq!(2, compressed.overall_duration_sec());
+        assert_eq!(vec![10.0, 30.0], rates(&compressed));
+        assert_eq!(30.0, compressed.max_records_per_sec());
+
+        // Compressed: higher rates, same records overall
+        let compressed = TimeScaledSchedule::new(workload.clone(), 4.0, TimeScaleKeep::Volume);
+        assert_eq!(vec![40.0, 120.0], rates(&compressed));
+        assert_eq!(160.0, rates(&compressed).iter().sum::<f64>());
+        assert_eq!(120.0, compressed.max_records_per_sec());
+
+        // Covering seconds of different segments, averages them
+        let uneven: Arc<dyn RateSchedule> = Arc::new(Workload::from_segments(vec![
+            WorkloadSegment::new(10.0, 3, Transition::None),
+            WorkloadSegment::new(30.0, 3, Transition::None),
+        ]));
+        let compressed = TimeScaledSchedule::new(uneven, 2.0, TimeScaleKeep::Rate);
+        assert_eq!(3, compressed.overall_duration_sec());
+        assert_eq!(vec![10.0, 20.0, 30.0], rates(&compressed));
+
+        // Stretched: same rates, more records overall
+        let stretched = TimeScaledSchedule::new(workload.clone(), 0.5, TimeScaleKeep::Rate);
+        assert_eq!(16, stretched.overall_duration_sec());
+        assert_eq!(vec![10.0, 10.0, 10.0, 10.0, 10.0, 10.0, 10.0, 10.0, 30.0, 30.0], rates(&stretched)[..10]);
+        assert_eq!(WorkloadPhase::Min, stretched.phase_at(7));
+        assert_eq!(WorkloadPhase::Max, stretched.phase_at(8));
+
+        // Stretched: lower rates, same records overall
+        let stretched = TimeScaledSchedule::new(workload, 0.5, TimeScaleKeep::Volume);
+        assert_eq!(160.0, rates(&stretched).iter().sum::<f64>());
+    }
+
+    #[test]
+    fn test_time_scale_fractional() {
+        let workload: Arc<dyn RateSchedule> = Arc::new(Workload::from_segments(vec![
+            WorkloadSegment::new(10.0, 5, Transition::None),
+            WorkloadSegment::new(30.0, 5, Transition::None),
+        ]));
+        let cycle_volume: f64 = (0..10).map(|sec| workload.records_per_sec_at(sec)).sum();
+        assert_eq!(200.0, cycle_volume);
+
+        // Each real second covers 2.5 seconds of the schedule: half of a segment
+        let compressed = TimeScaledSchedule::new(workload.clone(), 2.5, TimeScaleKeep::Volume);
+        assert_eq!(4, compressed.overall_duration_sec());
+        let rates: Vec<f64> = (0..4).map(|sec| compressed.records_per_sec_at(sec)).collect();
+        assert_eq!(vec![25.0, 25.0, 75.0, 75.0], rates);
+        assert_eq!(cycle_volume, rates.iter().sum::<f64>());
+
+        // Seconds of the schedule aren't counted twice, even when cycles don't end on a real second
+        let compressed = TimeScaledSchedule::new(workload.clone(), 3.0, TimeScaleKeep::Volume);
+        let volume: f64 = (0..10).map(|sec| compressed.records_per_sec_at(sec)).sum();
+        assert!((3.0 * cycle_volume - volume).abs() < 1e-9);
+
+        let stretched = TimeScaledSchedule::new(workload, 0.4, TimeScaleKeep::Volume);
+        let volume: f64 = (0..25).map(|sec| stretched.records_per_sec_at(sec)).sum();
+        assert!((cycle_volume - volume).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_time_scale_clap_parser() {
+        assert_eq!(Ok(24.0), time_scale_clap_parser("24"));
+        assert_eq!(Ok(0.5), time_scale_clap_parser("0.5"));
+        assert_eq!(
+            Err("Time scale FACTOR must be a number greater than 0, and up to 86400".to_string()),
+            time_scale_clap_parser("0")
+        );
+        assert!(time_scale_clap_parser("-1").is_err());
+        assert_eq!(Ok(86400.0), time_scale_clap_parser("86400"));
+        assert!(time_scale_clap_parser("86400.5").is_err());
+        assert!(time_scale_clap_parser("1e12").is_err());
+        assert!(time_scale_clap_parser("inf").is_err());
+        assert!(time_scale_clap_parser("NaN").is_err());
+        assert!(time_scale_clap_parser("fast").is_err());
+    }
+}
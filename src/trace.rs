@@ -0,0 +1,183 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::transition::Transition;
+use crate::workload::{is_valid_rate, WorkloadSegment};
+
+/// A recorded time series of rates (ex. exported from a production cluster), to replay as a workload.
+///
+/// It's made of points `(sec, rate)`, in strictly increasing order of seconds. Seconds are relative to
+/// the first point, so they can be absolute timestamps (ex. UNIX epoch seconds) as well.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateTrace {
+    points: Vec<(u64, f64)>,
+}
+
+/// A point of a JSON trace: either an object `{"sec": N, "rate": R}`, or an array `[N, R]`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonTracePoint {
+    Object {
+        sec: u64,
+        rate: f64,
+    },
+    Array(u64, f64),
+}
+
+impl RateTrace {
+    /// Creates a [`RateTrace`] from the given `(sec, rate)` points.
+    ///
+    /// In case of error, it returns a `String` describing the problem.
+    pub fn new(points: Vec<(u64, f64)>) -> Result<RateTrace, String> {
+        if points.is_empty() {
+            return Err("Rate trace must contain at least 1 point".to_string());
+        }
+        if let Some((sec, rate)) = points.iter().find(|(_, rate)| !is_valid_rate(*rate)) {
+            return Err(format!(
+                "Rate trace point at {sec} sec has invalid rate '{rate}': must be a non-negative number"
+            ));
+        }
+        if let Some(w) = points.windows(2).find(|w| w[0].0 >= w[1].0) {
+            return Err(format!(
+                "Rate trace points must be in increasing order of seconds: {} sec follows {} sec",
+                w[1].0, w[0].0
+            ));
+        }
+
+        Ok(RateTrace {
+            points,
+        })
+    }
+
+    /// Loads a [`RateTrace`] from the file at `path`.
+    ///
+    /// The format is determined by the file extension:
+    ///
+    /// * `.csv`: lines of `sec,rate` (an initial header line is ignored)
+    /// * `.json`: array of `{"sec": N, "rate": R}` objects, or of `[N, R]` arrays
+    ///
+    /// In case of error, it returns a `String` describing what went wrong.
+    pub fn from_file(path: &Path) -> Result<RateTrace, String> {
+        let content = match fs::read_to_string(path) {
+            Err(e) => return Err(format!("Failed to read rate trace file '{}': {e}", path.display())),
+            Ok(c) => c,
+        };
+
+        let points = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => RateTrace::parse_csv(&content),
+            Some("json") => RateTrace::parse_json(&content),
+            _ => Err(format!("Unsupported rate trace file '{}': expected extension '.csv' or '.json'", path.display())),
+        }
+        .map_err(|e| format!("Failed to parse rate trace file '{}': {e}", path.display()))?;
+
+        RateTrace::new(points)
+    }
+
+    fn parse_csv(content: &str) -> Result<Vec<(u64, f64)>, String> {
+        let mut reader =
+            csv::ReaderBuilder::new().has_headers(false).trim(csv::Trim::All).from_reader(content.as_bytes());
+
+        let mut points = Vec::new();
+        for (i, record) in reader.records().enumerate() {
+            let record = record.map_err(|e| e.to_string())?;
+            if record.len() != 2 {
+                return Err(format!("Line {} should have 'sec,rate' format", i + 1));
+            }
+
+            match (record[0].parse::<u64>(), record[1].parse::<f64>()) {
+                (Ok(sec), Ok(rate)) => points.push((sec, rate)),
+                // The first line can be a header
+                _ if i == 0 => {},
+                _ => return Err(format!("Line {} should have 'sec,rate' format", i + 1)),
+            }
+        }
+
+        Ok(points)
+    }
+
+    fn parse_json(content: &str) -> Result<Vec<(u64, f64)>, String> {
+        let points: Vec<JsonTracePoint> = serde_json::from_str(content).map_err(|e| e.to_string())?;
+
+        Ok(points
+            .into_iter()
+            .map(|p| match p {
+                JsonTracePoint::Object {
+                    sec,
+                    rate,
+                } => (sec, rate),
+                JsonTracePoint::Array(sec, rate) => (sec, rate),
+            })
+            .collect())
+    }
+
+    /// Converts the trace to the [`WorkloadSegment`]s that replay it.
+    ///
+    /// Each point is reached at its second, and between points the rate moves along the given [`Transition`]
+    /// (with [`Transition::None`], each rate is held until the next point). The last point lasts 1 second,
+    /// after which the workload loops back to the first point.
+    pub fn to_segments(&self, transition: Transition) -> Vec<WorkloadSegment> {
+        let mut segments = Vec::with_capacity(self.points.len());
+
+        for (i, (sec, rate)) in self.points.iter().enumerate() {
+            let next = self.points.get(i + 1);
+            let duration_sec = next.map_or(1, |(next_sec, _)| (next_sec - sec) as u32);
+
+            // A segment starts from the rate of the previous one: with a transition, the segment
+            // begins at this point, and reaches the next point (or loops back to the first)
+            let segment = match transition {
+                Transition::None => WorkloadSegment::new(*rate, duration_sec, Transition::None),
+                _ => WorkloadSegment::new(next.unwrap_or(&self.points[0]).1, duration_sec, transition),
+            };
+            segments.push(segment);
+        }
+
+        segments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::workload::{RateSchedule, Workload};
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let csv = "sec,rate\n1700000000,10\n1700000001, 12.5\n1700000005,0\n";
+        assert_eq!(vec![(1700000000, 10.0), (1700000001, 12.5), (1700000005, 0.0)], RateTrace::parse_csv(csv).unwrap());
+        assert!(RateTrace::parse_csv("0,10\n1,abc").is_err());
+
+        let json = r#"[{"sec": 0, "rate": 10}, [3, 20.5]]"#;
+        assert_eq!(vec![(0, 10.0), (3, 20.5)], RateTrace::parse_json(json).unwrap());
+        assert!(RateTrace::parse_json(r#"{"sec": 0}"#).is_err());
+
+        assert!(RateTrace::new(vec![]).is_err());
+        assert!(RateTrace::new(vec![(0, -1.0)]).is_err());
+        assert_eq!(
+            Err("Rate trace points must be in increasing order of seconds: 1 sec follows 5 sec".to_string()),
+            RateTrace::new(vec![(5, 1.0), (1, 2.0)])
+        );
+    }
+
+    #[test]
+    fn test_replay() {
+        let trace = RateTrace::new(vec![(100, 10.0), (101, 20.0), (105, 60.0)]).unwrap();
+
+        // Held until the next point
+        let w = Workload::from_segments(trace.to_segments(Transition::None));
+        assert_eq!(6, w.overall_duration_sec());
+        let rates = (0..7).map(|sec| w.records_per_sec_at(sec)).collect::<Vec<_>>();
+        assert_eq!(vec![10.0, 20.0, 20.0, 20.0, 20.0, 60.0, 10.0], rates);
+
+        // Interpolated between points
+        let w = Workload::from_segments(trace.to_segments(Transition::Linear));
+        assert_eq!(6, w.overall_duration_sec());
+        assert_eq!(10.0, w.records_per_sec_at(0));
+        assert_eq!(20.0, w.records_per_sec_at(1));
+        assert!(w.records_per_sec_at(2) > 20.0 && w.records_per_sec_at(4) < 60.0);
+        assert_eq!(60.0, w.records_per_sec_at(5));
+        assert_eq!(10.0, w.records_per_sec_at(6));
+    }
+}
@@ -1,5 +1,7 @@
-use clap::ValueEnum;
+use std::fmt;
+
 use flo_curves::{Coord2, Coordinate2D};
+use serde::{de, Deserialize, Deserializer};
 
 /// It represents a passing from the "min" phase to the "max" phase (i.e. "up" phase), or vice-versa (i.e. "down" phase).
 ///
@@ -15,9 +17,10 @@ use flo_curves::{Coord2, Coordinate2D};
 /// Note that the values of `P1` and `P2` in the documentation below are expressed at `t` values of a
 /// Bézier curve (i.e. `0 <= t <= 1`): they are mapped to the final control points,
 /// based on the bounding box of `P0` and `P3`.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub enum Transition {
     /// Immediate transition, with no in-between values.
+    #[default]
     None,
 
     /// Linear transition, constant increments between values.
@@ -75,9 +78,193 @@ pub enum Transition {
     /// * up:   P1 = (0,1) and P2 = (1,0)
     /// * down: P1 = (0,0) and P2 = (1,1)
     SpikeInOut,
+
+    /// User provided P1 and P2 control points, like a CSS `cubic-bezier(x1,y1,x2,y2)`.
+    ///
+    /// `x` is the progress in time, and `y` the progress in records/sec, from the beginning
+    /// of the transition (`0`) to its end (`1`): at any time, records/sec are the `y` of the point
+    /// of the curve at that `x`. The `y` of the "down" control points is mirrored:
+    ///
+    /// * up:   P1 = (x1,y1) and P2 = (x2,y2)
+    /// * down: P1 = (x1,1-y1) and P2 = (x2,1-y2)
+    Bezier(Coord2, Coord2),
+
+    /// Staircase of discrete steps: records/sec stay constant within each step, and jump at its beginning.
+    ///
+    /// The transition is split into `N` steps of equal duration: by the first step, records/sec already rise
+    /// (or fall) by `1/N` of the way, and reach the end of the transition with the last step.
+    /// Without easing, all the steps are of equal height; otherwise, step heights follow the
+    /// (named) transition used as easing, sampled at the end of each step.
+    Steps(u32, Option<&'static Transition>),
+
+    /// Exponential growth, with growth factor `K`: slow at the beginning, faster and faster until the end.
+    ///
+    /// Not a Bézier curve: given the progress in time `x` (i.e. `0 <= x <= 1`), the progress in records/sec is
+    /// `(e^(K*x) - 1) / (e^K - 1)`. The higher `K`, the more the growth is delayed towards the end.
+    Exponential(f64),
+
+    /// Logarithmic growth, with growth factor `K`: fast at the beginning, slower and slower until the end.
+    ///
+    /// Not a Bézier curve: given the progress in time `x` (i.e. `0 <= x <= 1`), the progress in records/sec is
+    /// `ln(1 + (e^K - 1) * x) / K` (the inverse of [`Transition::Exponential`]).
+    Logarithmic(f64),
+
+    /// Logistic S-curve, with steepness `S` and midpoint `M`: slow at both ends, fastest at `M`.
+    ///
+    /// Not a Bézier curve: given the progress in time `x` (i.e. `0 <= x <= 1`), the progress in records/sec is
+    /// `1 / (1 + e^(-S*(x-M)))`, rescaled so that it goes from `0` to `1` within the transition.
+    Sigmoid(f64, f64),
 }
 
+/// Highest growth factor (or steepness) of the parametric [`Transition`]s: beyond this, they are just a jump.
+const MAX_GROWTH: f64 = 100.;
+
+/// Names of the [`Transition`]s that don't require any parameter, in the format parsed by [`Transition::clap_parser`].
+static TRANSITION_NAMES: [(&str, Transition); 8] = [
+    ("none", Transition::None),
+    ("linear", Transition::Linear),
+    ("ease-in", Transition::EaseIn),
+    ("ease-out", Transition::EaseOut),
+    ("ease-in-out", Transition::EaseInOut),
+    ("spike-in", Transition::SpikeIn),
+    ("spike-out", Transition::SpikeOut),
+    ("spike-in-out", Transition::SpikeInOut),
+];
+
 impl Transition {
+    /// Implementation of [`clap::value_parser`], used to create an argument by parsing a user-provided value.
+    ///
+    /// The `&str` provided must be either the name of a transition (ex. `linear`, `ease-in`, ...),
+    /// or `bezier:X1,Y1,X2,Y2`, where `X1`, `Y1`, `X2` and `Y2` are parsed to `f64` coordinates
+    /// of the `P1` and `P2` control points. `X1` and `X2` must be within `[0..1]`: this guarantees that the
+    /// curve is monotonic in time (i.e. it never goes back in time), like for CSS `cubic-bezier()`.
+    ///
+    /// Stepped transitions are `steps:N[:EASING]`, where `N` is parsed to the `u32` number of steps
+    /// (greater than `0`), and `EASING` (optional) is the name of the transition step heights follow.
+    ///
+    /// Parametric transitions are `exp:K`, `log:K` and `sigmoid:S[:M]`, where the growth factor `K` and the
+    /// steepness `S` are parsed to `f64` within `(0..100]`, and the midpoint `M` to `f64` within `[0..1]`
+    /// (default: `0.5`).
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn clap_parser(transition_as_str: &str) -> Result<Transition, String> {
+        let ctrl_pts_str = match transition_as_str.split_once(':') {
+            None => {
+                return TRANSITION_NAMES
+                    .iter()
+                    .find(|(name, _)| *name == transition_as_str)
+                    .map(|(_, transition)| *transition)
+                    .ok_or_else(|| format!("Unsupported TRANSITION_TYPE '{transition_as_str}'"));
+            },
+            Some(("bezier", c)) => c,
+            Some(("steps", s)) => return Transition::steps_clap_parser(s),
+            Some(("exp", k)) => return parse_growth(k, "K", "exp:K").map(Transition::Exponential),
+            Some(("log", k)) => return parse_growth(k, "K", "log:K").map(Transition::Logarithmic),
+            Some(("sigmoid", s)) => return Transition::sigmoid_clap_parser(s),
+            Some((t, _)) => {
+                return Err(format!("Unsupported TRANSITION_TYPE '{t}:...'"));
+            },
+        };
+
+        let mut coords = Vec::with_capacity(4);
+        for c in ctrl_pts_str.split(',') {
+            match c.trim().parse::<f64>() {
+                Err(e) => return Err(format!("Failed to parse INPUT from 'bezier:X1,Y1,X2,Y2': {e}")),
+                Ok(v) if !v.is_finite() => return Err(format!("Invalid INPUT '{c}' in 'bezier:X1,Y1,X2,Y2'")),
+                Ok(v) => coords.push(v),
+            }
+        }
+        if coords.len() != 4 {
+            return Err("Should have 'bezier:X1,Y1,X2,Y2' format".to_string());
+        }
+
+        // With `X1` or `X2` outside of `[0..1]`, the curve could go back in time
+        let (x1, x2) = (coords[0], coords[2]);
+        if !(0. ..=1.).contains(&x1) || !(0. ..=1.).contains(&x2) {
+            return Err("INPUT 'X1' and 'X2' of 'bezier:X1,Y1,X2,Y2' must be within [0..1]".to_string());
+        }
+
+        Ok(Transition::Bezier(Coord2(x1, coords[1]), Coord2(x2, coords[3])))
+    }
+
+    /// Parses the `N[:EASING]` of a stepped transition `steps:N[:EASING]`.
+    fn steps_clap_parser(steps_as_str: &str) -> Result<Transition, String> {
+        let (steps, easing) = match steps_as_str.split_once(':') {
+            None => (steps_as_str, None),
+            Some((steps, easing)) => (steps, Some(easing)),
+        };
+
+        let steps = match steps.parse::<u32>() {
+            Err(e) => return Err(format!("Failed to parse INPUT 'N' from 'steps:N[:EASING]': {e}")),
+            Ok(0) => return Err("INPUT 'N' of 'steps:N[:EASING]' must be greater than 0".to_string()),
+            Ok(v) => v,
+        };
+
+        // Only named transitions that actually move can be used as easing
+        let easing = match easing {
+            None => None,
+            Some(e) => match TRANSITION_NAMES.iter().find(|(name, _)| *name == e) {
+                Some((_, Transition::None)) | None => {
+                    return Err(format!("Unsupported EASING '{e}' in 'steps:N[:EASING]'"));
+                },
+                Some((_, transition)) => Some(transition),
+            },
+        };
+
+        Ok(Transition::Steps(steps, easing))
+    }
+
+    /// Parses the `S[:M]` of a sigmoid transition `sigmoid:S[:M]`.
+    fn sigmoid_clap_parser(sigmoid_as_str: &str) -> Result<Transition, String> {
+        let (steepness, midpoint) = match sigmoid_as_str.split_once(':') {
+            None => (sigmoid_as_str, None),
+            Some((steepness, midpoint)) => (steepness, Some(midpoint)),
+        };
+
+        let steepness = parse_growth(steepness, "S", "sigmoid:S[:M]")?;
+        let midpoint = match midpoint.map(|m| m.parse::<f64>()) {
+            None => 0.5,
+            Some(Err(e)) => return Err(format!("Failed to parse INPUT 'M' from 'sigmoid:S[:M]': {e}")),
+            Some(Ok(v)) if !(0. ..=1.).contains(&v) => {
+                return Err("INPUT 'M' of 'sigmoid:S[:M]' must be within [0..1]".to_string());
+            },
+            Some(Ok(v)) => v,
+        };
+
+        Ok(Transition::Sigmoid(steepness, midpoint))
+    }
+
+    /// For the parametric transitions, given the progress in time `x` (i.e. `0 <= x <= 1`), returns the progress
+    /// in records/sec, from `0` at the beginning of the transition to `1` at its end.
+    ///
+    /// It's the same for the "up" and "down" phases: "down" moves from the higher to the lower records/sec.
+    /// For the transitions described by Bézier curves, it returns `None`.
+    pub fn progress_at(&self, x: f64) -> Option<f64> {
+        match *self {
+            Transition::Exponential(k) => Some((k * x).exp_m1() / k.exp_m1()),
+            Transition::Logarithmic(k) => Some((k.exp_m1() * x).ln_1p() / k),
+            Transition::Sigmoid(s, m) => {
+                let logistic = |x: f64| 1. / (1. + (-s * (x - m)).exp());
+                Some((logistic(x) - logistic(0.)) / (logistic(1.) - logistic(0.)))
+            },
+            _ => None,
+        }
+    }
+
+    /// Maps the progress `t` of the transition (i.e. `0 <= t <= 1`) to the `t` its records/sec are evaluated at.
+    ///
+    /// For [`Transition::Steps`], it's the end of the step `t` falls into, so that records/sec stay constant
+    /// within each step. For any other transition, it's `t` itself.
+    pub fn step_t(&self, t: f64) -> f64 {
+        match *self {
+            Transition::Steps(steps, _) => {
+                let steps = steps as f64;
+                ((t * steps).floor() + 1.).min(steps) / steps
+            },
+            _ => t,
+        }
+    }
+
     pub fn ctrl_pts_up(&self, p0: Coord2, p3: Coord2) -> Option<(Coord2, Coord2)> {
         let (p1_t, p2_t) = match *self {
             Transition::None => {
@@ -93,6 +280,16 @@ impl Transition {
             Transition::SpikeIn => (Coord2(0., 1.), Coord2(0., 1.)),
             Transition::SpikeOut => (Coord2(1., 0.), Coord2(1., 0.)),
             Transition::SpikeInOut => (Coord2(0., 1.), Coord2(1., 0.)),
+
+            Transition::Bezier(p1, p2) => (p1, p2),
+
+            // Without easing, control points a third of the way apart make the curve a straight line
+            Transition::Steps(_, None) => (Coord2(1. / 3., 1. / 3.), Coord2(2. / 3., 2. / 3.)),
+            Transition::Steps(_, Some(easing)) => return easing.ctrl_pts_up(p0, p3),
+
+            Transition::Exponential(_) | Transition::Logarithmic(_) | Transition::Sigmoid(..) => {
+                return None;
+            },
         };
 
         Some(map_p1t_p2t_to_p0_p3(p0, p3, p1_t, p2_t))
@@ -113,12 +310,60 @@ impl Transition {
             Transition::SpikeIn => (Coord2(1., 1.), Coord2(1., 1.)),
             Transition::SpikeOut => (Coord2(0., 0.), Coord2(0., 0.)),
             Transition::SpikeInOut => (Coord2(0., 0.), Coord2(1., 1.)),
+
+            Transition::Bezier(p1, p2) => (Coord2(p1.x(), 1. - p1.y()), Coord2(p2.x(), 1. - p2.y())),
+
+            Transition::Steps(_, None) => (Coord2(1. / 3., 2. / 3.), Coord2(2. / 3., 1. / 3.)),
+            Transition::Steps(_, Some(easing)) => return easing.ctrl_pts_down(p0, p3),
+
+            Transition::Exponential(_) | Transition::Logarithmic(_) | Transition::Sigmoid(..) => {
+                return None;
+            },
         };
 
         Some(map_p1t_p2t_to_p0_p3(p0, p3, p1_t, p2_t))
     }
 }
 
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transition::Bezier(p1, p2) => write!(f, "bezier:{},{},{},{}", p1.x(), p1.y(), p2.x(), p2.y()),
+            Transition::Steps(steps, None) => write!(f, "steps:{steps}"),
+            Transition::Steps(steps, Some(easing)) => write!(f, "steps:{steps}:{easing}"),
+            Transition::Exponential(k) => write!(f, "exp:{k}"),
+            Transition::Logarithmic(k) => write!(f, "log:{k}"),
+            Transition::Sigmoid(s, m) => write!(f, "sigmoid:{s}:{m}"),
+            _ => {
+                let (name, _) = TRANSITION_NAMES.iter().find(|(_, transition)| transition == self).unwrap();
+                write!(f, "{name}")
+            },
+        }
+    }
+}
+
+/// A [`Transition`] is deserialized from the same format parsed by [`Transition::clap_parser`].
+impl<'de> Deserialize<'de> for Transition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let transition_as_str = String::deserialize(deserializer)?;
+
+        Transition::clap_parser(&transition_as_str).map_err(de::Error::custom)
+    }
+}
+
+/// Parses the growth factor (or steepness) `input` of a parametric [`Transition`]: a `f64` within `(0..100]`.
+///
+/// `name` and `format` are used to describe the input in case of error.
+fn parse_growth(input: &str, name: &str, format: &str) -> Result<f64, String> {
+    match input.parse::<f64>() {
+        Err(e) => Err(format!("Failed to parse INPUT '{name}' from '{format}': {e}")),
+        Ok(v) if !(v > 0. && v <= MAX_GROWTH) => {
+            Err(format!("INPUT '{name}' of '{format}' must be within (0..{MAX_GROWTH}]"))
+        },
+        Ok(v) => Ok(v),
+    }
+}
+
 /// Find the control points `P1` and `P2`, between `P0` and `P3`, using the `t` value of `P1` and `P2`.
 fn map_p1t_p2t_to_p0_p3(p0: Coord2, p3: Coord2, p1_t: Coord2, p2_t: Coord2) -> (Coord2, Coord2) {
     (
@@ -222,4 +467,119 @@ mod tests {
         assert_eq!(p1, Coord2(11., 3.));
         assert_eq!(p2, Coord2(35., 50.));
     }
+
+    #[test]
+    fn test_bezier() {
+        let t = Transition::Bezier(Coord2(0.25, 0.1), Coord2(0.25, 1.));
+
+        let (p1, p2) = t.ctrl_pts_up(Coord2(11., 3.), Coord2(35., 53.)).unwrap();
+        assert_eq!(p1, Coord2(17., 8.));
+        assert_eq!(p2, Coord2(17., 53.));
+
+        let (p1, p2) = t.ctrl_pts_down(Coord2(11., 53.), Coord2(35., 3.)).unwrap();
+        assert_eq!(p1, Coord2(17., 48.));
+        assert_eq!(p2, Coord2(17., 3.));
+    }
+
+    #[test]
+    fn test_steps() {
+        // Without easing, a straight line
+        let (p1, p2) = Transition::Steps(4, None).ctrl_pts_up(Coord2(0., 3.), Coord2(30., 33.)).unwrap();
+        assert_eq!(p1, Coord2(10., 13.));
+        assert_eq!(p2, Coord2(20., 23.));
+
+        // With easing, the control points of the easing
+        let ease_in = Transition::Steps(4, Some(&Transition::EaseIn));
+        assert_eq!(
+            Transition::EaseIn.ctrl_pts_down(Coord2(1., 44.), Coord2(32., 2.)),
+            ease_in.ctrl_pts_down(Coord2(1., 44.), Coord2(32., 2.))
+        );
+
+        // Constant within each step, reaching the end with the last one
+        let t = (0..8).map(|i| Transition::Steps(4, None).step_t(i as f64 / 8.)).collect::<Vec<_>>();
+        assert_eq!(vec![0.25, 0.25, 0.5, 0.5, 0.75, 0.75, 1., 1.], t);
+        assert_eq!(1., Transition::Steps(4, None).step_t(1.));
+        assert_eq!(0.3, Transition::Linear.step_t(0.3));
+    }
+
+    #[test]
+    fn test_parametric() {
+        for t in [Transition::Exponential(3.), Transition::Logarithmic(3.), Transition::Sigmoid(10., 0.3)] {
+            assert_eq!(t.ctrl_pts_up(Coord2(0., 0.), Coord2(1., 1.)), None);
+
+            // From 0 to 1, always increasing
+            assert!(t.progress_at(0.).unwrap().abs() < 1e-9);
+            assert!((t.progress_at(1.).unwrap() - 1.).abs() < 1e-9);
+            let progress = (0..=10).map(|x| t.progress_at(x as f64 / 10.).unwrap()).collect::<Vec<_>>();
+            assert!(progress.windows(2).all(|p| p[1] > p[0]));
+        }
+
+        // Slow then fast, fast then slow, and fastest at the midpoint
+        assert!(Transition::Exponential(3.).progress_at(0.5).unwrap() < 0.2);
+        assert!(Transition::Logarithmic(3.).progress_at(0.5).unwrap() > 0.75);
+        assert!((Transition::Sigmoid(10., 0.5).progress_at(0.5).unwrap() - 0.5).abs() < 1e-9);
+        assert_eq!(None, Transition::Linear.progress_at(0.5));
+    }
+
+    #[test]
+    fn test_clap_parser() {
+        assert_eq!(Ok(Transition::None), Transition::clap_parser("none"));
+        assert_eq!(Ok(Transition::SpikeInOut), Transition::clap_parser("spike-in-out"));
+        assert_eq!(
+            Ok(Transition::Bezier(Coord2(0.42, 0.), Coord2(0.58, 1.))),
+            Transition::clap_parser("bezier:0.42,0,0.58,1")
+        );
+        assert_eq!(
+            Ok(Transition::Bezier(Coord2(0.3, -0.5), Coord2(0.7, 1.5))),
+            Transition::clap_parser("bezier:0.3, -0.5, 0.7, 1.5")
+        );
+
+        assert_eq!(Ok(Transition::Steps(5, None)), Transition::clap_parser("steps:5"));
+        assert_eq!(Ok(Transition::Steps(3, Some(&Transition::EaseOut))), Transition::clap_parser("steps:3:ease-out"));
+
+        assert_eq!(Ok(Transition::Exponential(2.5)), Transition::clap_parser("exp:2.5"));
+        assert_eq!(Ok(Transition::Logarithmic(4.)), Transition::clap_parser("log:4"));
+        assert_eq!(Ok(Transition::Sigmoid(12., 0.5)), Transition::clap_parser("sigmoid:12"));
+        assert_eq!(Ok(Transition::Sigmoid(12., 0.2)), Transition::clap_parser("sigmoid:12:0.2"));
+
+        // Display produces the same format
+        for t in
+            ["ease-in-out", "bezier:0.42,0,0.58,1", "steps:5", "steps:3:ease-out", "exp:2.5", "log:4", "sigmoid:12:0.2"]
+        {
+            assert_eq!(t, Transition::clap_parser(t).unwrap().to_string());
+        }
+
+        assert_eq!(Err("Unsupported TRANSITION_TYPE 'wobbly'".to_string()), Transition::clap_parser("wobbly"));
+        assert_eq!(Err("Unsupported TRANSITION_TYPE 'wobbly:...'".to_string()), Transition::clap_parser("wobbly:1"));
+        assert_eq!(
+            Err("Should have 'bezier:X1,Y1,X2,Y2' format".to_string()),
+            Transition::clap_parser("bezier:0.1,0.2,0.3")
+        );
+        assert_eq!(
+            Err("Failed to parse INPUT from 'bezier:X1,Y1,X2,Y2': invalid float literal".to_string()),
+            Transition::clap_parser("bezier:a,b,c,d")
+        );
+        assert_eq!(
+            Err("INPUT 'X1' and 'X2' of 'bezier:X1,Y1,X2,Y2' must be within [0..1]".to_string()),
+            Transition::clap_parser("bezier:1.1,0,0.5,1")
+        );
+        assert!(Transition::clap_parser("bezier:0.5,0,-0.1,1").is_err());
+        assert_eq!(
+            Err("INPUT 'N' of 'steps:N[:EASING]' must be greater than 0".to_string()),
+            Transition::clap_parser("steps:0")
+        );
+        assert_eq!(
+            Err("Unsupported EASING 'none' in 'steps:N[:EASING]'".to_string()),
+            Transition::clap_parser("steps:2:none")
+        );
+        assert!(Transition::clap_parser("steps:2:steps:3").is_err());
+        assert!(Transition::clap_parser("steps:two").is_err());
+        assert_eq!(Err("INPUT 'K' of 'exp:K' must be within (0..100]".to_string()), Transition::clap_parser("exp:0"));
+        assert!(Transition::clap_parser("log:1000").is_err());
+        assert!(Transition::clap_parser("sigmoid:-1").is_err());
+        assert_eq!(
+            Err("INPUT 'M' of 'sigmoid:S[:M]' must be within [0..1]".to_string()),
+            Transition::clap_parser("sigmoid:10:1.5")
+        );
+    }
 }
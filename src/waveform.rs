@@ -0,0 +1,257 @@
+use std::f64::consts::PI;
+use std::fmt;
+
+use serde::{de, Deserialize, Deserializer};
+
+use crate::workload::{is_valid_rate, RateSchedule, WorkloadPhase};
+
+/// The shapes of a [`Waveform`], each oscillating in `[-1..1]` over a period.
+///
+/// All shapes but [`WaveformType::Square`] begin the period at `0`, going up.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum WaveformType {
+    /// Smooth oscillation.
+    Sine,
+
+    /// Constant increase to the top, then immediate drop to the bottom.
+    Sawtooth,
+
+    /// Top for the first half of the period, bottom for the second half.
+    Square,
+
+    /// Constant increase to the top, then constant decrease to the bottom.
+    Triangle,
+}
+
+const WAVEFORM_TYPE_NAMES: [(&str, WaveformType); 4] = [
+    ("sine", WaveformType::Sine),
+    ("sawtooth", WaveformType::Sawtooth),
+    ("square", WaveformType::Square),
+    ("triangle", WaveformType::Triangle),
+];
+
+impl WaveformType {
+    /// Value of the wave at position `x` of the period, where `x` is in `[0..1)`.
+    fn value_at(&self, x: f64) -> f64 {
+        match self {
+            WaveformType::Sine => (2.0 * PI * x).sin(),
+            WaveformType::Sawtooth => 2.0 * (x + 0.5).fract() - 1.0,
+            WaveformType::Square => {
+                if x < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            },
+            WaveformType::Triangle => 1.0 - 2.0 * (2.0 * (x + 0.25).fract() - 1.0).abs(),
+        }
+    }
+}
+
+impl fmt::Display for WaveformType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = WAVEFORM_TYPE_NAMES.iter().find(|(_, t)| t == self).unwrap();
+        write!(f, "{name}")
+    }
+}
+
+/// A periodic analytic workload: the amount of records/sec oscillates around `offset`, by `amplitude`.
+///
+/// At second `t`, the records/sec are `offset + amplitude * wave((t + shift_sec) / period_sec)`, where
+/// `wave` is the [`WaveformType`]. Where `amplitude` exceeds `offset`, the records/sec stay at `0`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Waveform {
+    wave: WaveformType,
+    amplitude: f64,
+    offset: f64,
+    period_sec: u32,
+    shift_sec: u32,
+}
+
+impl Waveform {
+    pub fn new(wave: WaveformType, amplitude: f64, offset: f64, period_sec: u32, shift_sec: u32) -> Waveform {
+        Waveform {
+            wave,
+            amplitude,
+            offset,
+            period_sec,
+            shift_sec,
+        }
+    }
+
+    /// Implementation of [`clap::value_parser`], used to create an argument by parsing a user-provided value.
+    ///
+    /// The `&str` provided must be of format `TYPE:AMPLITUDE:OFFSET:PERIOD[:SHIFT]`:
+    ///
+    /// * `TYPE` is one of `sine`, `sawtooth`, `square` or `triangle`
+    /// * `AMPLITUDE` and `OFFSET` are parsed to non-negative `f64` amounts of records/sec
+    /// * `PERIOD` is parsed to `u32` duration of the period, in seconds (greater than `0`)
+    /// * `SHIFT` (optional) is parsed to `u32` seconds the wave is shifted by, defaulting to `0`
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn clap_parser(waveform_as_str: &str) -> Result<Waveform, String> {
+        const FORMAT: &str = "TYPE:AMPLITUDE:OFFSET:PERIOD[:SHIFT]";
+
+        let parts: Vec<&str> = waveform_as_str.split(':').collect();
+        if parts.len() != 4 && parts.len() != 5 {
+            return Err(format!("Should have '{FORMAT}' format"));
+        }
+
+        let wave = match WAVEFORM_TYPE_NAMES.iter().find(|(name, _)| *name == parts[0]) {
+            None => return Err(format!("Unsupported waveform TYPE '{}'", parts[0])),
+            Some((_, t)) => *t,
+        };
+
+        let parse_rate = |name: &str, s: &str| match s.parse::<f64>() {
+            Err(e) => Err(format!("Failed to parse '{name}' from '{FORMAT}': {e}")),
+            Ok(v) if !is_valid_rate(v) => Err(format!("'{name}' must be a non-negative number")),
+            Ok(v) => Ok(v),
+        };
+        let amplitude = parse_rate("AMPLITUDE", parts[1])?;
+        let offset = parse_rate("OFFSET", parts[2])?;
+
+        let period_sec = match parts[3].parse::<u32>() {
+            Err(e) => return Err(format!("Failed to parse 'PERIOD' from '{FORMAT}': {e}")),
+            Ok(0) => return Err("'PERIOD' must be greater than 0".to_string()),
+            Ok(v) => v,
+        };
+
+        let shift_sec = match parts.get(4).map(|s| s.parse::<u32>()) {
+            None => 0,
+            Some(Err(e)) => return Err(format!("Failed to parse 'SHIFT' from '{FORMAT}': {e}")),
+            Some(Ok(v)) => v,
+        };
+
+        Ok(Waveform::new(wave, amplitude, offset, period_sec, shift_sec))
+    }
+
+    /// Lowest amount of records/sec this waveform will ever indicate.
+    fn min_records_per_sec(&self) -> f64 {
+        (self.offset - self.amplitude).max(0.0)
+    }
+}
+
+impl RateSchedule for Waveform {
+    fn records_per_sec_at(&self, sec: u64) -> f64 {
+        let x = ((sec + self.shift_sec as u64) % self.period_sec as u64) as f64 / self.period_sec as f64;
+
+        (self.offset + self.amplitude * self.wave.value_at(x)).max(0.0)
+    }
+
+    /// At the top and bottom of the wave, it's [`WorkloadPhase::Max`] and [`WorkloadPhase::Min`]:
+    /// otherwise, it depends on where the wave is headed.
+    fn phase_at(&self, sec: u64) -> WorkloadPhase {
+        let curr = self.records_per_sec_at(sec);
+        let next = self.records_per_sec_at(sec + 1);
+
+        if curr == self.max_records_per_sec() {
+            WorkloadPhase::Max
+        } else if curr == self.min_records_per_sec() {
+            WorkloadPhase::Min
+        } else if next > curr {
+            WorkloadPhase::Up
+        } else if next < curr {
+            WorkloadPhase::Down
+        } else {
+            WorkloadPhase::Hold
+        }
+    }
+
+    fn max_records_per_sec(&self) -> f64 {
+        self.offset + self.amplitude
+    }
+
+    /// The cycle of a waveform is its period.
+    fn overall_duration_sec(&self) -> u32 {
+        self.period_sec
+    }
+}
+
+impl fmt::Display for Waveform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}:{}:{}", self.wave, self.amplitude, self.offset, self.period_sec, self.shift_sec)
+    }
+}
+
+/// A [`Waveform`] is deserialized from the same format parsed by [`Waveform::clap_parser`].
+impl<'de> Deserialize<'de> for Waveform {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let waveform_as_str = String::deserialize(deserializer)?;
+
+        Waveform::clap_parser(&waveform_as_str).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waves() {
+        let rates = |w: Waveform| (0..8).map(|sec| w.records_per_sec_at(sec).round()).collect::<Vec<_>>();
+
+        let sine = Waveform::new(WaveformType::Sine, 100.0, 200.0, 4, 0);
+        assert_eq!(vec![200.0, 300.0, 200.0, 100.0, 200.0, 300.0, 200.0, 100.0], rates(sine));
+        assert_eq!(4, sine.overall_duration_sec());
+        assert_eq!(300.0, sine.max_records_per_sec());
+
+        let sawtooth = Waveform::new(WaveformType::Sawtooth, 100.0, 200.0, 4, 0);
+        assert_eq!(vec![200.0, 250.0, 100.0, 150.0, 200.0, 250.0, 100.0, 150.0], rates(sawtooth));
+
+        let square = Waveform::new(WaveformType::Square, 100.0, 200.0, 4, 0);
+        assert_eq!(vec![300.0, 300.0, 100.0, 100.0, 300.0, 300.0, 100.0, 100.0], rates(square));
+
+        let triangle = Waveform::new(WaveformType::Triangle, 100.0, 200.0, 4, 0);
+        assert_eq!(vec![200.0, 300.0, 200.0, 100.0, 200.0, 300.0, 200.0, 100.0], rates(triangle));
+
+        // Shifted by a quarter of the period
+        let shifted = Waveform::new(WaveformType::Triangle, 100.0, 200.0, 4, 1);
+        assert_eq!(vec![300.0, 200.0, 100.0, 200.0, 300.0, 200.0, 100.0, 200.0], rates(shifted));
+
+        // Never below 0
+        let clipped = Waveform::new(WaveformType::Square, 100.0, 50.0, 4, 0);
+        assert_eq!(vec![150.0, 150.0, 0.0, 0.0, 150.0, 150.0, 0.0, 0.0], rates(clipped));
+    }
+
+    #[test]
+    fn test_phases() {
+        let triangle = Waveform::new(WaveformType::Triangle, 100.0, 200.0, 8, 0);
+        let phases = (0..8).map(|sec| triangle.phase_at(sec)).collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                WorkloadPhase::Up,
+                WorkloadPhase::Up,
+                WorkloadPhase::Max,
+                WorkloadPhase::Down,
+                WorkloadPhase::Down,
+                WorkloadPhase::Down,
+                WorkloadPhase::Min,
+                WorkloadPhase::Up
+            ],
+            phases
+        );
+    }
+
+    #[test]
+    fn test_clap_parser() {
+        assert_eq!(
+            Ok(Waveform::new(WaveformType::Sine, 100.0, 200.0, 600, 0)),
+            Waveform::clap_parser("sine:100:200:600")
+        );
+        assert_eq!(
+            Ok(Waveform::new(WaveformType::Square, 0.5, 1.0, 60, 15)),
+            Waveform::clap_parser("square:0.5:1:60:15")
+        );
+
+        // Display produces the same format
+        assert_eq!("sawtooth:10:20.5:30:5", Waveform::clap_parser("sawtooth:10:20.5:30:5").unwrap().to_string());
+
+        assert_eq!(Err("Unsupported waveform TYPE 'cosine'".to_string()), Waveform::clap_parser("cosine:1:1:1"));
+        assert_eq!(
+            Err("Should have 'TYPE:AMPLITUDE:OFFSET:PERIOD[:SHIFT]' format".to_string()),
+            Waveform::clap_parser("sine:1:1")
+        );
+        assert_eq!(Err("'PERIOD' must be greater than 0".to_string()), Waveform::clap_parser("sine:1:1:0"));
+        assert_eq!(Err("'AMPLITUDE' must be a non-negative number".to_string()), Waveform::clap_parser("sine:-1:1:10"));
+    }
+}
@@ -1,136 +1,309 @@
+use std::fmt::Debug;
+
 use flo_curves::bezier;
 use flo_curves::*;
+use serde::Deserialize;
 
 use crate::transition::Transition;
 
+/// The amount of "work" to do at any given second: the contract [`RecordsTap`] produces records by.
+///
+/// Time is measured in seconds since the beginning of the run, and the amount of work in records/sec
+/// (or bytes/sec, see [`ThroughputUnit`]). A schedule is cyclical: it repeats over and over, with a period
+/// of [`RateSchedule::overall_duration_sec`].
+///
+/// [`RecordsTap`]: crate::records_tap::RecordsTap
+/// [`ThroughputUnit`]: crate::records_tap::ThroughputUnit
+pub trait RateSchedule: Debug + Send + Sync {
+    /// Given the input `sec`, returns the number of records/sec that this schedule indicates.
+    ///
+    /// The value is never negative, but it can be fractional.
+    fn records_per_sec_at(&self, sec: u64) -> f64;
+
+    /// Given the input `sec`, informs of what [`WorkloadPhase`] that is at.
+    fn phase_at(&self, sec: u64) -> WorkloadPhase;
+
+    /// Highest amount of records/sec this schedule will ever indicate.
+    fn max_records_per_sec(&self) -> f64;
+
+    /// How long the entire "cycle" of the schedule lasts, in seconds.
+    fn overall_duration_sec(&self) -> u32;
+
+    /// Whether the schedule is followed only once, instead of repeating every cycle.
+    fn is_once(&self) -> bool {
+        false
+    }
+}
+
 /// It represents the amount of "work" to do, at any given time.
 /// Time itself is measured in seconds, from `0` to [`std::u64::MAX`].
 ///
-/// The amount of work is expressed as a `u32`, referring to the amount of records/sec,
-/// and it's retrieved via [`Workload::records_per_sec_at`].
+/// The amount of work is expressed as a `f64`, referring to the amount of records/sec,
+/// and it's retrieved via [`Workload::records_per_sec_at`]. It can be fractional (ex. `0.1`
+/// means 1 record every 10 seconds): it's up to the consumer of the workload to accumulate
+/// the fractions of records across seconds.
+///
+/// The workload is described by an ordered list of [`WorkloadSegment`]s: each segment reaches a
+/// target amount of records/sec, via a [`Transition`] that starts from the amount of records/sec
+/// of the previous segment, and lasts `duration_sec`.
 ///
-/// The workload goes through 4 phases:
+/// Given the input at construction time, the workload repeats over and over, with a period
+/// equivalent to the sum of the duration of all the segments: this means that
+/// after the last segment, the first one starts again.
+///
+/// The classic 4 phases cycle (see [`Workload::new`]) is a special case of this, made of the segments:
 ///
 /// * [`WorkloadPhase::Min`], lasting `min_sec`
 /// * [`WorkloadPhase::Up`], lasting `up_sec`
 /// * [`WorkloadPhase::Max`], lasting `max_sec`
 /// * [`WorkloadPhase::Down`], lasting `down_sec`
-///
-/// Given the input at construction time, the workload repeats over and over, with a period
-/// equivalent to the sum of `min_sec + max_sec + up_sec + down_sec`: this means that
-/// after the [`WorkloadPhase::Down`], the [`WorkloadPhase::Min`] starts again.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Workload {
-    /// Minimum amount of records per second
-    min: u32,
+    /// Segments of the workload, in the order they are traversed
+    segments: Vec<ScheduledSegment>,
 
-    /// How long to stay at minimum records/sec before ramp-up
-    min_sec: u32,
+    /// Highest amount of records per second, across all segments
+    max: f64,
+}
+
+/// A segment of a [`Workload`] schedule, as provided by the user.
+///
+/// It describes the amount of records/sec to reach, how long the segment lasts and the [`Transition`]
+/// used to get there from the amount of records/sec of the previous segment.
+/// With [`Transition::None`], the segment immediately jumps to `rate` and stays there for `duration_sec`.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkloadSegment {
+    /// Amount of records per second reached by the segment
+    rate: f64,
+
+    /// How long the segment lasts
+    #[serde(rename = "sec")]
+    duration_sec: u32,
+
+    /// Transition from the previous segment `rate` to this segment `rate`
+    #[serde(default)]
+    transition: Transition,
+}
 
-    /// Maximum amount of records per second
-    max: u32,
+/// A [`WorkloadSegment`], placed in the schedule of a [`Workload`].
+#[derive(Debug, Clone, PartialEq)]
+struct ScheduledSegment {
+    segment: WorkloadSegment,
+
+    /// When the segment begins, in seconds since the beginning of the cycle
+    start_sec: u32,
 
-    /// How long to stay at maximum records/sec, before ramp-down
-    max_sec: u32,
+    /// Shape of the transition into the segment.
+    /// Present if the segment [`Transition`] is not [`Transition::None`], and `rate` actually changes.
+    shape: Option<TransitionShape>,
 
-    /// Ramp-up transition duration
-    up_sec: u32,
+    /// The [`WorkloadPhase`] this segment represents
+    phase: WorkloadPhase,
+}
 
-    /// Bézier Curve describing the ramp-up transition.
-    /// Present if [`up_transition`] is not [`Transition::None`].
-    up_curve: Option<bezier::Curve<Coord2>>,
+/// How the records/sec of a [`ScheduledSegment`] move, during the transition into it.
+#[derive(Debug, Clone, PartialEq)]
+enum TransitionShape {
+    /// Bézier Curve describing the transition, from the previous segment `rate` to this segment `rate`.
+    Curve(bezier::Curve<Coord2>),
 
-    /// Ramp-down transition duration
-    down_sec: u32,
+    /// Like [`TransitionShape::Curve`], but the X of the curve is the time (like CSS `cubic-bezier()`):
+    /// the records/sec at a given second are the Y of the point of the curve at that X.
+    TimedCurve(bezier::Curve<Coord2>),
 
-    /// Bézier Curve describing the ramp-down" transition.
-    /// Present if [`down_transition`] is not [`Transition::None`].
-    down_curve: Option<bezier::Curve<Coord2>>,
+    /// Parametric transition (see [`Transition::progress_at`]), evaluated directly from the previous segment `rate`.
+    Parametric(f64),
 }
 
 /// Describes the phases that a [`Workload`] goes through cyclically.
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum WorkloadPhase {
+    /// Steady at the lowest amount of records/sec of the workload
     Min,
+
+    /// Increasing amount of records/sec
     Up,
+
+    /// Steady at the highest amount of records/sec of the workload
     Max,
+
+    /// Decreasing amount of records/sec
     Down,
+
+    /// Steady at an amount of records/sec between the lowest and the highest of the workload
+    Hold,
+}
+
+impl WorkloadSegment {
+    pub fn new(rate: f64, duration_sec: u32, transition: Transition) -> WorkloadSegment {
+        WorkloadSegment {
+            rate,
+            duration_sec,
+            transition,
+        }
+    }
+
+    /// Amount of records per second reached by the segment
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// How long the segment lasts, in seconds
+    pub fn duration_sec(&self) -> u32 {
+        self.duration_sec
+    }
+
+    /// Transition used to reach the segment `rate`
+    pub fn transition(&self) -> Transition {
+        self.transition
+    }
+
+    /// Implementation of [`clap::value_parser`], used to create an argument by parsing a user-provided value.
+    ///
+    /// The `&str` provided must be of format `REC/SEC:SEC[:TRANSITION_TYPE]`:
+    ///
+    /// * `REC/SEC` is parsed to `f64` amount of records/sec that the segment reaches (non-negative, can be fractional)
+    /// * `SEC` is parsed to `u32` duration of the segment, in seconds
+    /// * `TRANSITION_TYPE` (optional) is parsed to [`Transition`], defaulting to [`Transition::None`]
+    ///
+    /// In case of error, it returns a `String` that [`clap`] adds to the error message returned to the user.
+    pub fn clap_parser(segment_as_str: &str) -> Result<WorkloadSegment, String> {
+        let mut parts = segment_as_str.splitn(3, ':');
+
+        let rate = match parts.next().unwrap_or_default().parse::<f64>() {
+            Err(e) => return Err(format!("Failed to parse 'REC/SEC' from 'REC/SEC:SEC[:TRANSITION_TYPE]': {e}")),
+            Ok(v) if !is_valid_rate(v) => return Err("'REC/SEC' must be a non-negative number".to_string()),
+            Ok(v) => v,
+        };
+
+        let duration_sec = match parts.next() {
+            None => return Err("Should have 'REC/SEC:SEC[:TRANSITION_TYPE]' format".to_string()),
+            Some(s) => match s.parse::<u32>() {
+                Err(e) => return Err(format!("Failed to parse 'SEC' from 'REC/SEC:SEC[:TRANSITION_TYPE]': {e}")),
+                Ok(v) => v,
+            },
+        };
+
+        let transition = match parts.next() {
+            None => Transition::None,
+            Some(s) => Transition::clap_parser(s)
+                .map_err(|e| format!("Failed to parse 'TRANSITION_TYPE' from 'REC/SEC:SEC[:TRANSITION_TYPE]': {e}"))?,
+        };
+
+        if transition != Transition::None && duration_sec == 0 {
+            return Err("'SEC' must be greater than 0 when 'TRANSITION_TYPE' is not 'none'".to_string());
+        }
+
+        Ok(WorkloadSegment::new(rate, duration_sec, transition))
+    }
+}
+
+/// Returns `true` if `rate` is a valid amount of records/sec: finite and non-negative.
+pub fn is_valid_rate(rate: f64) -> bool {
+    rate.is_finite() && rate >= 0.0
 }
 
 impl Workload {
     #![allow(clippy::too_many_arguments)]
+    /// Creates the classic 4 phases [`Workload`]: `min`, `up`, `max` and `down`.
+    ///
+    /// If `up_transition` (or `down_transition`) is [`Transition::None`], the corresponding phase is skipped.
     pub fn new(
-        min: u32,
+        min: f64,
         min_sec: u32,
-        max: u32,
+        max: f64,
         max_sec: u32,
         up_transition: Transition,
         up_sec: u32,
         down_transition: Transition,
         down_sec: u32,
     ) -> Workload {
-        // P0/P3 for the "up" phase
-        let up_p0 = Coord2(min_sec as f64, min as f64);
-        let up_p3 = Coord2((min_sec + up_sec) as f64, max as f64);
-
-        // P0/P3 for the "down" phase
-        let down_p0 = Coord2((min_sec + up_sec + max_sec) as f64, max as f64);
-        let down_p3 = Coord2((min_sec + up_sec + max_sec + down_sec) as f64, min as f64);
-
-        // "up" phase duration and curve, depend on the corresponding Transition
-        let (up_sec, up_curve) = if up_transition != Transition::None {
-            (up_sec, Some(bezier::Curve::from_points(up_p0, up_transition.ctrl_pts_up(up_p0, up_p3).unwrap(), up_p3)))
-        } else {
-            (0, None)
-        };
-
-        // "down" phase duration and curve, depend on the corresponding Transition
-        let (down_sec, down_curve) = if down_transition != Transition::None {
-            (
-                down_sec,
-                Some(bezier::Curve::from_points(
-                    down_p0,
-                    down_transition.ctrl_pts_down(down_p0, down_p3).unwrap(),
-                    down_p3,
-                )),
-            )
-        } else {
-            (0, None)
-        };
-
-        Workload {
-            min,
-            min_sec,
-            max,
-            max_sec,
-            up_sec,
-            up_curve,
-            down_sec,
-            down_curve,
+        let mut segments = vec![WorkloadSegment::new(min, min_sec, Transition::None)];
+        if up_transition != Transition::None {
+            segments.push(WorkloadSegment::new(max, up_sec, up_transition));
+        }
+        segments.push(WorkloadSegment::new(max, max_sec, Transition::None));
+        if down_transition != Transition::None {
+            segments.push(WorkloadSegment::new(min, down_sec, down_transition));
         }
-    }
 
-    /// How long the [`WorkloadPhase::Min`] lasts, in seconds
-    pub fn min_duration_sec(&self) -> u32 {
-        self.min_sec
+        Workload::from_segments(segments)
     }
 
-    /// How long before the [`WorkloadPhase::Max`] starts, in seconds
-    pub fn before_max_duration_sec(&self) -> u32 {
-        self.min_sec + self.up_sec
-    }
+    /// Creates a [`Workload`] that traverses the given `segments` in order, and loops back
+    /// to the first after the last.
+    ///
+    /// Segments lasting `0` seconds are ignored. The first segment transitions from the `rate`
+    /// of the last one, as the workload repeats in a loop.
+    pub fn from_segments(segments: Vec<WorkloadSegment>) -> Workload {
+        let segments: Vec<WorkloadSegment> = segments.into_iter().filter(|s| s.duration_sec > 0).collect();
+        assert!(!segments.is_empty(), "Workload requires at least 1 segment lasting more than 0 seconds");
+
+        let min = segments.iter().map(|s| s.rate).fold(f64::INFINITY, f64::min);
+        let max = segments.iter().map(|s| s.rate).fold(0f64, f64::max);
+        // Custom transitions can overshoot the highest segment `rate`
+        let mut max_overshoot = max;
+
+        let mut scheduled = Vec::with_capacity(segments.len());
+        let mut start_sec = 0;
+        for (i, segment) in segments.iter().enumerate() {
+            // The segment starts from where the previous one ended: the first segment follows the last one
+            let prev_rate = segments[(i + segments.len() - 1) % segments.len()].rate;
+
+            // P0/P3 for the transition into the segment
+            let p0 = Coord2(start_sec as f64, prev_rate);
+            let p3 = Coord2((start_sec + segment.duration_sec) as f64, segment.rate);
+
+            // Shape and phase depend on the Transition, and the direction it goes to
+            let (shape, phase) = if segment.transition == Transition::None || segment.rate == prev_rate {
+                let phase = if segment.rate == min {
+                    WorkloadPhase::Min
+                } else if segment.rate == max {
+                    WorkloadPhase::Max
+                } else {
+                    WorkloadPhase::Hold
+                };
+                (None, phase)
+            } else {
+                let (ctrl_pts, phase) = if segment.rate > prev_rate {
+                    (segment.transition.ctrl_pts_up(p0, p3), WorkloadPhase::Up)
+                } else {
+                    (segment.transition.ctrl_pts_down(p0, p3), WorkloadPhase::Down)
+                };
+
+                // Transitions without control points are parametric
+                let shape = match ctrl_pts {
+                    Some(ctrl_pts) if matches!(segment.transition, Transition::Bezier(..)) => {
+                        let curve = bezier::Curve::from_points(p0, ctrl_pts, p3);
+                        let bounds: Bounds<Coord2> = curve.bounding_box();
+                        max_overshoot = max_overshoot.max(bounds.max().y());
+                        TransitionShape::TimedCurve(curve)
+                    },
+                    Some(ctrl_pts) => TransitionShape::Curve(bezier::Curve::from_points(p0, ctrl_pts, p3)),
+                    None => TransitionShape::Parametric(prev_rate),
+                };
+                (Some(shape), phase)
+            };
+
+            scheduled.push(ScheduledSegment {
+                segment: *segment,
+                start_sec,
+                shape,
+                phase,
+            });
+            start_sec += segment.duration_sec;
+        }
 
-    /// How long before the [`WorkloadPhase::Max`] ends, in seconds
-    pub fn after_max_duration_sec(&self) -> u32 {
-        self.min_sec + self.up_sec + self.max_sec
+        Workload {
+            segments: scheduled,
+            max: max_overshoot,
+        }
     }
 
-    /// How long before the [`WorkloadPhase::Down`] ends, in seconds.
-    ///
-    /// This represents also the entire length of a "cycle" of workload:
-    /// after [`WorkloadPhase::Down`] ends, the [`WorkloadPhase::Min`] starts agai.
-    pub fn overall_duration_sec(&self) -> u32 {
-        self.min_sec + self.up_sec + self.max_sec + self.down_sec
+    /// The [`WorkloadSegment`]s that compose this workload, in order.
+    pub fn segments(&self) -> impl Iterator<Item = &WorkloadSegment> {
+        self.segments.iter().map(|s| &s.segment)
     }
 
     /// Normalizes the input `sec` from absolute to relative.
@@ -143,49 +316,80 @@ impl Workload {
         (sec % self.overall_duration_sec() as u64) as u32
     }
 
-    /// Given the input `sec`, informs of what [`WorkloadPhase`] that is at.
-    pub fn phase_at(&self, sec: u64) -> WorkloadPhase {
-        let nor_sec = self.normalize_sec(sec);
+    /// Given the normalized `nor_sec`, returns the [`ScheduledSegment`] it falls into.
+    fn segment_at(&self, nor_sec: u32) -> &ScheduledSegment {
+        // Index of the first segment that starts after `nor_sec`: we need the one before it
+        let idx = self.segments.partition_point(|s| s.start_sec <= nor_sec);
+        &self.segments[idx - 1]
+    }
+}
 
-        if nor_sec < self.min_duration_sec() {
-            WorkloadPhase::Min
-        } else if nor_sec < self.before_max_duration_sec() {
-            WorkloadPhase::Up
-        } else if nor_sec < self.after_max_duration_sec() {
-            WorkloadPhase::Max
-        } else {
-            WorkloadPhase::Down
-        }
+impl RateSchedule for Workload {
+    fn max_records_per_sec(&self) -> f64 {
+        self.max
+    }
+
+    /// After the last segment ends, the first starts again.
+    fn overall_duration_sec(&self) -> u32 {
+        let last = self.segments.last().unwrap();
+        last.start_sec + last.segment.duration_sec
+    }
+
+    fn phase_at(&self, sec: u64) -> WorkloadPhase {
+        self.segment_at(self.normalize_sec(sec)).phase.clone()
     }
 
-    /// Given the input `sec`, returns the number of records/sec that this `Workload` indicates.
-    ///
     /// The [`WorkloadPhase`]s repeat in a loop, so it takes any absolute seconds input, and
     /// returns the amount of records/sec for that moment in time.
-    pub fn records_per_sec_at(&self, sec: u64) -> u32 {
+    fn records_per_sec_at(&self, sec: u64) -> f64 {
         let nor_sec = self.normalize_sec(sec);
+        let scheduled = self.segment_at(nor_sec);
 
-        match self.phase_at(sec) {
-            WorkloadPhase::Min => self.min,
-            WorkloadPhase::Up => {
-                // The corresponding Bézier `t` for `nor_sec` during "up" phase
-                let nor_sec_t = (nor_sec - self.min_duration_sec()) as f64 / self.up_sec as f64;
+        let Some(shape) = &scheduled.shape else {
+            return scheduled.segment.rate;
+        };
 
-                // Return the corresponding Y (amount of records per second) give `t` as X
-                self.up_curve.unwrap().point_at_pos(nor_sec_t).y().round() as u32
+        // The corresponding `t` for `nor_sec` during the segment (at the end of its step, if stepped)
+        let nor_sec_t = (nor_sec - scheduled.start_sec) as f64 / scheduled.segment.duration_sec as f64;
+        let nor_sec_t = scheduled.segment.transition.step_t(nor_sec_t);
+
+        match shape {
+            // Return the corresponding Y (amount of records per second) give `t` as X:
+            // custom transitions can overshoot, but never below 0 records
+            TransitionShape::Curve(curve) => curve.point_at_pos(nor_sec_t).y().max(0.0),
+            TransitionShape::TimedCurve(curve) => {
+                let x = scheduled.start_sec as f64 + nor_sec_t * scheduled.segment.duration_sec as f64;
+                curve.point_at_pos(t_at_x(curve, x)).y().max(0.0)
             },
-            WorkloadPhase::Max => self.max,
-            WorkloadPhase::Down => {
-                // The corresponding Bézier `t` for `nor_sec` during "down" phase
-                let nor_sec_t = (nor_sec - self.after_max_duration_sec()) as f64 / self.down_sec as f64;
-
-                // Return the corresponding Y (amount of records per second) give `t` as X
-                self.down_curve.unwrap().point_at_pos(nor_sec_t).y().round() as u32
+            TransitionShape::Parametric(prev_rate) => {
+                // Valid, as only parametric transitions have no control points
+                let progress = scheduled.segment.transition.progress_at(nor_sec_t).unwrap();
+                prev_rate + (scheduled.segment.rate - prev_rate) * progress
             },
         }
     }
 }
 
+/// Returns the `t` at which the `curve` reaches the given `x`.
+///
+/// Valid for curves whose X grows with `t` (i.e. control points with X within the X of the end points),
+/// as it searches `t` by bisection.
+fn t_at_x(curve: &bezier::Curve<Coord2>, x: f64) -> f64 {
+    let (mut low, mut high) = (0f64, 1f64);
+
+    // Each iteration halves the interval: 50 are well within the precision of a `f64`
+    for _ in 0..50 {
+        let mid = (low + high) / 2.;
+        if curve.point_at_pos(mid).x() < x {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+
+    (low + high) / 2.
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -196,49 +400,49 @@ mod tests {
 
     #[test]
     fn test_up_linear_down_none() {
-        let w = Workload::new(1, 20, 100, 5, Transition::Linear, 3, Transition::None, 0);
+        let w = Workload::new(1.0, 20, 100.0, 5, Transition::Linear, 3, Transition::None, 0);
 
         // min_sec=20 + up_sec=3 + max_sec=5 + down_sec=0
         assert_eq!(28, w.overall_duration_sec());
 
         // min at 0-19
         for sec in 0u64..=19u64 {
-            assert_eq!(1, w.records_per_sec_at(sec));
+            assert_eq!(1.0, w.records_per_sec_at(sec).round());
         }
 
         // up at 20-22
-        assert_eq!(1, w.records_per_sec_at(20));
-        assert_eq!(27, w.records_per_sec_at(21));
-        assert_eq!(74, w.records_per_sec_at(22));
+        assert_eq!(1.0, w.records_per_sec_at(20).round());
+        assert_eq!(27.0, w.records_per_sec_at(21).round());
+        assert_eq!(74.0, w.records_per_sec_at(22).round());
 
         // max at 23-27
         for sec in 23u64..=27u64 {
-            assert_eq!(100, w.records_per_sec_at(sec));
+            assert_eq!(100.0, w.records_per_sec_at(sec).round());
         }
 
         // down_sec is 0, so it's time for min again
         for sec in 28u64..=47u64 {
-            assert_eq!(1, w.records_per_sec_at(sec));
+            assert_eq!(1.0, w.records_per_sec_at(sec).round());
         }
 
         // up at 48-50
-        assert_eq!(1, w.records_per_sec_at(48));
-        assert_eq!(27, w.records_per_sec_at(49));
-        assert_eq!(74, w.records_per_sec_at(50));
+        assert_eq!(1.0, w.records_per_sec_at(48).round());
+        assert_eq!(27.0, w.records_per_sec_at(49).round());
+        assert_eq!(74.0, w.records_per_sec_at(50).round());
 
         // max at 51-55
         for sec in 51u64..=55u64 {
-            assert_eq!(100, w.records_per_sec_at(sec));
+            assert_eq!(100.0, w.records_per_sec_at(sec).round());
         }
     }
 
     #[test]
     fn test_up_spike_out_down_ease_in() {
-        let w = Workload::new(3, 60, 100, 5, Transition::SpikeOut, 20, Transition::EaseIn, 20);
+        let w = Workload::new(3.0, 60, 100.0, 5, Transition::SpikeOut, 20, Transition::EaseIn, 20);
 
         let mut occurrences = HashMap::new();
 
-        let mut prev = 0;
+        let mut prev = 0.0;
         for sec in 0u64..(w.overall_duration_sec() as u64 * 10u64) {
             let curr = w.records_per_sec_at(sec);
 
@@ -249,17 +453,18 @@ mod tests {
             // Given the phase, check that the behaviour is what we expect
             match sec_phase {
                 Min => {
-                    assert_eq!(curr, 3);
+                    assert_eq!(curr, 3.0);
                 },
                 Up => {
                     assert!(curr >= prev);
                 },
                 Max => {
-                    assert_eq!(curr, 100);
+                    assert_eq!(curr, 100.0);
                 },
                 Down => {
                     assert!(curr <= prev);
                 },
+                Hold => unreachable!("a 4 phases workload never holds between min and max"),
             }
 
             prev = curr;
@@ -271,4 +476,166 @@ mod tests {
         assert_eq!(50, occurrences.get(&Max).cloned().unwrap());
         assert_eq!(200, occurrences.get(&Down).cloned().unwrap());
     }
+
+    #[test]
+    fn test_segments() {
+        let w = Workload::from_segments(vec![
+            WorkloadSegment::new(10.0, 10, Transition::None),
+            WorkloadSegment::new(50.0, 5, Transition::Linear),
+            WorkloadSegment::new(50.0, 10, Transition::None),
+            WorkloadSegment::new(1000.0, 0, Transition::None),
+            WorkloadSegment::new(500.0, 3, Transition::None),
+            WorkloadSegment::new(10.0, 4, Transition::EaseIn),
+        ]);
+
+        // The segment lasting 0 seconds is ignored
+        assert_eq!(5, w.segments().count());
+        assert_eq!(32, w.overall_duration_sec());
+        assert_eq!(500.0, w.max_records_per_sec());
+
+        for sec in 0u64..10u64 {
+            assert_eq!(Min, w.phase_at(sec));
+            assert_eq!(10.0, w.records_per_sec_at(sec).round());
+        }
+
+        // Transition from the previous segment (10) to 50
+        assert_eq!(Up, w.phase_at(10));
+        assert_eq!(10.0, w.records_per_sec_at(10).round());
+        assert!(w.records_per_sec_at(14) > w.records_per_sec_at(12));
+
+        // Plateau in between min and max
+        for sec in 15u64..25u64 {
+            assert_eq!(Hold, w.phase_at(sec));
+            assert_eq!(50.0, w.records_per_sec_at(sec).round());
+        }
+
+        // Spike without transition
+        for sec in 25u64..28u64 {
+            assert_eq!(Max, w.phase_at(sec));
+            assert_eq!(500.0, w.records_per_sec_at(sec).round());
+        }
+
+        // Decay back to the first segment
+        assert_eq!(Down, w.phase_at(28));
+        assert_eq!(500.0, w.records_per_sec_at(28).round());
+        assert!(w.records_per_sec_at(31) < w.records_per_sec_at(29));
+
+        // Then it loops
+        assert_eq!(Min, w.phase_at(32));
+        assert_eq!(10.0, w.records_per_sec_at(32).round());
+    }
+
+    #[test]
+    fn test_custom_bezier() {
+        let workload = |transition: &str| {
+            let transition = Transition::clap_parser(transition).unwrap();
+            Workload::new(0.0, 10, 100.0, 10, transition, 100, Transition::None, 0)
+        };
+
+        // Same Y of the control points, but different X: the X is the progress in time
+        let early = workload("bezier:0.1,0.9,0.9,0.9");
+        let late = workload("bezier:0.9,0.9,0.1,0.9");
+        assert!(early.records_per_sec_at(20) > late.records_per_sec_at(20) + 10.0);
+        assert!(early.records_per_sec_at(100) < late.records_per_sec_at(100));
+        for w in [&early, &late] {
+            assert_eq!(0.0, w.records_per_sec_at(10).round());
+            assert_eq!(100.0, w.records_per_sec_at(110).round());
+        }
+
+        // Like CSS `cubic-bezier(0,0,1,1)`, a straight line
+        let linear = workload("bezier:0,0,1,1");
+        for sec in 10u64..110u64 {
+            assert!((linear.records_per_sec_at(sec) - (sec - 10) as f64).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn test_custom_bezier_overshoot() {
+        let transition = Transition::clap_parser("bezier:0.3,1.5,0.7,1.5").unwrap();
+        let w = Workload::new(0.0, 10, 100.0, 10, transition, 100, Transition::None, 0);
+
+        let highest = (0u64..120u64).map(|sec| w.records_per_sec_at(sec)).fold(0f64, f64::max);
+        assert!(highest > 100.0);
+        assert!(w.max_records_per_sec() >= highest);
+        // Y of the control points at 1.5, the curve peaks at 0.75 of the way above `max`
+        assert!(w.max_records_per_sec() < 100.0 * 1.5);
+    }
+
+    #[test]
+    fn test_segment_clap_parser() {
+        assert_eq!(Ok(WorkloadSegment::new(100.0, 60, Transition::None)), WorkloadSegment::clap_parser("100:60"));
+        assert_eq!(
+            Ok(WorkloadSegment::new(5000.0, 10, Transition::SpikeIn)),
+            WorkloadSegment::clap_parser("5000:10:spike-in")
+        );
+
+        assert_eq!(
+            Err("Should have 'REC/SEC:SEC[:TRANSITION_TYPE]' format".to_string()),
+            WorkloadSegment::clap_parser("100")
+        );
+        assert_eq!(
+            Err("Failed to parse 'SEC' from 'REC/SEC:SEC[:TRANSITION_TYPE]': invalid digit found in string".to_string()),
+            WorkloadSegment::clap_parser("100:abc")
+        );
+        assert_eq!(
+            Err("'SEC' must be greater than 0 when 'TRANSITION_TYPE' is not 'none'".to_string()),
+            WorkloadSegment::clap_parser("100:0:linear")
+        );
+        assert!(WorkloadSegment::clap_parser("100:10:wobbly").is_err());
+
+        assert_eq!(Ok(WorkloadSegment::new(0.1, 60, Transition::None)), WorkloadSegment::clap_parser("0.1:60"));
+        assert_eq!(Err("'REC/SEC' must be a non-negative number".to_string()), WorkloadSegment::clap_parser("-1:60"));
+    }
+
+    #[test]
+    fn test_fractional_rates() {
+        let w = Workload::new(0.1, 10, 2.0, 10, Transition::Linear, 10, Transition::None, 0);
+
+        assert_eq!(0.1, w.records_per_sec_at(0));
+        assert_eq!(2.0, w.max_records_per_sec());
+
+        // The ramp-up moves smoothly between fractional values
+        assert_eq!(0.1, w.records_per_sec_at(10));
+        for sec in 11u64..20u64 {
+            let (prev, curr) = (w.records_per_sec_at(sec - 1), w.records_per_sec_at(sec));
+            assert!(curr > prev && curr - prev < 0.5);
+        }
+        assert_eq!(2.0, w.records_per_sec_at(20));
+    }
+
+    #[test]
+    fn test_steps() {
+        let w = Workload::new(0.0, 2, 100.0, 2, Transition::Steps(4, None), 8, Transition::Steps(2, None), 4);
+
+        // Up in 4 steps of 2 seconds each, then down in 2 steps of 2 seconds each
+        let rates = (0..16).map(|sec| w.records_per_sec_at(sec).round()).collect::<Vec<_>>();
+        assert_eq!(
+            vec![0.0, 0.0, 25.0, 25.0, 50.0, 50.0, 75.0, 75.0, 100.0, 100.0, 100.0, 100.0, 50.0, 50.0, 0.0, 0.0],
+            rates
+        );
+        assert_eq!(Up, w.phase_at(9));
+        assert_eq!(Down, w.phase_at(14));
+
+        // With easing, step heights follow the easing curve
+        let w =
+            Workload::new(0.0, 1, 100.0, 1, Transition::Steps(4, Some(&Transition::SpikeIn)), 4, Transition::None, 0);
+        let rates = (1..5).map(|sec| w.records_per_sec_at(sec).round()).collect::<Vec<_>>();
+        assert_eq!(vec![58.0, 88.0, 98.0, 100.0], rates);
+    }
+
+    #[test]
+    fn test_parametric() {
+        let w = Workload::new(0.0, 1, 100.0, 1, Transition::Exponential(3.0), 4, Transition::Sigmoid(10.0, 0.5), 4);
+
+        // Up exponentially: slow at the beginning
+        let rates = (1..6).map(|sec| w.records_per_sec_at(sec).round()).collect::<Vec<_>>();
+        assert_eq!(vec![0.0, 6.0, 18.0, 44.0, 100.0], rates);
+        assert_eq!(Up, w.phase_at(2));
+
+        // Down along an S-curve: symmetric around the midpoint
+        let rates = (6..10).map(|sec| w.records_per_sec_at(sec).round()).collect::<Vec<_>>();
+        assert_eq!(vec![100.0, 93.0, 50.0, 7.0], rates);
+        assert_eq!(Down, w.phase_at(7));
+        assert_eq!(0.0, w.records_per_sec_at(10));
+    }
 }